@@ -0,0 +1,32 @@
+/// Shown over the terminal while a close waits for confirmation
+pub const PROMPT: &str = "A command is still running. Close anyway?  Enter: close  Esc: cancel";
+
+/// Whether closing should ask first: only when `general.confirm_close` is
+/// on and something could be lost, i.e. the shell is running a foreground
+/// job or another tab is open
+pub fn should_confirm_close(enabled: bool, foreground_job: bool, tabs: usize) -> bool {
+    enabled && (foreground_job || tabs > 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn idle_shell_closes_without_asking() {
+        assert!(!should_confirm_close(true, false, 1));
+        assert!(!should_confirm_close(false, false, 1));
+    }
+
+    #[test]
+    fn running_job_asks_only_when_enabled() {
+        assert!(should_confirm_close(true, true, 1));
+        assert!(!should_confirm_close(false, true, 1));
+    }
+
+    #[test]
+    fn other_tabs_count_as_something_to_lose() {
+        assert!(should_confirm_close(true, false, 2));
+        assert!(!should_confirm_close(false, true, 3));
+    }
+}
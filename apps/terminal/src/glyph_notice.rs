@@ -0,0 +1,64 @@
+use winit::keyboard::KeyCode;
+
+/// The one-time missing-glyph notice, shown over the terminal like the close
+/// confirmation; Enter opens the details behind it
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum GlyphNotice {
+    /// The short notice
+    Toast(String),
+    /// Top missing ranges and the config key to fix it
+    Details(String),
+}
+
+impl GlyphNotice {
+    /// Text for the overlay band
+    pub fn overlay_text(&self) -> String {
+        match self {
+            GlyphNotice::Toast(notice) => format!("{}  Enter: details  Esc: dismiss", notice),
+            GlyphNotice::Details(details) => format!("{}\nEnter/Esc: dismiss", details),
+        }
+    }
+
+    /// What is shown after `key`: Enter on the notice opens `details`, Enter
+    /// or Esc on the details and Esc on the notice dismiss it. Other keys
+    /// leave it up.
+    pub fn on_key(self, key: KeyCode, details: impl FnOnce() -> String) -> Option<Self> {
+        match (self, key) {
+            (GlyphNotice::Toast(_), KeyCode::Enter | KeyCode::NumpadEnter) => Some(GlyphNotice::Details(details())),
+            (_, KeyCode::Enter | KeyCode::NumpadEnter | KeyCode::Escape) => None,
+            (shown, _) => Some(shown),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn toast() -> GlyphNotice {
+        GlyphNotice::Toast("Your font is missing 8 symbols".to_string())
+    }
+
+    #[test]
+    fn enter_opens_the_details_then_dismisses() {
+        let details = toast().on_key(KeyCode::Enter, || "Powerline: 8".to_string());
+        assert_eq!(details, Some(GlyphNotice::Details("Powerline: 8".to_string())));
+        assert_eq!(details.unwrap().on_key(KeyCode::Enter, || unreachable!()), None);
+    }
+
+    #[test]
+    fn escape_dismisses_and_other_keys_leave_it_up() {
+        assert_eq!(toast().on_key(KeyCode::Escape, || unreachable!()), None);
+        assert_eq!(toast().on_key(KeyCode::KeyA, || unreachable!()), Some(toast()));
+        let details = GlyphNotice::Details("Braille: 9".to_string());
+        assert_eq!(details.clone().on_key(KeyCode::Space, || unreachable!()), Some(details.clone()));
+        assert_eq!(details.on_key(KeyCode::Escape, || unreachable!()), None);
+    }
+
+    #[test]
+    fn details_keep_every_line_on_the_overlay() {
+        let text = GlyphNotice::Details("Missing glyphs by range:\n  Braille: 9".to_string()).overlay_text();
+        assert_eq!(text.lines().count(), 3);
+        assert!(toast().overlay_text().contains("Enter: details"));
+    }
+}
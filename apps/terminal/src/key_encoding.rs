@@ -0,0 +1,187 @@
+use the_dev_terminal_core::keyboard::KITTY_DISAMBIGUATE;
+use winit::keyboard::{Key, KeyCode, ModifiersState, PhysicalKey};
+
+/// Kitty modifier parameter: 1 + (shift 1 | alt 2 | ctrl 4 | super 8)
+pub fn kitty_modifiers(mods: ModifiersState) -> u8 {
+    let mut bits = 0;
+    if mods.shift_key() {
+        bits |= 1;
+    }
+    if mods.alt_key() {
+        bits |= 2;
+    }
+    if mods.control_key() {
+        bits |= 4;
+    }
+    if mods.super_key() {
+        bits |= 8;
+    }
+    1 + bits
+}
+
+// CSI <code> [; mods] <final>, omitting the default modifier
+fn csi(code: u32, mods: u8, final_byte: char) -> Vec<u8> {
+    if mods > 1 {
+        format!("\x1b[{};{}{}", code, mods, final_byte).into_bytes()
+    } else {
+        format!("\x1b[{}{}", code, final_byte).into_bytes()
+    }
+}
+
+/// Encode a key press under the kitty keyboard protocol.
+/// Returns None when the protocol is off or the key keeps its legacy encoding.
+pub fn encode_kitty(flags: u8, key: PhysicalKey, logical: &Key, mods: ModifiersState) -> Option<Vec<u8>> {
+    if flags & KITTY_DISAMBIGUATE == 0 {
+        return None;
+    }
+    let m = kitty_modifiers(mods);
+    let PhysicalKey::Code(code) = key else {
+        return None;
+    };
+
+    match code {
+        // Escape is always disambiguated from the start of an escape sequence
+        KeyCode::Escape => return Some(csi(27, m, 'u')),
+        // Enter, Tab and Backspace stay legacy unless modified
+        KeyCode::Enter => return (m > 1).then(|| csi(13, m, 'u')),
+        KeyCode::Tab => return (m > 1).then(|| csi(9, m, 'u')),
+        KeyCode::Backspace => return (m > 1).then(|| csi(127, m, 'u')),
+        // Cursor keys keep their legacy finals, with modifiers as CSI 1 ; m X
+        KeyCode::ArrowUp => return (m > 1).then(|| csi(1, m, 'A')),
+        KeyCode::ArrowDown => return (m > 1).then(|| csi(1, m, 'B')),
+        KeyCode::ArrowRight => return (m > 1).then(|| csi(1, m, 'C')),
+        KeyCode::ArrowLeft => return (m > 1).then(|| csi(1, m, 'D')),
+        KeyCode::Home => return (m > 1).then(|| csi(1, m, 'H')),
+        KeyCode::End => return (m > 1).then(|| csi(1, m, 'F')),
+        KeyCode::PageUp => return (m > 1).then(|| csi(5, m, '~')),
+        KeyCode::PageDown => return (m > 1).then(|| csi(6, m, '~')),
+        _ => {}
+    }
+
+    // Text keys: plain or shifted text is sent as-is; anything with
+    // Ctrl/Alt/Super becomes CSI <unshifted codepoint> ; mods u
+    let only_shift = !(mods.control_key() || mods.alt_key() || mods.super_key());
+    if only_shift {
+        return None;
+    }
+    let ch = match logical {
+        Key::Character(s) => s.chars().next()?,
+        Key::Named(winit::keyboard::NamedKey::Space) => ' ',
+        _ => return None,
+    };
+    // Some platforms report Ctrl+letter as the control character itself
+    let ch = match ch as u32 {
+        1..=26 => (b'a' + ch as u8 - 1) as char,
+        _ => ch,
+    };
+    let base = ch.to_lowercase().next().unwrap_or(ch);
+    Some(csi(base as u32, m, 'u'))
+}
+
+/// Legacy bytes for the cursor and paging keys. While the app has set DECCKM
+/// (`app_cursor`), arrows, Home and End use SS3 (ESC O) instead of CSI;
+/// PageUp/PageDown are the same in both modes.
+pub fn cursor_key(code: KeyCode, app_cursor: bool) -> Option<&'static [u8]> {
+    let seq: &[u8] = match (code, app_cursor) {
+        (KeyCode::ArrowUp, false) => b"\x1b[A",
+        (KeyCode::ArrowDown, false) => b"\x1b[B",
+        (KeyCode::ArrowRight, false) => b"\x1b[C",
+        (KeyCode::ArrowLeft, false) => b"\x1b[D",
+        (KeyCode::Home, false) => b"\x1b[H",
+        (KeyCode::End, false) => b"\x1b[F",
+        (KeyCode::ArrowUp, true) => b"\x1bOA",
+        (KeyCode::ArrowDown, true) => b"\x1bOB",
+        (KeyCode::ArrowRight, true) => b"\x1bOC",
+        (KeyCode::ArrowLeft, true) => b"\x1bOD",
+        (KeyCode::Home, true) => b"\x1bOH",
+        (KeyCode::End, true) => b"\x1bOF",
+        (KeyCode::PageUp, _) => b"\x1b[5~",
+        (KeyCode::PageDown, _) => b"\x1b[6~",
+        _ => return None,
+    };
+    Some(seq)
+}
+
+const LETTER_KEYS: [KeyCode; 26] = [
+    KeyCode::KeyA, KeyCode::KeyB, KeyCode::KeyC, KeyCode::KeyD, KeyCode::KeyE,
+    KeyCode::KeyF, KeyCode::KeyG, KeyCode::KeyH, KeyCode::KeyI, KeyCode::KeyJ,
+    KeyCode::KeyK, KeyCode::KeyL, KeyCode::KeyM, KeyCode::KeyN, KeyCode::KeyO,
+    KeyCode::KeyP, KeyCode::KeyQ, KeyCode::KeyR, KeyCode::KeyS, KeyCode::KeyT,
+    KeyCode::KeyU, KeyCode::KeyV, KeyCode::KeyW, KeyCode::KeyX, KeyCode::KeyY,
+    KeyCode::KeyZ,
+];
+
+/// Control code for Ctrl+<letter> (0x01..=0x1A). The letter comes from the
+/// layout's logical character, so Ctrl+C is 0x03 wherever C sits; the physical
+/// key is only used when the layout yields no Latin letter (e.g. Cyrillic).
+pub fn control_code(key: PhysicalKey, logical: &Key) -> Option<u8> {
+    if let Key::Character(s) = logical {
+        let mut chars = s.chars();
+        if let (Some(c), None) = (chars.next(), chars.next()) {
+            match c {
+                'a'..='z' | 'A'..='Z' => return Some(c.to_ascii_lowercase() as u8 - b'a' + 1),
+                // Some platforms report the control character itself
+                '\u{1}'..='\u{1a}' => return Some(c as u8),
+                _ => {}
+            }
+        }
+    }
+    let PhysicalKey::Code(code) = key else {
+        return None;
+    };
+    LETTER_KEYS.iter().position(|&k| k == code).map(|i| i as u8 + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use winit::keyboard::NamedKey;
+
+    fn ctrl(code: KeyCode, logical: &str) -> Option<u8> {
+        control_code(PhysicalKey::Code(code), &Key::Character(logical.into()))
+    }
+
+    #[test]
+    fn ctrl_letter_uses_the_logical_letter() {
+        assert_eq!(ctrl(KeyCode::KeyC, "c"), Some(0x03));
+        assert_eq!(ctrl(KeyCode::KeyC, "C"), Some(0x03));
+        assert_eq!(ctrl(KeyCode::KeyA, "a"), Some(0x01));
+        assert_eq!(ctrl(KeyCode::KeyZ, "z"), Some(0x1a));
+        // Some platforms hand over the control character itself
+        assert_eq!(ctrl(KeyCode::KeyC, "\u{3}"), Some(0x03));
+    }
+
+    #[test]
+    fn non_us_layouts_follow_the_letter_not_the_key() {
+        // Dvorak: C sits on the physical I key, so Ctrl+C still interrupts
+        assert_eq!(ctrl(KeyCode::KeyI, "c"), Some(0x03));
+        // AZERTY: A and Q are swapped
+        assert_eq!(ctrl(KeyCode::KeyQ, "a"), Some(0x01));
+        assert_eq!(ctrl(KeyCode::KeyA, "q"), Some(0x11));
+        // Cyrillic has no Latin letter, so the physical key decides
+        assert_eq!(ctrl(KeyCode::KeyC, "\u{441}"), Some(0x03));
+    }
+
+    #[test]
+    fn navigation_keys_follow_cursor_key_mode() {
+        assert_eq!(cursor_key(KeyCode::Home, false), Some(&b"\x1b[H"[..]));
+        assert_eq!(cursor_key(KeyCode::End, false), Some(&b"\x1b[F"[..]));
+        assert_eq!(cursor_key(KeyCode::Home, true), Some(&b"\x1bOH"[..]));
+        assert_eq!(cursor_key(KeyCode::End, true), Some(&b"\x1bOF"[..]));
+        assert_eq!(cursor_key(KeyCode::ArrowUp, false), Some(&b"\x1b[A"[..]));
+        assert_eq!(cursor_key(KeyCode::ArrowDown, true), Some(&b"\x1bOB"[..]));
+        // Paging keys don't change with DECCKM
+        for app_cursor in [false, true] {
+            assert_eq!(cursor_key(KeyCode::PageUp, app_cursor), Some(&b"\x1b[5~"[..]));
+            assert_eq!(cursor_key(KeyCode::PageDown, app_cursor), Some(&b"\x1b[6~"[..]));
+        }
+        assert_eq!(cursor_key(KeyCode::KeyA, true), None);
+    }
+
+    #[test]
+    fn non_letters_have_no_control_code() {
+        assert_eq!(ctrl(KeyCode::Digit1, "1"), None);
+        assert_eq!(ctrl(KeyCode::Minus, "-"), None);
+        assert_eq!(control_code(PhysicalKey::Code(KeyCode::Enter), &Key::Named(NamedKey::Enter)), None);
+    }
+}
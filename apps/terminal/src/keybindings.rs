@@ -0,0 +1,293 @@
+use the_dev_terminal_core::config::KeybindingsConfig;
+use tracing::warn;
+use winit::keyboard::{KeyCode, ModifiersState};
+
+/// App-level actions that can be bound to keys in the config
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Action {
+    Scroll(ScrollAction),
+    Search(SearchAction),
+    /// Blank the visible screen, keeping scrollback
+    ClearScreen,
+    /// Copy the output of the last finished command
+    CopyLastOutput,
+    /// Select the output of the command under the mouse pointer
+    SelectCommandOutput,
+    /// Save scrollback and screen to a file, with SGR styling when `ansi`
+    SaveScrollback { ansi: bool },
+    /// Write fixed bytes to the shell (a `send_keys` macro)
+    SendBytes(Vec<u8>),
+}
+
+/// Viewport movements through the scrollback
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScrollAction {
+    PageUp,
+    PageDown,
+    LineUp,
+    LineDown,
+    Top,
+    Bottom,
+    /// To the nearest shell-integration prompt above / below the viewport top
+    PrevPrompt,
+    NextPrompt,
+}
+
+/// Actions on the current search results, available while search is open
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SearchAction {
+    /// Copy every line containing a match, in buffer order
+    CopyMatchingLines,
+    /// Turn every match into one multi-range selection
+    SelectAllMatches,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KeyBinding {
+    pub mods: ModifiersState,
+    pub key: KeyCode,
+    pub action: Action,
+}
+
+/// Resolved key bindings, matched against physical keys
+#[derive(Default)]
+pub struct KeyBindings {
+    bindings: Vec<KeyBinding>,
+}
+
+impl KeyBindings {
+    pub fn from_config(cfg: &KeybindingsConfig) -> Self {
+        let specs = [
+            (&cfg.scroll_page_up, Action::Scroll(ScrollAction::PageUp)),
+            (&cfg.scroll_page_down, Action::Scroll(ScrollAction::PageDown)),
+            (&cfg.scroll_line_up, Action::Scroll(ScrollAction::LineUp)),
+            (&cfg.scroll_line_down, Action::Scroll(ScrollAction::LineDown)),
+            (&cfg.scroll_to_top, Action::Scroll(ScrollAction::Top)),
+            (&cfg.scroll_to_bottom, Action::Scroll(ScrollAction::Bottom)),
+            (&cfg.scroll_to_prev_prompt, Action::Scroll(ScrollAction::PrevPrompt)),
+            (&cfg.scroll_to_next_prompt, Action::Scroll(ScrollAction::NextPrompt)),
+            (&cfg.clear_screen, Action::ClearScreen),
+            (&cfg.copy_last_output, Action::CopyLastOutput),
+            (&cfg.select_command_output, Action::SelectCommandOutput),
+            (&cfg.save_scrollback, Action::SaveScrollback { ansi: false }),
+            (&cfg.save_scrollback_ansi, Action::SaveScrollback { ansi: true }),
+            (&cfg.copy_matching_lines, Action::Search(SearchAction::CopyMatchingLines)),
+            (&cfg.select_all_matches, Action::Search(SearchAction::SelectAllMatches)),
+        ];
+
+        let macros = cfg.send_keys.iter().filter_map(|m| match parse_escapes(&m.text) {
+            Ok(bytes) => Some((&m.key, Action::SendBytes(bytes))),
+            Err(e) => {
+                warn!("Ignoring send_keys macro for {:?}: {} in {:?}", m.key, e, m.text);
+                None
+            }
+        });
+
+        let mut bindings = Vec::new();
+        for (spec, action) in specs.into_iter().chain(macros) {
+            // An empty spec leaves the action unbound
+            if spec.trim().is_empty() {
+                continue;
+            }
+            match parse_key_spec(spec) {
+                Some((mods, key)) => bindings.push(KeyBinding { mods, key, action }),
+                None => warn!("Ignoring invalid key binding {:?} for {:?}", spec, action),
+            }
+        }
+        Self { bindings }
+    }
+
+    /// Action bound to this exact modifier + key combination
+    pub fn action_for(&self, mods: ModifiersState, key: KeyCode) -> Option<&Action> {
+        self.bindings
+            .iter()
+            .find(|b| b.key == key && b.mods == mods)
+            .map(|b| &b.action)
+    }
+}
+
+/// Bytes for a macro string with `\n`, `\r`, `\t`, `\e`, `\\`, `\xNN` (a raw
+/// byte, e.g. `\x1b`) and `\u{N}` (a character, UTF-8 encoded) escapes; the
+/// error says which escape is wrong
+pub fn parse_escapes(s: &str) -> Result<Vec<u8>, String> {
+    let mut out = Vec::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push(b'\n'),
+            Some('r') => out.push(b'\r'),
+            Some('t') => out.push(b'\t'),
+            Some('e') => out.push(0x1b),
+            Some('\\') => out.push(b'\\'),
+            Some('x') => {
+                let hex: String = chars.by_ref().take(2).collect();
+                match u8::from_str_radix(&hex, 16) {
+                    Ok(b) if hex.len() == 2 && !hex.starts_with('+') => out.push(b),
+                    _ => return Err("\\x needs two hex digits, like \\x1b".to_string()),
+                }
+            }
+            Some('u') => {
+                let rest = chars.as_str();
+                let hex = rest.strip_prefix('{').and_then(|r| r.split_once('}')).map(|(hex, _)| hex);
+                let ch = hex
+                    .filter(|h| !h.starts_with('+'))
+                    .and_then(|h| u32::from_str_radix(h, 16).ok())
+                    .and_then(char::from_u32);
+                let (Some(hex), Some(ch)) = (hex, ch) else {
+                    return Err("\\u needs a character code in braces, like \\u{1b}".to_string());
+                };
+                out.extend_from_slice(ch.encode_utf8(&mut [0; 4]).as_bytes());
+                chars = rest[hex.len() + 2..].chars();
+            }
+            Some(other) => return Err(format!("unknown escape \\{}", other)),
+            None => return Err("trailing backslash".to_string()),
+        }
+    }
+    Ok(out)
+}
+
+/// Parse a key spec like `ctrl+shift+up` or `cmd+k`
+pub fn parse_key_spec(spec: &str) -> Option<(ModifiersState, KeyCode)> {
+    let mut mods = ModifiersState::empty();
+    let mut key = None;
+
+    for part in spec.split('+').map(str::trim) {
+        match part.to_ascii_lowercase().as_str() {
+            "cmd" | "command" | "super" | "meta" => mods |= ModifiersState::SUPER,
+            "ctrl" | "control" => mods |= ModifiersState::CONTROL,
+            "alt" | "option" | "opt" => mods |= ModifiersState::ALT,
+            "shift" => mods |= ModifiersState::SHIFT,
+            // '+' itself splits into an empty part; spell it "plus"
+            "" => return None,
+            name => {
+                if key.is_some() {
+                    return None;
+                }
+                key = Some(key_code_from_name(name)?);
+            }
+        }
+    }
+
+    key.map(|k| (mods, k))
+}
+
+fn key_code_from_name(name: &str) -> Option<KeyCode> {
+    let code = match name {
+        "pageup" | "pgup" => KeyCode::PageUp,
+        "pagedown" | "pgdn" => KeyCode::PageDown,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        "up" => KeyCode::ArrowUp,
+        "down" => KeyCode::ArrowDown,
+        "left" => KeyCode::ArrowLeft,
+        "right" => KeyCode::ArrowRight,
+        "enter" | "return" => KeyCode::Enter,
+        "tab" => KeyCode::Tab,
+        "space" => KeyCode::Space,
+        "backspace" => KeyCode::Backspace,
+        "delete" | "del" => KeyCode::Delete,
+        "insert" | "ins" => KeyCode::Insert,
+        "escape" | "esc" => KeyCode::Escape,
+        "=" | "plus" => KeyCode::Equal,
+        "-" | "minus" => KeyCode::Minus,
+        "[" => KeyCode::BracketLeft,
+        "]" => KeyCode::BracketRight,
+        ";" => KeyCode::Semicolon,
+        "'" => KeyCode::Quote,
+        "," => KeyCode::Comma,
+        "." => KeyCode::Period,
+        "/" => KeyCode::Slash,
+        "\\" => KeyCode::Backslash,
+        "`" => KeyCode::Backquote,
+        "f1" => KeyCode::F1,
+        "f2" => KeyCode::F2,
+        "f3" => KeyCode::F3,
+        "f4" => KeyCode::F4,
+        "f5" => KeyCode::F5,
+        "f6" => KeyCode::F6,
+        "f7" => KeyCode::F7,
+        "f8" => KeyCode::F8,
+        "f9" => KeyCode::F9,
+        "f10" => KeyCode::F10,
+        "f11" => KeyCode::F11,
+        "f12" => KeyCode::F12,
+        _ => {
+            let mut chars = name.chars();
+            let (Some(c), None) = (chars.next(), chars.next()) else {
+                return None;
+            };
+            return letter_or_digit_code(c);
+        }
+    };
+    Some(code)
+}
+
+fn letter_or_digit_code(c: char) -> Option<KeyCode> {
+    const LETTERS: [KeyCode; 26] = [
+        KeyCode::KeyA, KeyCode::KeyB, KeyCode::KeyC, KeyCode::KeyD, KeyCode::KeyE,
+        KeyCode::KeyF, KeyCode::KeyG, KeyCode::KeyH, KeyCode::KeyI, KeyCode::KeyJ,
+        KeyCode::KeyK, KeyCode::KeyL, KeyCode::KeyM, KeyCode::KeyN, KeyCode::KeyO,
+        KeyCode::KeyP, KeyCode::KeyQ, KeyCode::KeyR, KeyCode::KeyS, KeyCode::KeyT,
+        KeyCode::KeyU, KeyCode::KeyV, KeyCode::KeyW, KeyCode::KeyX, KeyCode::KeyY,
+        KeyCode::KeyZ,
+    ];
+    const DIGITS: [KeyCode; 10] = [
+        KeyCode::Digit0, KeyCode::Digit1, KeyCode::Digit2, KeyCode::Digit3, KeyCode::Digit4,
+        KeyCode::Digit5, KeyCode::Digit6, KeyCode::Digit7, KeyCode::Digit8, KeyCode::Digit9,
+    ];
+
+    match c {
+        'a'..='z' => Some(LETTERS[(c as u8 - b'a') as usize]),
+        '0'..='9' => Some(DIGITS[(c as u8 - b'0') as usize]),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_become_bytes() {
+        assert_eq!(parse_escapes("ls\\n").unwrap(), b"ls\n");
+        assert_eq!(parse_escapes("\\r\\t\\e\\\\").unwrap(), b"\r\t\x1b\\");
+        assert_eq!(parse_escapes("\\x1b[A\\x7F").unwrap(), b"\x1b[A\x7f");
+        assert_eq!(parse_escapes("\\u{1b}\\u{e9}!").unwrap(), "\x1b\u{e9}!".as_bytes());
+        assert_eq!(parse_escapes("\\u{1F600}").unwrap(), "\u{1F600}".as_bytes());
+        assert_eq!(parse_escapes("plain \u{e9}").unwrap(), "plain \u{e9}".as_bytes());
+    }
+
+    #[test]
+    fn bad_escapes_are_errors() {
+        for bad in ["\\q", "end\\", "\\x1", "\\xg0", "\\u1b", "\\u{1b", "\\u{}", "\\u{zz}", "\\u{d800}", "\\u{110000}", "\\x+1", "\\u{+41}"] {
+            assert!(parse_escapes(bad).is_err(), "{bad:?}");
+        }
+        assert_eq!(parse_escapes("a\\qb").unwrap_err(), "unknown escape \\q");
+        assert_eq!(parse_escapes("a\\").unwrap_err(), "trailing backslash");
+    }
+
+    #[test]
+    fn default_bindings_are_distinct() {
+        let cfg = KeybindingsConfig::default();
+        // The fixed app shortcuts are configured alongside the rebindable actions
+        let fixed = [
+            &cfg.copy, &cfg.paste, &cfg.search, &cfg.new_tab, &cfg.close_tab, &cfg.next_tab,
+            &cfg.prev_tab, &cfg.zoom_in, &cfg.zoom_out, &cfg.zoom_reset, &cfg.clear_scrollback,
+        ];
+        let mut seen: Vec<(ModifiersState, KeyCode, String)> = Vec::new();
+        let resolved = KeyBindings::from_config(&cfg).bindings.into_iter()
+            .map(|b| (b.mods, b.key, format!("{:?}", b.action)));
+        let fixed = fixed.into_iter().filter(|s| !s.is_empty())
+            .map(|s| parse_key_spec(s).map(|(m, k)| (m, k, s.clone())).expect(s));
+        for (mods, key, name) in resolved.chain(fixed) {
+            if let Some((_, _, other)) = seen.iter().find(|(m, k, _)| *m == mods && *k == key) {
+                panic!("{name} and {other} share {mods:?} {key:?}");
+            }
+            seen.push((mods, key, name));
+        }
+    }
+}
@@ -1,43 +1,75 @@
+mod close_confirm;
+mod glyph_notice;
+mod key_encoding;
+mod keybindings;
+mod selection;
+mod window_toggle;
+
 use anyhow::Result;
 use clap::Parser;
 use copypasta::{ClipboardContext, ClipboardProvider};
 use std::sync::{Arc, Mutex};
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::Ordering;
 use std::time::{Duration, Instant};
-use the_dev_terminal_core::{grid::Grid, pty::PtyHandle, vt::advance_bytes_with_bracketed};
-use the_dev_terminal_ui_wgpu::Renderer;
+use the_dev_terminal_core::{
+    config::{BellConfig, BellStyle, Config, ScrollBehavior},
+    frame_pacing::FramePacer,
+    grid::{find_word_boundaries, Cell, Grid},
+    palette::Palette,
+    paths::{self, LaunchSentinel},
+    search::{SearchProgress, SearchQuery, SearchSession},
+    perf::{PerfTimer, StartupProfile},
+    pty::{PtyHandle, PtyInbox},
+    terminal::{encode_paste, normalize_paste},
+    Terminal,
+};
+use the_dev_terminal_ui_wgpu::{drawn_pixels, RenderError, Renderer};
+use glyph_notice::GlyphNotice;
+use keybindings::{Action, KeyBindings, ScrollAction, SearchAction};
+use selection::{Region, Selection};
+use window_toggle::{ToggleAction, WindowToggle};
 use tokio::sync::mpsc;
-use tracing::{error, info};
-use tracing_subscriber;
+use tracing::{error, info, warn};
 use winit::{
     event::{Event, WindowEvent, ElementState, KeyEvent, MouseButton, MouseScrollDelta},
     event_loop::{ControlFlow, EventLoopBuilder, EventLoopProxy},
     keyboard::{Key, KeyCode, PhysicalKey, ModifiersState},
-    window::WindowBuilder,
+    window::{CursorIcon, UserAttentionType, Window, WindowBuilder},
 };
 
+/// How long the visual bell keeps the background flashed
+const BELL_FLASH: Duration = Duration::from_millis(100);
+/// Bells arriving closer together than this are coalesced into one
+const BELL_COALESCE: Duration = Duration::from_millis(150);
+
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
     #[arg(long)]
     smoketest: bool,
+    /// Start with built-in defaults only, ignoring the config file
+    #[arg(long)]
+    safe_mode: bool,
+    /// Log a breakdown of startup phases once the first prompt is shown
+    #[arg(long)]
+    perf: bool,
+    /// Print the startup breakdown and exit once the first prompt is shown
+    #[arg(long)]
+    measure_startup: bool,
 }
 
 #[derive(Debug, Clone)]
 enum UserEvent {
-    PtyData(Vec<u8>),
-}
-
-#[derive(Default, Clone, Copy)]
-struct Region { 
-    start: (usize, usize), 
-    end: (usize, usize) 
+    /// Output is waiting in the PTY inbox
+    PtyData,
+    /// The global show/hide hotkey was pressed
+    ToggleWindow,
 }
 
 #[derive(Default)]
 struct SelectionState {
     dragging: bool,              // true only while mouse is down
-    region: Option<Region>,      // current selection to render/copy
+    regions: Selection,          // current selection to render/copy
     last_click_time: Option<std::time::Instant>,
     last_click_pos: Option<(usize, usize)>,
     click_count: usize,          // For double/triple click detection
@@ -55,72 +87,302 @@ struct ScrollState {
 struct SearchState {
     active: bool,                // Is search mode active
     query: String,               // Current search query
-    matches: Vec<(usize, usize, usize, usize)>, // (start_col, start_row, end_col, end_row)
+    regex: bool,                 // Query is a regex rather than plain text
+    matches: Vec<(usize, usize, usize, usize)>, // (start_col, start_row, end_col, end_row), absolute rows
     current_match: Option<usize>, // Index of currently highlighted match
+    session: Option<SearchSession>, // Searching the buffer a chunk per event-loop pass
+}
+
+/// Search the grid for the current query from scratch, cancelling any
+/// search in progress; an invalid regex is logged and leaves no matches
+fn restart_search(search: &mut SearchState, grid: &Grid) {
+    search.matches.clear();
+    search.current_match = None;
+    if search.query.is_empty() {
+        search.session = None;
+        return;
+    }
+    let query = if search.regex {
+        SearchQuery::Regex(search.query.clone())
+    } else {
+        SearchQuery::Plain { text: search.query.clone(), case_sensitive: false }
+    };
+    let started = match search.session.as_mut() {
+        Some(session) => session.set_query(&query, grid),
+        None => SearchSession::new(&query, grid).map(|session| search.session = Some(session)),
+    };
+    if let Err(e) = started {
+        warn!("Invalid search pattern: {}", e);
+        search.session = None;
+    }
+}
+
+/// Grid dimensions that fit a window of `width` x `height` physical pixels
+fn grid_size_for(width: u32, height: u32, cw: f32, ch: f32, padding: f32) -> (u16, u16) {
+    let cols = ((width as f32 - 2.0 * padding) / cw).floor().max(1.0) as u16;
+    let rows = ((height as f32 - 2.0 * padding) / ch).floor().max(1.0) as u16;
+    (cols, rows)
 }
 
-fn pixels_to_cell(x: f32, y: f32, cw: f32, ch: f32) -> (usize, usize) {
-    let col = (x / cw).floor().max(0.0) as usize;
-    let row = (y / ch).floor().max(0.0) as usize;
+/// Map a physical window position to a grid cell; `padding` is in physical pixels.
+/// Clicks inside the padding clamp to the edge cells
+fn pixels_to_cell(x: f32, y: f32, cw: f32, ch: f32, padding: f32) -> (usize, usize) {
+    let col = ((x - padding) / cw).floor().max(0.0) as usize;
+    let row = ((y - padding) / ch).floor().max(0.0) as usize;
     (col, row)
 }
 
+#[cfg(target_os = "macos")]
+fn play_system_bell() {
+    #[link(name = "AppKit", kind = "framework")]
+    extern "C" {
+        fn NSBeep();
+    }
+    unsafe { NSBeep() }
+}
+
+#[cfg(not(target_os = "macos"))]
+fn play_system_bell() {
+    tracing::debug!("No system alert sound on this platform");
+}
+
+/// Whether a BEL at `now` rings, given when the bell last rang: bells
+/// within `BELL_COALESCE` of a ring are folded into it, so a steady stream
+/// still rings every `BELL_COALESCE`
+fn bell_rings(last_rung: Option<Instant>, now: Instant) -> bool {
+    match last_rung {
+        Some(t) => now.duration_since(t) >= BELL_COALESCE,
+        None => true,
+    }
+}
+
+/// React to a BEL from the shell according to the bell style and config.
+/// Returns the deadline of the visual flash, if one was started.
+fn ring_bell(style: BellStyle, bell: &BellConfig, window: &Window, focused: bool) -> Option<Instant> {
+    if bell.urgent && !focused {
+        window.request_user_attention(Some(UserAttentionType::Informational));
+    }
+    match style {
+        BellStyle::None => None,
+        BellStyle::Audible => {
+            play_system_bell();
+            None
+        }
+        BellStyle::Visual => {
+            window.request_redraw();
+            Some(Instant::now() + BELL_FLASH)
+        }
+    }
+}
+
+/// Move the viewport over `grid` for a scroll action
+fn apply_scroll_action(
+    action: ScrollAction,
+    s: &mut ScrollState,
+    grid: &Grid,
+    page_fraction: f32,
+) {
+    let rows = grid.rows;
+    let max_top = grid.scrollable_lines().saturating_sub(rows);
+    let page = ((rows as f32 * page_fraction).round() as usize).max(1);
+    
+    match action {
+        ScrollAction::PageUp => s.top_abs = s.top_abs.saturating_sub(page),
+        ScrollAction::LineUp => s.top_abs = s.top_abs.saturating_sub(1),
+        ScrollAction::PageDown => s.top_abs = (s.top_abs + page).min(max_top),
+        ScrollAction::LineDown => s.top_abs = (s.top_abs + 1).min(max_top),
+        ScrollAction::Top => s.top_abs = 0,
+        ScrollAction::Bottom => s.top_abs = max_top,
+        // Without shell-integration marks in that direction the viewport stays
+        ScrollAction::PrevPrompt => s.top_abs = grid.prev_mark(s.top_abs).unwrap_or(s.top_abs),
+        ScrollAction::NextPrompt => s.top_abs = grid.next_mark(s.top_abs).unwrap_or(s.top_abs).min(max_top),
+    }
+    
+    s.subrow = 0.0;
+    s.vel_rows_per_s = 0.0;
+    // Reaching the bottom re-enables following new output
+    s.stick_to_bottom = s.top_abs == max_top;
+}
+
+/// Viewport update when new output arrives; `max_top` is the bottom-most top
+/// row and `evicted` how many scrollback lines the output pushed out. While
+/// `pinned` (a selection drag) the viewport stays on the text it shows.
+fn follow_output(policy: ScrollBehavior, s: &mut ScrollState, max_top: usize, evicted: usize, pinned: bool) {
+    // Evictions move every absolute row up; a viewport off the bottom follows its text
+    if !s.stick_to_bottom || pinned {
+        s.top_abs = s.top_abs.saturating_sub(evicted);
+    }
+    if pinned {
+        s.stick_to_bottom = false;
+        s.top_abs = s.top_abs.min(max_top);
+        return;
+    }
+    match policy {
+        ScrollBehavior::Always => s.stick_to_bottom = true,
+        ScrollBehavior::WhenAtBottom => {}
+        // Stay on the rows being read; only a keystroke brings us back down
+        ScrollBehavior::OnKeystroke => s.stick_to_bottom = false,
+    }
+    if s.stick_to_bottom {
+        s.top_abs = max_top;
+        s.subrow = 0.0;
+        s.vel_rows_per_s = 0.0;
+    } else {
+        // Keep viewport valid if content grew
+        s.top_abs = s.top_abs.min(max_top);
+    }
+}
+
+/// Resize the grid, rewrapping when `reflow` is on (never on the alternate
+/// screen, whose app redraws), and move the selection with its text. False
+/// when the selection didn't survive.
+fn resize_grid(g: &mut Grid, cols: usize, rows: usize, reflow: bool, selection: &mut Selection) -> bool {
+    if reflow && !g.is_alt_screen() {
+        let reflow = g.reflow(cols, rows);
+        selection.remap(|pos| reflow.abs_position(pos))
+    } else {
+        let resize = g.resize_preserve(cols, rows);
+        selection.remap(|pos| resize.abs_position(pos))
+    }
+}
+
+/// Grid, PTY and viewport update for a new window or font size
+fn apply_resize(
+    (cols, rows): (u16, u16),
+    reflow: bool,
+    terminal: &Mutex<Terminal>,
+    pty: &PtyHandle,
+    scroll: &Mutex<ScrollState>,
+    selection: &mut SelectionState,
+    selection_text: &mut Option<String>,
+) {
+    let mut term = terminal.lock().unwrap();
+    let g = &mut term.grid;
+    if !resize_grid(g, cols as usize, rows as usize, reflow, &mut selection.regions) {
+        *selection_text = None;
+    }
+    let _ = pty.resize(rows, cols);
+
+    // Reset fractional scroll to avoid stale offsets after metrics change
+    let max_top = g.scrollable_lines().saturating_sub(g.rows);
+    let mut s = scroll.lock().unwrap();
+    if s.stick_to_bottom {
+        s.top_abs = max_top;
+    } else {
+        s.top_abs = s.top_abs.min(max_top);
+    }
+    s.subrow = 0.0;
+    s.vel_rows_per_s = 0.0;
+}
+
+/// Viewport update when a key goes to the shell
+fn follow_keystroke(policy: ScrollBehavior, s: &mut ScrollState, max_top: usize) {
+    if matches!(policy, ScrollBehavior::Always | ScrollBehavior::OnKeystroke) {
+        s.top_abs = max_top;
+        s.subrow = 0.0;
+        s.vel_rows_per_s = 0.0;
+        s.stick_to_bottom = true;
+    }
+}
+
+/// Send a keystroke's bytes to the shell; the viewport follows only once
+/// something was actually written
+fn write_key(pty: &PtyHandle, bytes: &[u8], terminal: &Mutex<Terminal>, scroll: &Mutex<ScrollState>, policy: ScrollBehavior) {
+    if let Err(e) = pty.write(bytes) {
+        error!("Failed to write to PTY: {}", e);
+        return;
+    }
+    let term = terminal.lock().unwrap();
+    let max_top = term.grid.scrollable_lines().saturating_sub(term.grid.rows);
+    follow_keystroke(policy, &mut scroll.lock().unwrap(), max_top);
+}
+
+/// Most arrow presses one wheel event sends to a full-screen app
+const MAX_WHEEL_ARROWS: usize = 20;
+
+/// Arrow keys standing in for a wheel scroll of `rows_delta` rows on the alt
+/// screen (negative is up), encoded for the app's cursor key mode
+fn wheel_arrows(rows_delta: f32, app_cursor: bool) -> Vec<u8> {
+    let code = if rows_delta < 0.0 { KeyCode::ArrowUp } else { KeyCode::ArrowDown };
+    let arrow = key_encoding::cursor_key(code, app_cursor).unwrap_or_default();
+    let count = (rows_delta.abs().round() as usize).min(MAX_WHEEL_ARROWS);
+    arrow.repeat(count)
+}
+
 fn copy_to_clipboard(s: &str) {
     if let Ok(mut cb) = ClipboardContext::new() {
         let _ = cb.set_contents(s.to_string());
     }
 }
 
+/// Write scrollback and screen to a new timestamped file in `~/Downloads`,
+/// streamed so long histories are never held in memory as one string
+fn save_scrollback(grid: &Grid, ansi: bool) -> anyhow::Result<std::path::PathBuf> {
+    use std::io::Write;
+    let dir = paths::downloads_dir()?;
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join(paths::dump_file_name(std::time::SystemTime::now()));
+    let mut out = std::io::BufWriter::new(std::fs::File::create(&path)?);
+    if ansi {
+        grid.write_all_ansi(&mut out)?;
+    } else {
+        grid.write_all_text(&mut out)?;
+    }
+    out.flush()?;
+    Ok(path)
+}
+
 fn paste_from_clipboard() -> Option<String> {
     ClipboardContext::new().ok()?.get_contents().ok()
 }
 
-fn find_word_boundaries(grid: &Grid, col: usize, row: usize) -> (usize, usize) {
-    // Find word boundaries at the given position
-    let line_start = row * grid.cols;
-    
-    // Helper to check if a character is a word boundary
-    let is_word_char = |ch: char| ch.is_alphanumeric() || ch == '_';
-    
-    let mut start = col;
-    let mut end = col;
-    
-    // If we're not on a word character, return the single position
-    let idx = line_start + col;
-    if idx >= grid.cells.len() || !is_word_char(grid.cells[idx].ch) {
-        return (col, col);
-    }
-    
-    // Find start of word
-    while start > 0 {
-        let idx = line_start + start - 1;
-        if idx >= grid.cells.len() || !is_word_char(grid.cells[idx].ch) {
-            break;
+#[cfg(all(
+    unix,
+    not(any(target_os = "macos", target_os = "android", target_os = "ios", target_os = "emscripten"))
+))]
+mod primary_selection {
+    use copypasta::x11_clipboard::{Primary, X11ClipboardContext};
+    use copypasta::ClipboardProvider;
+
+    pub fn copy(s: &str) {
+        if let Ok(mut cb) = X11ClipboardContext::<Primary>::new() {
+            let _ = cb.set_contents(s.to_string());
         }
-        start -= 1;
     }
-    
-    // Find end of word
-    while end < grid.cols - 1 {
-        let idx = line_start + end + 1;
-        if idx >= grid.cells.len() || !is_word_char(grid.cells[idx].ch) {
-            break;
-        }
-        end += 1;
+
+    pub fn paste() -> Option<String> {
+        X11ClipboardContext::<Primary>::new().ok()?.get_contents().ok()
+    }
+}
+
+// Platforms without a primary selection: copy-on-select and middle-click are no-ops
+#[cfg(not(all(
+    unix,
+    not(any(target_os = "macos", target_os = "android", target_os = "ios", target_os = "emscripten"))
+)))]
+mod primary_selection {
+    pub fn copy(_s: &str) {}
+
+    pub fn paste() -> Option<String> {
+        None
     }
-    
-    (start, end)
 }
 
-fn find_line_boundaries(grid: &Grid, row: usize) -> (usize, usize) {
+fn paste_to_pty(pty: &PtyHandle, text: &str, bracketed: bool) {
+    // Respect bracketed paste if enabled
+    if !bracketed && normalize_paste(text, false).contains('\n') {
+        warn!("Multi-line paste without bracketed paste: each line runs as it arrives");
+    }
+    let _ = pty.write(&encode_paste(text, bracketed));
+}
+
+fn find_line_boundaries(line: &[Cell]) -> (usize, usize) {
     // Find the actual content boundaries of a line (trimming trailing spaces)
-    let line_start = row * grid.cols;
-    let mut end_col = grid.cols - 1;
+    let mut end_col = line.len().saturating_sub(1);
     
     // Find last non-space character
     while end_col > 0 {
-        let idx = line_start + end_col;
-        if idx < grid.cells.len() && grid.cells[idx].ch != ' ' && grid.cells[idx].ch != '\0' {
+        if !matches!(line[end_col].ch(), ' ' | '\0') {
             break;
         }
         end_col -= 1;
@@ -129,40 +391,73 @@ fn find_line_boundaries(grid: &Grid, row: usize) -> (usize, usize) {
     (0, end_col)
 }
 
-fn detect_url_at_position(grid: &Grid, col: usize, row: usize) -> Option<String> {
-    // Simple URL detection - look for http:// or https:// patterns
-    let line_start = row * grid.cols;
-    let mut text = String::new();
-    
-    // Collect the line text
-    for c in 0..grid.cols {
-        let idx = line_start + c;
-        if idx < grid.cells.len() {
-            let ch = grid.cells[idx].ch;
-            if ch != '\0' {
-                text.push(ch);
-            }
-        }
+/// Schemes we are willing to hand to the OS opener. `file:` is left out: the
+/// opener would launch an executable target instead of showing it
+const URL_SCHEMES: &[&str] = &["http", "https", "ftp", "mailto"];
+
+fn is_allowed_url_scheme(url: &str) -> bool {
+    url.split_once(':').is_some_and(|(scheme, _)| {
+        URL_SCHEMES.iter().any(|s| s.eq_ignore_ascii_case(scheme))
+    })
+}
+
+/// Modifier that turns a click into "open the URL under the mouse"
+fn is_url_click(mods: ModifiersState) -> bool {
+    if cfg!(target_os = "macos") {
+        mods.super_key()
+    } else {
+        mods.control_key()
     }
+}
+
+/// Open a URL in the default handler for its scheme
+fn open_url(url: &str) {
+    if !is_allowed_url_scheme(url) {
+        info!("Refusing to open URL with unsupported scheme: {}", url);
+        return;
+    }
+    info!("Opening URL: {}", url);
     
-    // Look for URLs in the text
-    let url_prefixes = ["http://", "https://", "ftp://", "file://"];
-    for prefix in &url_prefixes {
-        if let Some(start_idx) = text.find(prefix) {
-            if col >= start_idx && col < start_idx + text[start_idx..].len() {
-                // Find the end of the URL
-                let url_start = start_idx;
-                let remaining = &text[start_idx..];
-                let url_end = remaining.find(|c: char| c.is_whitespace() || c == '"' || c == '\'' || c == '>' || c == ')' || c == ']')
-                    .unwrap_or(remaining.len());
-                
-                let url = &text[url_start..url_start + url_end];
-                return Some(url.to_string());
-            }
-        }
+    #[cfg(target_os = "macos")]
+    let result = std::process::Command::new("open").arg(url).spawn();
+    
+    #[cfg(windows)]
+    // Straight to the URL handler: no shell re-parses the URL's `&` or `|`
+    let result = std::process::Command::new("rundll32").args(["url.dll,FileProtocolHandler", url]).spawn();
+    
+    #[cfg(not(any(target_os = "macos", windows)))]
+    let result = std::process::Command::new("xdg-open").arg(url).spawn();
+    
+    if let Err(e) = result {
+        error!("Failed to open URL {}: {}", url, e);
     }
+}
+
+/// Start tracking this launch. Returns the sentinel to clear once a frame is
+/// shown, and whether safe mode is on (requested, or after repeated crashes).
+fn begin_launch(requested_safe_mode: bool) -> (Option<LaunchSentinel>, bool) {
+    let begun = paths::state_dir()
+        .map_err(|e| e.to_string())
+        .and_then(|dir| LaunchSentinel::begin(&dir).map_err(|e| e.to_string()));
+    let (sentinel, history) = match begun {
+        Ok(begun) => begun,
+        Err(e) => {
+            warn!("Could not track startup state: {}", e);
+            return (None, requested_safe_mode);
+        }
+    };
     
-    None
+    if requested_safe_mode {
+        return (Some(sentinel), true);
+    }
+    if history.should_engage_safe_mode() {
+        warn!("The last {} launches crashed before the window appeared; starting in safe mode", history.failures);
+        return (Some(sentinel), true);
+    }
+    if history.failures > 0 {
+        warn!("The previous launch crashed during startup; if this keeps happening, run with --safe-mode");
+    }
+    (Some(sentinel), false)
 }
 
 fn main() -> Result<()> {
@@ -176,37 +471,127 @@ fn main() -> Result<()> {
     runtime.block_on(run(args))
 }
 
+/// Smoketest: draw a known string offscreen and check that glyphs landed
+/// where the text is, so a frame that only shows the clear color fails
+async fn check_rendered_text() -> Result<()> {
+    const TEXT: &str = "The Dev Terminal smoketest";
+    let (width, height) = (640, 120);
+    let mut r = Renderer::new_headless(width, height).await?;
+    let mut term = Terminal::with_grid(Grid::new(40, 3, 0));
+    term.advance(TEXT.as_bytes());
+    let mut snapshot = term.grid.snapshot_viewport(0, 3);
+    // The cursor block would count as drawn
+    snapshot.cursor = None;
+    r.set_snapshot(snapshot);
+    r.render_frame()?;
+    let frame = r.capture_frame()?;
+    
+    // Nothing is drawn below the three rows, so the last pixel is the clear color
+    let background: [u8; 4] = frame[frame.len() - 4..].try_into()?;
+    let text_width = (TEXT.len() as f32 * r.cell_width) as u32;
+    let in_text = drawn_pixels(&frame, width, 0..text_width, 0..r.cell_height as u32, background);
+    if in_text > 0 {
+        return Ok(());
+    }
+    if drawn_pixels(&frame, width, 0..width, 0..height, background) == 0 {
+        anyhow::bail!("rendered frame is entirely the clear color; no text was drawn");
+    }
+    anyhow::bail!("no text pixels where {:?} should be drawn", TEXT)
+}
+
 async fn run(args: Args) -> Result<()> {
+    let mut profile = StartupProfile::new();
+    if args.smoketest {
+        if let Err(e) = check_rendered_text().await {
+            error!("Smoketest failed: {}", e);
+            std::process::exit(1);
+        }
+        info!("Smoketest: offscreen text rendered");
+    }
+    let (mut launch_sentinel, safe_mode) = begin_launch(args.safe_mode);
+    if safe_mode {
+        warn!("==================== SAFE MODE ====================");
+        warn!("The config file is ignored; using the built-in theme, font and key bindings.");
+        warn!("Fix or remove {}/config.toml, then relaunch without --safe-mode.",
+            paths::config_dir().map(|d| d.display().to_string()).unwrap_or_else(|_| "~/.config/the-dev-terminal".into()));
+    }
+    
+    let config_timer = PerfTimer::new("config");
+    let config = Config::resolve(safe_mode).unwrap_or_else(|e| {
+        error!("Failed to load config, using defaults: {}", e);
+        Config::default()
+    });
+    profile.record(&config_timer);
+    
     let event_loop = EventLoopBuilder::<UserEvent>::with_user_event().build()?;
-    let window = Arc::new(
-        WindowBuilder::new()
-            .with_title("The Dev Terminal")
-            .with_inner_size(winit::dpi::LogicalSize::new(800, 600))
-            .build(&event_loop)?
-    );
     
-    let renderer = Arc::new(Mutex::new(Renderer::new(window.clone()).await?));
+    let grid = Grid::new(80, 25, config.general.scrollback_lines);
+    let terminal = Arc::new(Mutex::new(Terminal::with_grid(grid)));
     
-    let grid = Arc::new(Mutex::new(Grid::new(80, 25)));
+    // Grid settings from config: theme colors for color queries, clear behavior
+    let palette = Palette {
+        bold_as_bright: config.appearance.draw_bold_text_with_bright_colors,
+        ..Palette::from_theme(&config.theme)
+    };
+    {
+        let mut term = terminal.lock().unwrap();
+        term.grid.set_palette(palette.clone());
+        term.grid.clear_pushes_to_scrollback = config.general.clear_pushes_to_scrollback;
+        term.grid.scrollback.set_max_bytes(config.general.scrollback_max_bytes);
+        term.set_c1_controls(config.general.c1_controls);
+    }
     
-    let (pty, pty_rx) = PtyHandle::spawn(25, 80)?;
+    // Start the shell before the GPU: it boots while the renderer initializes,
+    // and its output waits in the PTY inbox until the loop starts
+    let pty_timer = PerfTimer::new("pty spawn");
+    // Resolved colors for prompt frameworks that read them from the environment
+    let theme_json = terminal.lock().unwrap().grid.theme_json();
+    let (pty, pty_rx) = PtyHandle::spawn(25, 80, &[("TDT_THEME_JSON", theme_json)])?;
     
     let proxy = event_loop.create_proxy();
     
-    spawn_pty_reader(pty_rx, proxy.clone());
+    let pty_inbox = PtyInbox::new();
+    spawn_pty_reader(pty_rx, pty_inbox.clone(), proxy.clone());
+    profile.record(&pty_timer);
+    
+    let window_timer = PerfTimer::new("window");
+    let window = Arc::new(
+        WindowBuilder::new()
+            .with_title(if safe_mode { "The Dev Terminal (Safe Mode)" } else { "The Dev Terminal" })
+            .with_inner_size(winit::dpi::LogicalSize::new(800, 600))
+            .build(&event_loop)?
+    );
+    profile.record(&window_timer);
+    profile.mark("window created");
+    
+    let renderer = Arc::new(Mutex::new(Renderer::new(window.clone(), config.appearance.window_padding, &mut profile).await?));
+    {
+        let mut r = renderer.lock().unwrap();
+        r.set_palette(palette);
+        r.set_bidi(config.appearance.bidi);
+    }
+    profile.mark("renderer ready");
     
     let mut frame_count = 0;
     let start_time = Instant::now();
     let mut modifiers = ModifiersState::empty();
+    let keybindings = KeyBindings::from_config(&config.keybindings);
     
     // Selection state
     let mut selection = SelectionState::default();
     let mut selection_text: Option<String> = None;
     let mut cursor_position = (0.0, 0.0);
+    // The mouse is over a link it would open, shown with a pointer
+    let mut hovering_link = false;
     
     // Search state
     let mut search = SearchState::default();
     
+    // A close is waiting for Enter (close) or Esc (cancel)
+    let mut confirming_close = false;
+    // Missing-glyph notice waiting to be read or dismissed
+    let mut glyph_notice: Option<GlyphNotice> = None;
+    
     // Initialize scroll state - stick to bottom by default
     let scroll = Arc::new(Mutex::new(ScrollState {
         top_abs: 0,
@@ -217,55 +602,121 @@ async fn run(args: Args) -> Result<()> {
     }));
     
     // Bracketed paste state (updated by VT parser when it sees CSI ? 2004 h/l)
-    let bracketed_paste_enabled = Arc::new(AtomicBool::new(false));
+    let bracketed_paste_enabled = terminal.lock().unwrap().bracketed_paste();
+    
+    // Viewport top and history length the renderer's cells were taken at;
+    // while both hold, only dirty grid rows need new text
+    let mut shown_view: Option<(usize, usize)> = None;
+    // Redraws for output, inertia and drags: max_fps while busy, idle_fps otherwise
+    let mut frame_pacer = FramePacer::new(config.performance.max_fps, config.performance.idle_fps);
+    
+    // Bell state
+    let mut window_focused = true;
+    let mut last_bell: Option<Instant> = None;
+    let mut bell_flash_until: Option<Instant> = None;
+    
+    // Quake-style show/hide from anywhere; the manager must outlive the loop
+    let mut window_toggle = WindowToggle::new();
+    let _hotkey_manager = window_toggle::register(&config.keybindings.toggle_window, proxy.clone(), UserEvent::ToggleWindow);
     
     event_loop.set_control_flow(ControlFlow::Wait);
     
     event_loop.run(move |event, elwt| {
         match event {
             Event::UserEvent(user_event) => match user_event {
-                UserEvent::PtyData(data) => {
-                    // Parse VT sequences and update grid
-                    {
-                        let mut g = grid.lock().unwrap();
-                        advance_bytes_with_bracketed(&mut g, &data, Some(bracketed_paste_enabled.clone()));
+                UserEvent::PtyData => {
+                    // Parse everything that arrived since the last batch in one go
+                    let data = pty_inbox.take();
+                    if data.is_empty() {
+                        return;
                     }
+                    let (bells, replies, evicted) = {
+                        let mut term = terminal.lock().unwrap();
+                        let evicted_before = term.grid.scrollback.evicted();
+                        term.advance(&data);
+                        note_first_prompt(&mut profile, &term.grid);
+                        (term.grid.take_bells(), term.take_replies(), term.grid.scrollback.evicted() - evicted_before)
+                    };
                     
-                    // Update scroll position if stick-to-bottom is enabled
-                    {
-                        let g = grid.lock().unwrap();
-                        let total = g.scrollback.len() + g.rows;
-                        let vis = g.rows;
-                        let max_top = total.saturating_sub(vis);
-                        
-                        let mut s = scroll.lock().unwrap();
-                        if s.stick_to_bottom {
-                            s.top_abs = max_top;
-                            s.subrow = 0.0;
-                        } else {
-                            // Keep viewport valid if content grew
-                            s.top_abs = s.top_abs.min(max_top);
+                    // The selection and search matches hold absolute rows, which evictions move
+                    if !selection.regions.shift_up(evicted) {
+                        selection.dragging = false;
+                        selection_text = None;
+                    }
+                    if evicted > 0 {
+                        search.matches.retain(|m| m.1 >= evicted);
+                        for m in &mut search.matches {
+                            m.1 -= evicted;
+                            m.3 -= evicted;
+                        }
+                    }
+                    
+                    // Answer terminal queries (DECRQM, ...)
+                    if !replies.is_empty() {
+                        if let Err(e) = pty.write(&replies) {
+                            error!("Failed to write reply to PTY: {}", e);
+                        }
+                    }
+                    
+                    // Several BELs in one chunk or in quick succession ring once
+                    if bells > 0 {
+                        let now = Instant::now();
+                        if bell_rings(last_bell, now) {
+                            last_bell = Some(now);
+                            if let Some(until) = ring_bell(config.appearance.bell_style, &config.general.bell, &window, window_focused) {
+                                bell_flash_until = Some(until);
+                            }
                         }
                     }
                     
-                    // Get text snapshot from grid and update cursor
+                    // Follow the new output according to the scroll policy
                     {
-                        let g = grid.lock().unwrap();
-                        let cells = g.get_cells_for_display();
-                        let snapshot = g.get_display_content();
-                        let mut r = renderer.lock().unwrap();
-                        r.set_cells(cells, g.cols, g.rows);
-                        r.set_text(snapshot);
-                        r.set_cursor(g.x, g.y, true);
+                        let term = terminal.lock().unwrap();
+                        let g = &term.grid;
+                        let max_top = g.scrollable_lines().saturating_sub(g.rows);
+                        follow_output(config.general.scroll_behavior, &mut scroll.lock().unwrap(), max_top, evicted, selection.dragging);
+                    }
+                    
+                    // Redraw only when the output changed a row or moved the
+                    // cursor; the frame picks up the dirty rows
+                    let changed = {
+                        let term = terminal.lock().unwrap();
+                        let g = &term.grid;
+                        let r = renderer.lock().unwrap();
+                        g.has_dirty_rows() || g.viewport_cursor(r.viewport_top_row, g.rows) != r.cursor()
+                    };
+                    if changed {
+                        frame_pacer.request_frame(Instant::now(), true);
                     }
-                    window.request_redraw();
                 }
+
+                // Output parsed while hidden is already in the grid, so
+                // showing only needs a redraw
+                UserEvent::ToggleWindow => match window_toggle.toggle() {
+                    ToggleAction::Show => {
+                        window.set_visible(true);
+                        window.focus_window();
+                        window.request_redraw();
+                    }
+                    ToggleAction::Focus => window.focus_window(),
+                    ToggleAction::Hide => window.set_visible(false),
+                },
             },
             
             Event::WindowEvent { event, .. } => match event {
                 WindowEvent::CloseRequested => {
                     info!("Close requested");
-                    elwt.exit();
+                    if close_confirm::should_confirm_close(config.general.confirm_close, pty.has_foreground_job(), 1) {
+                        confirming_close = true;
+                        window.request_redraw();
+                    } else {
+                        elwt.exit();
+                    }
+                }
+                
+                WindowEvent::Focused(focused) => {
+                    window_focused = focused;
+                    window_toggle.set_focused(focused);
                 }
                 
                 WindowEvent::ModifiersChanged(new_mods) => {
@@ -276,22 +727,39 @@ async fn run(args: Args) -> Result<()> {
                     cursor_position = (position.x as f32, position.y as f32);
                     // If dragging, update selection end
                     if selection.dragging {
-                        if let Some(mut region) = selection.region {
-                            let (cw, ch) = {
+                        if let Some(region) = selection.regions.last_mut() {
+                            let (cw, ch, pad) = {
                                 let r = renderer.lock().unwrap();
-                                (r.cell_width, r.cell_height)
+                                (r.cell_width, r.cell_height, r.padding_px())
                             };
                             let (col, row) = pixels_to_cell(
                                 cursor_position.0,
                                 cursor_position.1,
                                 cw,
-                                ch
+                                ch,
+                                pad
                             );
-                            region.end = (col, row);
-                            selection.region = Some(region);
-                            window.request_redraw();
+                            let col = renderer.lock().unwrap().display_col(row, col);
+                            // Selections hold absolute rows so they stay on their text while scrolling
+                            region.end = (col, scroll.lock().unwrap().top_abs + row);
+                            // Mice can report far faster than frames are drawn
+                            frame_pacer.request_frame(Instant::now(), true);
                         }
                     }
+                    
+                    // Link scans are cached per line, so this is cheap on every move
+                    let over_link = is_url_click(modifiers) && {
+                        let r = renderer.lock().unwrap();
+                        let (col, row) = pixels_to_cell(cursor_position.0, cursor_position.1, r.cell_width, r.cell_height, r.padding_px());
+                        let col = r.display_col(row, col);
+                        drop(r);
+                        let row = scroll.lock().unwrap().top_abs + row;
+                        terminal.lock().unwrap().grid.link_at(col, row).is_some()
+                    };
+                    if over_link != hovering_link {
+                        hovering_link = over_link;
+                        window.set_cursor_icon(if over_link { CursorIcon::Pointer } else { CursorIcon::Default });
+                    }
                 }
                 
                 WindowEvent::MouseWheel { delta, .. } => {
@@ -304,6 +772,21 @@ async fn run(args: Args) -> Result<()> {
                         }
                     };
                     
+                    // The alt screen has no scrollback: translate the wheel into arrow keys
+                    let (alt_screen, app_cursor) = {
+                        let term = terminal.lock().unwrap();
+                        (term.grid.is_alt_screen(), term.grid.modes.app_cursor_keys)
+                    };
+                    if alt_screen {
+                        let arrows = wheel_arrows(rows_delta, app_cursor);
+                        if !arrows.is_empty() {
+                            if let Err(e) = pty.write(&arrows) {
+                                error!("Failed to write to PTY: {}", e);
+                            }
+                        }
+                        return;
+                    }
+                    
                     {
                         let mut s = scroll.lock().unwrap();
                         // Immediate response + inertia kick
@@ -314,158 +797,140 @@ async fn run(args: Args) -> Result<()> {
                         s.stick_to_bottom = false;
                     }
                     
-                    window.request_redraw();
+                    frame_pacer.request_frame(Instant::now(), true);
                 }
                 
-                WindowEvent::MouseInput { state, button, .. } => {
-                    if button == MouseButton::Left {
-                        if state == ElementState::Pressed {
-                            // Calculate cell position
-                            let (cw, ch) = {
-                                let r = renderer.lock().unwrap();
-                                (r.cell_width, r.cell_height)
-                            };
-                            let (col, row) = pixels_to_cell(
-                                cursor_position.0,
-                                cursor_position.1,
-                                cw,
-                                ch
-                            );
-                            
-                            // Check for Cmd+Click on URL
-                            if modifiers.super_key() {
-                                let g = grid.lock().unwrap();
-                                if let Some(url) = detect_url_at_position(&g, col, row) {
-                                    info!("Opening URL: {}", url);
-                                    // Open URL in default browser
-                                    #[cfg(target_os = "macos")]
-                                    {
-                                        let _ = std::process::Command::new("open")
-                                            .arg(&url)
-                                            .spawn();
-                                    }
-                                    return; // Don't process as normal click
-                                }
+                WindowEvent::MouseInput { state, button: MouseButton::Left, .. } => {
+                    if state == ElementState::Pressed {
+                        // Calculate cell position
+                        let (cw, ch, pad) = {
+                            let r = renderer.lock().unwrap();
+                            (r.cell_width, r.cell_height, r.padding_px())
+                        };
+                        let (col, row) = pixels_to_cell(
+                            cursor_position.0,
+                            cursor_position.1,
+                            cw,
+                            ch,
+                            pad
+                        );
+                        // Reordered RTL runs: the clicked column back to its cell
+                        let col = renderer.lock().unwrap().display_col(row, col);
+                        // The clicked row may be in scrollback; work in absolute rows
+                        let row = scroll.lock().unwrap().top_abs + row;
+                        
+                        // Check for Cmd+Click (Ctrl+Click off macOS) on URL
+                        if is_url_click(modifiers) {
+                            let term = terminal.lock().unwrap();
+                            if let Some(link) = term.grid.link_at(col, row) {
+                                open_url(&link.uri);
+                                return; // Don't process as normal click
                             }
-                            
-                            // Handle multi-click selection
-                            let now = Instant::now();
-                            const DOUBLE_CLICK_TIME: Duration = Duration::from_millis(500);
-                            
-                            // Check if this is a double or triple click
-                            if let Some(last_time) = selection.last_click_time {
-                                if let Some((last_col, last_row)) = selection.last_click_pos {
-                                    if now.duration_since(last_time) < DOUBLE_CLICK_TIME 
-                                       && last_col == col && last_row == row {
-                                        selection.click_count += 1;
-                                    } else {
-                                        selection.click_count = 1;
-                                    }
+                        }
+                        
+                        // Handle multi-click selection
+                        let now = Instant::now();
+                        const DOUBLE_CLICK_TIME: Duration = Duration::from_millis(500);
+                        
+                        // Check if this is a double or triple click
+                        if let Some(last_time) = selection.last_click_time {
+                            if let Some((last_col, last_row)) = selection.last_click_pos {
+                                if now.duration_since(last_time) < DOUBLE_CLICK_TIME 
+                                   && last_col == col && last_row == row {
+                                    selection.click_count += 1;
                                 } else {
                                     selection.click_count = 1;
                                 }
                             } else {
                                 selection.click_count = 1;
                             }
-                            
-                            selection.last_click_time = Some(now);
-                            selection.last_click_pos = Some((col, row));
-                            
-                            // Perform selection based on click count
-                            match selection.click_count {
-                                2 => {
-                                    // Double-click: select word
-                                    let g = grid.lock().unwrap();
-                                    let (start_col, end_col) = find_word_boundaries(&g, col, row);
-                                    selection.region = Some(Region {
-                                        start: (start_col, row),
-                                        end: (end_col, row)
-                                    });
-                                    selection.dragging = false; // Don't drag on double-click
-                                }
-                                3 => {
-                                    // Triple-click: select line
-                                    let g = grid.lock().unwrap();
-                                    let (start_col, end_col) = find_line_boundaries(&g, row);
-                                    selection.region = Some(Region {
-                                        start: (start_col, row),
-                                        end: (end_col, row)
-                                    });
-                                    selection.dragging = false; // Don't drag on triple-click
-                                    selection.click_count = 0; // Reset for next click
-                                }
-                                _ => {
-                                    // Single click: start normal selection
-                                    selection.dragging = true;
-                                    selection.region = Some(Region { 
-                                        start: (col, row), 
-                                        end: (col, row) 
-                                    });
-                                }
-                            }
-                            
-                            selection_text = None; // Clear old selection text
-                            window.request_redraw();
                         } else {
-                            // Mouse released - finalize selection
-                            selection.dragging = false;
-                            if let Some(region) = selection.region {
-                                let (x0, y0) = region.start;
-                                let (x1, y1) = region.end;
-                                let (minx, maxx) = if x0 <= x1 { (x0, x1) } else { (x1, x0) };
-                                let (miny, maxy) = if y0 <= y1 { (y0, y1) } else { (y1, y0) };
-                                let text = grid.lock().unwrap().get_text_in_region(minx, miny, maxx, maxy);
-                                // Trim trailing whitespace from selection
-                                let text = text.trim_end().to_string();
-                                if !text.is_empty() {
-                                    selection_text = Some(text.clone());
-                                    info!("Selected text: {} chars", text.len());
-                                } else {
-                                    // Clear selection if no text selected
-                                    selection.region = None;
-                                    window.request_redraw();
+                            selection.click_count = 1;
+                        }
+                        
+                        selection.last_click_time = Some(now);
+                        selection.last_click_pos = Some((col, row));
+                        
+                        // Perform selection based on click count
+                        match selection.click_count {
+                            2 => {
+                                // Double-click: select word
+                                let term = terminal.lock().unwrap();
+                                let (start_col, end_col) = find_word_boundaries(term.grid.row_cells(row), col, &config.general.word_chars);
+                                selection.regions = Selection::single(Region {
+                                    start: (start_col, row),
+                                    end: (end_col, row)
+                                });
+                                selection.dragging = false; // Don't drag on double-click
+                            }
+                            3 => {
+                                // Triple-click: select line
+                                let term = terminal.lock().unwrap();
+                                let (start_col, end_col) = find_line_boundaries(term.grid.row_cells(row));
+                                selection.regions = Selection::single(Region {
+                                    start: (start_col, row),
+                                    end: (end_col, row)
+                                });
+                                selection.dragging = false; // Don't drag on triple-click
+                                selection.click_count = 0; // Reset for next click
+                            }
+                            _ => {
+                                // Single click: start normal selection
+                                selection.dragging = true;
+                                selection.regions = Selection::single(Region { 
+                                    start: (col, row), 
+                                    end: (col, row) 
+                                });
+                            }
+                        }
+                        
+                        selection_text = None; // Clear old selection text
+                        window.request_redraw();
+                    } else {
+                        // Mouse released - finalize selection
+                        selection.dragging = false;
+                        if !selection.regions.is_empty() {
+                            let text = selection.regions.text(&terminal.lock().unwrap().grid);
+                            if let Some(text) = selection::finalize_selection_text(&text) {
+                                info!("Selected text: {} chars", text.len());
+                                // Copy-on-select into the primary selection (X11)
+                                if config.general.clipboard_access {
+                                    primary_selection::copy(&text);
                                 }
+                                selection_text = Some(text);
+                            } else {
+                                // Clear selection if no text selected
+                                selection.regions = Selection::default();
+                                window.request_redraw();
                             }
                         }
                     }
                 }
                 
-                WindowEvent::Resized(physical_size) => {
+                // Middle-click pastes the primary selection
+                WindowEvent::MouseInput { state: ElementState::Pressed, button: MouseButton::Middle, .. }
+                    if config.general.clipboard_access =>
+                {
+                    if let Some(text) = primary_selection::paste() {
+                        paste_to_pty(&pty, &text, bracketed_paste_enabled.load(Ordering::Relaxed));
+                        info!("Pasted primary selection: {} chars", text.len());
+                    }
+                }
+                
+                // Moving to a display with a different DPI changes the scale
+                // factor; both cases reread the window's size and scale
+                WindowEvent::Resized(_) | WindowEvent::ScaleFactorChanged { .. } => {
+                    let physical_size = window.inner_size();
                     let (cols, rows) = {
                         let mut r = renderer.lock().unwrap();
+                        r.set_scale_factor(window.scale_factor() as f32);
                         r.resize(physical_size);
                         
                         // Calculate cells based on actual font metrics
-                        let cols = ((physical_size.width as f32) / r.cell_width).floor().max(1.0) as u16;
-                        let rows = ((physical_size.height as f32) / r.cell_height).floor().max(1.0) as u16;
-                        (cols, rows)
+                        grid_size_for(physical_size.width, physical_size.height, r.cell_width, r.cell_height, r.padding_px())
                     };
                     
-                    // Update grid - preserve content
-                    {
-                        let mut g = grid.lock().unwrap();
-                        g.resize_preserve(cols as usize, rows as usize);
-                    }
-                    
-                    // Update PTY
-                    let _ = pty.resize(rows, cols);
-                    
-                    // Reset fractional scroll to avoid stale offsets after metrics change
-                    {
-                        let g = grid.lock().unwrap();
-                        let total = g.scrollback.len() + g.rows;
-                        let vis = g.rows;
-                        let max_top = total.saturating_sub(vis);
-                        
-                        let mut s = scroll.lock().unwrap();
-                        if s.stick_to_bottom {
-                            s.top_abs = max_top;
-                        } else {
-                            s.top_abs = s.top_abs.min(max_top);
-                        }
-                        s.subrow = 0.0;
-                        s.vel_rows_per_s = 0.0;
-                    }
+                    apply_resize((cols, rows), config.general.reflow_on_resize, &terminal, &pty, &scroll, &mut selection, &mut selection_text);
                     
                     window.request_redraw();
                 }
@@ -479,30 +944,161 @@ async fn run(args: Args) -> Result<()> {
                     },
                     ..
                 } => {
+                    // The close confirmation takes every key until it is answered
+                    if confirming_close {
+                        match physical_key {
+                            PhysicalKey::Code(KeyCode::Enter | KeyCode::NumpadEnter) => elwt.exit(),
+                            PhysicalKey::Code(KeyCode::Escape) => {
+                                confirming_close = false;
+                                window.request_redraw();
+                            }
+                            _ => {}
+                        }
+                        return;
+                    }
+                    
+                    // So does the missing-glyph notice, until dismissed
+                    if let Some(notice) = glyph_notice.take() {
+                        if let PhysicalKey::Code(code) = physical_key {
+                            glyph_notice = notice.on_key(code, || renderer.lock().unwrap().missing_glyphs().details());
+                        } else {
+                            glyph_notice = Some(notice);
+                        }
+                        window.request_redraw();
+                        return;
+                    }
+                    
+                    // Configurable key bindings take precedence; on the alt screen
+                    // scroll keys belong to the full-screen app instead
+                    let alt_screen = terminal.lock().unwrap().grid.is_alt_screen();
+                    if let PhysicalKey::Code(code) = physical_key {
+                        let action = keybindings
+                            .action_for(modifiers, code)
+                            .filter(|a| match a {
+                                Action::Scroll(_) => !alt_screen,
+                                // Search actions only apply while search is open
+                                Action::Search(_) => search.active,
+                                Action::ClearScreen
+                                | Action::CopyLastOutput
+                                | Action::SelectCommandOutput
+                                | Action::SaveScrollback { .. }
+                                | Action::SendBytes(_) => true,
+                            });
+                        if let Some(action) = action {
+                            match action {
+                                Action::Scroll(scroll_action) => {
+                                    let term = terminal.lock().unwrap();
+                                    let mut s = scroll.lock().unwrap();
+                                    apply_scroll_action(*scroll_action, &mut s, &term.grid, config.general.page_scroll_fraction);
+                                }
+                                Action::ClearScreen => {
+                                    // Unlike ⌘K: history stays and the shell isn't sent Ctrl-L
+                                    let mut term = terminal.lock().unwrap();
+                                    let g = &mut term.grid;
+                                    let keep = g.clear_pushes_to_scrollback;
+                                    if keep {
+                                        g.push_screen_to_scrollback();
+                                    }
+                                    g.clear_screen();
+                                    
+                                    // Pushed rows move the bottom down; stay on it
+                                    let max_top = g.scrollable_lines().saturating_sub(g.rows);
+                                    let mut s = scroll.lock().unwrap();
+                                    s.top_abs = if s.stick_to_bottom { max_top } else { s.top_abs.min(max_top) };
+                                    info!("Clear visible screen{}", if keep { " (kept in scrollback)" } else { "" });
+                                }
+                                Action::CopyLastOutput => {
+                                    match terminal.lock().unwrap().grid.last_command_output() {
+                                        Some(text) if !text.is_empty() => {
+                                            copy_to_clipboard(&text);
+                                            info!("Copied last command output ({} bytes)", text.len());
+                                        }
+                                        Some(_) => info!("Last command printed nothing; clipboard left alone"),
+                                        None => info!("No finished command to copy (needs OSC 133 shell integration)"),
+                                    }
+                                }
+                                Action::SelectCommandOutput => {
+                                    let row = {
+                                        let r = renderer.lock().unwrap();
+                                        pixels_to_cell(cursor_position.0, cursor_position.1, r.cell_width, r.cell_height, r.padding_px()).1
+                                    };
+                                    let row = scroll.lock().unwrap().top_abs + row;
+                                    let term = terminal.lock().unwrap();
+                                    match term.grid.output_block_for_row(row) {
+                                        Some((first, last)) => {
+                                            // Whole rows, copied like a mouse selection
+                                            selection.regions = Selection::single(Region {
+                                                start: (0, first),
+                                                end: (term.grid.cols - 1, last),
+                                            });
+                                            selection.dragging = false;
+                                            selection_text = selection::finalize_selection_text(&selection.regions.text(&term.grid));
+                                            if let Some(text) = selection_text.as_ref().filter(|_| config.general.clipboard_access) {
+                                                primary_selection::copy(text);
+                                            }
+                                            info!("Selected command output, rows {}..={}", first, last);
+                                        }
+                                        None => info!("No command output under the pointer (needs OSC 133 shell integration)"),
+                                    }
+                                }
+                                Action::SaveScrollback { ansi } => {
+                                    let term = terminal.lock().unwrap();
+                                    match save_scrollback(&term.grid, *ansi) {
+                                        Ok(path) => info!("Saved scrollback to {}", path.display()),
+                                        Err(e) => error!("Failed to save scrollback: {}", e),
+                                    }
+                                }
+                                Action::SendBytes(bytes) => {
+                                    write_key(&pty, bytes, &terminal, &scroll, config.general.scroll_behavior);
+                                }
+                                Action::Search(SearchAction::CopyMatchingLines) => {
+                                    let term = terminal.lock().unwrap();
+                                    let rows = selection::matching_rows(&search.matches).len();
+                                    let text = selection::matching_lines_text(&term.grid, &search.matches);
+                                    if rows > 0 {
+                                        copy_to_clipboard(&text);
+                                    }
+                                    info!("Copied {} matching lines", rows);
+                                }
+                                Action::Search(SearchAction::SelectAllMatches) => {
+                                    selection.regions = selection::selection_from_matches(&search.matches);
+                                    selection.dragging = false;
+                                    selection_text = (!selection.regions.is_empty())
+                                        .then(|| selection.regions.text(&terminal.lock().unwrap().grid));
+                                    info!("Selected {} matches", selection.regions.len());
+                                }
+                            }
+                            window.request_redraw();
+                            return;
+                        }
+                    }
+                    
+                    // Typing may bring the viewport back to the prompt
+                    let policy = config.general.scroll_behavior;
+                    let send = |bytes: &[u8]| write_key(&pty, bytes, &terminal, &scroll, policy);
+                    
                     // Handle Command-based shortcuts (macOS)
                     if modifiers.super_key() {
                         const STEP_PT: f32 = 1.0;
                         const DEFAULT_PT: f32 = 18.0;
                         
                         match physical_key {
-                            // Clear screen + scrollback: ⌘K
+                            // Clear screen + scrollback: ⌘K, always destructive
+                            // (clear_pushes_to_scrollback only covers the gentler clears)
                             PhysicalKey::Code(KeyCode::KeyK) => {
-                                // Clear grid and scrollback
                                 {
-                                    let mut g = grid.lock().unwrap();
-                                    g.clear_all();
+                                    let mut term = terminal.lock().unwrap();
+                                    let g = &mut term.grid;
                                     g.scrollback.clear();
-                                    g.x = 0;
-                                    g.y = 0;
-                                }
-                                {
-                                    let g = grid.lock().unwrap();
-                                    let cells = g.get_cells_for_display();
-                                    let content = g.get_display_content();
-                                    let mut r = renderer.lock().unwrap();
-                                    r.set_cells(cells, g.cols, g.rows);
-                                    r.set_text(content);
+                                    g.marks.clear();
+                                    g.clear_screen();
+                                    
+                                    let max_top = g.scrollable_lines().saturating_sub(g.rows);
+                                    let mut s = scroll.lock().unwrap();
+                                    s.top_abs = max_top;
+                                    s.stick_to_bottom = true;
                                 }
+                                // Clearing dirtied every row; the frame takes the new snapshot
                                 window.request_redraw();
                                 // Ask shell to repaint prompt (Ctrl-L)
                                 let _ = pty.write(b"\x0C");
@@ -518,7 +1114,7 @@ async fn run(args: Args) -> Result<()> {
                                     }
                                 } else {
                                     // If no selection and no shift, let Ctrl-C through for SIGINT
-                                    let _ = pty.write(b"\x03");
+                                    send(b"\x03");
                                 }
                             }
                             
@@ -528,11 +1124,13 @@ async fn run(args: Args) -> Result<()> {
                                 if search.active {
                                     info!("Search mode activated");
                                     // TODO: Show search UI overlay
+                                    restart_search(&mut search, &terminal.lock().unwrap().grid);
                                 } else {
                                     info!("Search mode deactivated");
                                     search.query.clear();
                                     search.matches.clear();
                                     search.current_match = None;
+                                    search.session = None;
                                 }
                                 window.request_redraw();
                             }
@@ -540,14 +1138,7 @@ async fn run(args: Args) -> Result<()> {
                             // Paste: ⌘V
                             PhysicalKey::Code(KeyCode::KeyV) => {
                                 if let Some(text) = paste_from_clipboard() {
-                                    // Respect bracketed paste if enabled
-                                    if bracketed_paste_enabled.load(Ordering::Relaxed) {
-                                        let _ = pty.write(b"\x1b[200~");
-                                        let _ = pty.write(text.as_bytes());
-                                        let _ = pty.write(b"\x1b[201~");
-                                    } else {
-                                        let _ = pty.write(text.as_bytes());
-                                    }
+                                    paste_to_pty(&pty, &text, bracketed_paste_enabled.load(Ordering::Relaxed));
                                     info!("Pasted from clipboard: {} chars", text.len());
                                 }
                             }
@@ -565,20 +1156,25 @@ async fn run(args: Args) -> Result<()> {
                             // Close window: ⌘W
                             PhysicalKey::Code(KeyCode::KeyW) => {
                                 info!("Close window requested");
-                                elwt.exit();
+                                if close_confirm::should_confirm_close(config.general.confirm_close, pty.has_foreground_job(), 1) {
+                                    confirming_close = true;
+                                    window.request_redraw();
+                                } else {
+                                    elwt.exit();
+                                }
                             }
                             
                             // Move to start/end of line: ⌘←/⌘→
                             PhysicalKey::Code(KeyCode::ArrowLeft) => {
-                                let _ = pty.write(b"\x01"); // Ctrl-A (beginning of line)
+                                send(b"\x01"); // Ctrl-A (beginning of line)
                             }
                             PhysicalKey::Code(KeyCode::ArrowRight) => {
-                                let _ = pty.write(b"\x05"); // Ctrl-E (end of line)
+                                send(b"\x05"); // Ctrl-E (end of line)
                             }
                             
                             // Delete to start of line: ⌘Backspace
                             PhysicalKey::Code(KeyCode::Backspace) => {
-                                let _ = pty.write(b"\x15"); // Ctrl-U
+                                send(b"\x15"); // Ctrl-U
                             }
                             
                             // Zoom controls
@@ -591,37 +1187,12 @@ async fn run(args: Args) -> Result<()> {
                                     
                                     // Recalculate cols/rows with new font size
                                     let size = window.inner_size();
-                                    let cols = ((size.width as f32) / r.cell_width).floor().max(1.0) as u16;
-                                    let rows = ((size.height as f32) / r.cell_height).floor().max(1.0) as u16;
+                                    let (cols, rows) = grid_size_for(size.width, size.height, r.cell_width, r.cell_height, r.padding_px());
                                     info!("Zoom in: font size {}", r.font_size());
                                     (cols, rows)
                                 };
                                 
-                                // Update grid - preserve content
-                                {
-                                    let mut g = grid.lock().unwrap();
-                                    g.resize_preserve(cols as usize, rows as usize);
-                                }
-                                
-                                // Update PTY
-                                let _ = pty.resize(rows, cols);
-                                
-                                // Reset fractional scroll to avoid stale offsets after zoom
-                                {
-                                    let g = grid.lock().unwrap();
-                                    let total = g.scrollback.len() + g.rows;
-                                    let vis = g.rows;
-                                    let max_top = total.saturating_sub(vis);
-                                    
-                                    let mut s = scroll.lock().unwrap();
-                                    if s.stick_to_bottom {
-                                        s.top_abs = max_top;
-                                    } else {
-                                        s.top_abs = s.top_abs.min(max_top);
-                                    }
-                                    s.subrow = 0.0;
-                                    s.vel_rows_per_s = 0.0;
-                                }
+                                apply_resize((cols, rows), config.general.reflow_on_resize, &terminal, &pty, &scroll, &mut selection, &mut selection_text);
                                 
                                 window.request_redraw();
                             }
@@ -634,37 +1205,12 @@ async fn run(args: Args) -> Result<()> {
                                     
                                     // Recalculate cols/rows with new font size
                                     let size = window.inner_size();
-                                    let cols = ((size.width as f32) / r.cell_width).floor().max(1.0) as u16;
-                                    let rows = ((size.height as f32) / r.cell_height).floor().max(1.0) as u16;
+                                    let (cols, rows) = grid_size_for(size.width, size.height, r.cell_width, r.cell_height, r.padding_px());
                                     info!("Zoom out: font size {}", r.font_size());
                                     (cols, rows)
                                 };
                                 
-                                // Update grid - preserve content
-                                {
-                                    let mut g = grid.lock().unwrap();
-                                    g.resize_preserve(cols as usize, rows as usize);
-                                }
-                                
-                                // Update PTY
-                                let _ = pty.resize(rows, cols);
-                                
-                                // Reset fractional scroll to avoid stale offsets after zoom
-                                {
-                                    let g = grid.lock().unwrap();
-                                    let total = g.scrollback.len() + g.rows;
-                                    let vis = g.rows;
-                                    let max_top = total.saturating_sub(vis);
-                                    
-                                    let mut s = scroll.lock().unwrap();
-                                    if s.stick_to_bottom {
-                                        s.top_abs = max_top;
-                                    } else {
-                                        s.top_abs = s.top_abs.min(max_top);
-                                    }
-                                    s.subrow = 0.0;
-                                    s.vel_rows_per_s = 0.0;
-                                }
+                                apply_resize((cols, rows), config.general.reflow_on_resize, &terminal, &pty, &scroll, &mut selection, &mut selection_text);
                                 
                                 window.request_redraw();
                             }
@@ -676,37 +1222,12 @@ async fn run(args: Args) -> Result<()> {
                                     
                                     // Recalculate cols/rows with new font size
                                     let size = window.inner_size();
-                                    let cols = ((size.width as f32) / r.cell_width).floor().max(1.0) as u16;
-                                    let rows = ((size.height as f32) / r.cell_height).floor().max(1.0) as u16;
+                                    let (cols, rows) = grid_size_for(size.width, size.height, r.cell_width, r.cell_height, r.padding_px());
                                     info!("Zoom reset: font size {}", DEFAULT_PT);
                                     (cols, rows)
                                 };
                                 
-                                // Update grid - preserve content
-                                {
-                                    let mut g = grid.lock().unwrap();
-                                    g.resize_preserve(cols as usize, rows as usize);
-                                }
-                                
-                                // Update PTY
-                                let _ = pty.resize(rows, cols);
-                                
-                                // Reset fractional scroll to avoid stale offsets after zoom reset
-                                {
-                                    let g = grid.lock().unwrap();
-                                    let total = g.scrollback.len() + g.rows;
-                                    let vis = g.rows;
-                                    let max_top = total.saturating_sub(vis);
-                                    
-                                    let mut s = scroll.lock().unwrap();
-                                    if s.stick_to_bottom {
-                                        s.top_abs = max_top;
-                                    } else {
-                                        s.top_abs = s.top_abs.min(max_top);
-                                    }
-                                    s.subrow = 0.0;
-                                    s.vel_rows_per_s = 0.0;
-                                }
+                                apply_resize((cols, rows), config.general.reflow_on_resize, &terminal, &pty, &scroll, &mut selection, &mut selection_text);
                                 
                                 window.request_redraw();
                             }
@@ -716,25 +1237,33 @@ async fn run(args: Args) -> Result<()> {
                         return;
                     }
                     
+                    // Kitty keyboard protocol, when the app has enabled it;
+                    // keys it leaves alone fall through to the legacy encoding
+                    let kitty_flags = terminal.lock().unwrap().grid.keyboard().flags();
+                    if let Some(seq) = key_encoding::encode_kitty(kitty_flags, physical_key, &logical_key, modifiers) {
+                        send(&seq);
+                        return;
+                    }
+                    
                     // Handle Option-based shortcuts (word navigation)
                     if modifiers.alt_key() {
                         match physical_key {
                             // Option+← / → : back/forward by word
                             PhysicalKey::Code(KeyCode::ArrowLeft) => {
-                                let _ = pty.write(b"\x1bb"); // ESC b (backward word)
+                                send(b"\x1bb"); // ESC b (backward word)
                             }
                             PhysicalKey::Code(KeyCode::ArrowRight) => {
-                                let _ = pty.write(b"\x1bf"); // ESC f (forward word)
+                                send(b"\x1bf"); // ESC f (forward word)
                             }
                             
                             // Option+Backspace: delete previous word
                             PhysicalKey::Code(KeyCode::Backspace) => {
-                                let _ = pty.write(b"\x17"); // Ctrl-W
+                                send(b"\x17"); // Ctrl-W
                             }
                             
                             // Option+D: delete next word
                             PhysicalKey::Code(KeyCode::KeyD) => {
-                                let _ = pty.write(b"\x1bd"); // ESC d
+                                send(b"\x1bd"); // ESC d
                             }
                             
                             _ => {}
@@ -743,26 +1272,20 @@ async fn run(args: Args) -> Result<()> {
                         return;
                     }
                     
-                    // Handle Control shortcuts
+                    // Ctrl+<letter>: control code from the layout's letter
                     if modifiers.control_key() {
-                        match physical_key {
-                            PhysicalKey::Code(KeyCode::KeyC) => {
-                                let _ = pty.write(b"\x03"); // Ctrl-C (SIGINT)
-                                return;
-                            }
-                            PhysicalKey::Code(KeyCode::KeyD) => {
-                                let _ = pty.write(b"\x04"); // Ctrl-D (EOF)
-                                return;
-                            }
-                            PhysicalKey::Code(KeyCode::KeyZ) => {
-                                let _ = pty.write(b"\x1A"); // Ctrl-Z (suspend)
-                                return;
-                            }
-                            PhysicalKey::Code(KeyCode::KeyL) => {
-                                let _ = pty.write(b"\x0C"); // Ctrl-L (clear)
-                                return;
-                            }
-                            _ => {}
+                        if let Some(code) = key_encoding::control_code(physical_key, &logical_key) {
+                            send(&[code]);
+                            return;
+                        }
+                    }
+                    
+                    // Cursor and paging keys, in the app's cursor key mode
+                    if let PhysicalKey::Code(code) = physical_key {
+                        let app_cursor = terminal.lock().unwrap().grid.modes.app_cursor_keys;
+                        if let Some(seq) = key_encoding::cursor_key(code, app_cursor) {
+                            send(seq);
+                            return;
                         }
                     }
                     
@@ -773,65 +1296,7 @@ async fn run(args: Args) -> Result<()> {
                         PhysicalKey::Code(KeyCode::Backspace) => Some(b"\x7f"),
                         PhysicalKey::Code(KeyCode::Tab) => Some(b"\t"),
                         PhysicalKey::Code(KeyCode::Escape) => Some(b"\x1b"),
-                        PhysicalKey::Code(KeyCode::ArrowUp) => Some(b"\x1b[A"),
-                        PhysicalKey::Code(KeyCode::ArrowDown) => Some(b"\x1b[B"),
-                        PhysicalKey::Code(KeyCode::ArrowRight) => Some(b"\x1b[C"),
-                        PhysicalKey::Code(KeyCode::ArrowLeft) => Some(b"\x1b[D"),
                         
-                        // Scrollback controls
-                        PhysicalKey::Code(KeyCode::PageUp) => {
-                            {
-                                let mut s = scroll.lock().unwrap();
-                                let g = grid.lock().unwrap();
-                                let page_size = g.rows;
-                                s.top_abs = s.top_abs.saturating_sub(page_size);
-                                s.subrow = 0.0;
-                                s.stick_to_bottom = false;
-                            }
-                            window.request_redraw();
-                            None
-                        }
-                        PhysicalKey::Code(KeyCode::PageDown) => {
-                            {
-                                let mut s = scroll.lock().unwrap();
-                                let g = grid.lock().unwrap();
-                                let page_size = g.rows;
-                                let total_lines = g.scrollback.len() + g.rows;
-                                let max_top = total_lines.saturating_sub(g.rows);
-                                s.top_abs = (s.top_abs + page_size).min(max_top);
-                                s.subrow = 0.0;
-                                if s.top_abs == max_top {
-                                    s.stick_to_bottom = true;
-                                }
-                            }
-                            window.request_redraw();
-                            None
-                        }
-                        PhysicalKey::Code(KeyCode::Home) if modifiers.shift_key() => {
-                            // Shift+Home: scroll to top
-                            {
-                                let mut s = scroll.lock().unwrap();
-                                s.top_abs = 0;
-                                s.subrow = 0.0;
-                                s.stick_to_bottom = false;
-                            }
-                            window.request_redraw();
-                            None
-                        }
-                        PhysicalKey::Code(KeyCode::End) if modifiers.shift_key() => {
-                            // Shift+End: scroll to bottom
-                            {
-                                let mut s = scroll.lock().unwrap();
-                                let g = grid.lock().unwrap();
-                                let total_lines = g.scrollback.len() + g.rows;
-                                let max_top = total_lines.saturating_sub(g.rows);
-                                s.top_abs = max_top;
-                                s.subrow = 0.0;
-                                s.stick_to_bottom = true;
-                            }
-                            window.request_redraw();
-                            None
-                        }
                         _ => {
                             // Handle regular characters via logical key
                             if let Key::Character(s) = logical_key {
@@ -839,18 +1304,14 @@ async fn run(args: Args) -> Result<()> {
                                 if s == " " {
                                     info!("Sending space character to PTY");
                                 }
-                                if let Err(e) = pty.write(s.as_bytes()) {
-                                    error!("Failed to write to PTY: {}", e);
-                                }
+                                send(s.as_bytes());
                             }
                             None
                         }
                     };
                     
                     if let Some(s) = seq {
-                        if let Err(e) = pty.write(s) {
-                            error!("Failed to write to PTY: {}", e);
-                        }
+                        send(s);
                     }
                 }
                 
@@ -870,8 +1331,9 @@ async fn run(args: Args) -> Result<()> {
                         
                         // Convert whole rows from subrow safely with bounds-aware loops
                         let (total, vis) = {
-                            let g = grid.lock().unwrap();
-                            (g.scrollback.len() + g.rows, g.rows)
+                            let term = terminal.lock().unwrap();
+                            let g = &term.grid;
+                            (g.scrollable_lines(), g.rows)
                         };
                         let max_top = total.saturating_sub(vis);
                         
@@ -925,45 +1387,102 @@ async fn run(args: Args) -> Result<()> {
                         let mut r = renderer.lock().unwrap();
                         r.set_viewport(top_abs, y_offset_px);
                         
-                        // Update text content based on viewport
-                        let (cells, content, cursor_x, cursor_y, cols, rows) = {
-                            let g = grid.lock().unwrap();
-                            (g.get_cells_for_display(), g.get_display_content(), g.x, g.y, g.cols, g.rows)
+                        // Update text content when the grid or the viewport changed
+                        let mut term = terminal.lock().unwrap();
+                        let g = &mut term.grid;
+                        let dirty = g.take_dirty_rows();
+                        let history = g.history_len();
+                        // Scrolling dirties every row and can shift the history shown above them
+                        if shown_view != Some((top_abs, history)) || dirty.len() == g.rows {
+                            r.set_snapshot(g.snapshot_viewport(top_abs, g.rows));
+                            shown_view = Some((top_abs, history));
+                        } else if !dirty.is_empty() {
+                            // Dirty screen rows, as rows of the scrolled view
+                            let rows: Vec<usize> = dirty
+                                .iter()
+                                .filter_map(|&row| (history + row).checked_sub(top_abs))
+                                .filter(|&row| row < g.rows)
+                                .collect();
+                            r.set_snapshot_rows(g.snapshot_viewport(top_abs, g.rows), &rows);
+                        } else {
+                            // The cursor scrolls with its row and hides once that is out of view
+                            r.set_cursor(g.viewport_cursor(top_abs, g.rows));
+                        }
+                        // Marks belong to the primary screen's rows
+                        r.mark_rows = if g.is_alt_screen() {
+                            Vec::new()
+                        } else {
+                            g.marks.iter().map(|m| m.row).filter(|row| (top_abs..top_abs + g.rows).contains(row)).collect()
+                        };
+                        drop(term);
+                        r.set_visual_bell(bell_flash_until.is_some_and(|t| now < t));
+                        let overlay = if confirming_close {
+                            Some(close_confirm::PROMPT.to_string())
+                        } else {
+                            glyph_notice.as_ref().map(GlyphNotice::overlay_text)
                         };
-                        r.set_cells(cells, cols, rows);
-                        r.set_text(content);
-                        r.set_cursor(cursor_x, cursor_y, true);
+                        r.set_overlay(overlay.as_deref());
                         
                         // Update renderer with current selection for highlighting
-                        if let Some(region) = selection.region {
-                            r.selection = Some((region.start, region.end));
-                        } else {
-                            r.selection = None;
-                        }
+                        r.selections = selection.regions.regions().iter().map(|reg| (reg.start, reg.end)).collect();
+                        r.search_matches = search.matches.iter().map(|&(c0, r0, c1, r1)| ((c0, r0), (c1, r1))).collect();
+                        r.current_match = search.current_match;
                     }
                     
-                    // Keep animating if we have velocity
+                    // Keep animating if we have velocity, no faster than max_fps
+                    frame_pacer.frame_drawn(now);
                     if should_animate {
-                        window.request_redraw();
+                        frame_pacer.request_frame(now, true);
                     }
                     
                     if let Err(e) = renderer.lock().unwrap().render_frame() {
-                        match e.downcast_ref::<wgpu::SurfaceError>() {
-                            Some(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
+                        match e {
+                            // Skip this frame; the next vsync gets a fresh one
+                            RenderError::Timeout => window.request_redraw(),
+                            RenderError::Reconfigure => {
                                 let size = window.inner_size();
                                 renderer.lock().unwrap().resize(size);
                             }
-                            Some(wgpu::SurfaceError::OutOfMemory) => {
+                            RenderError::OutOfMemory => {
                                 error!("Out of memory");
                                 elwt.exit();
                             }
-                            _ => error!("Render error: {:?}", e),
+                            RenderError::Other(e) => error!("Render error: {:?}", e),
+                        }
+                    }
+
+                    {
+                        let mut r = renderer.lock().unwrap();
+                        if let Some(notice) = r.take_missing_glyph_notice() {
+                            warn!("{}", notice);
+                            glyph_notice = Some(GlyphNotice::Toast(notice));
+                            window.request_redraw();
                         }
                     }
                     
                     frame_count += 1;
                     info!("Frame {} presented", frame_count);
                     
+                    // First frame is up: this launch didn't crash on startup
+                    if let Some(sentinel) = launch_sentinel.take() {
+                        if let Err(e) = sentinel.mark_started() {
+                            warn!("Could not record successful startup: {}", e);
+                        }
+                    }
+                    profile.mark("first frame");
+                    
+                    // The first prompt is on screen: startup is over
+                    if profile.has_mark("first prompt output") && !profile.has_mark("first prompt shown") {
+                        profile.mark("first prompt shown");
+                        if args.measure_startup {
+                            print!("{}", profile.report());
+                            std::process::exit(0);
+                        }
+                        if args.perf {
+                            info!("{}", profile.report());
+                        }
+                    }
+                    
                     if args.smoketest {
                         if frame_count >= 3 {
                             info!("Smoketest passed: {} frames", frame_count);
@@ -982,6 +1501,30 @@ async fn run(args: Args) -> Result<()> {
                     error!("Smoketest failed: timeout");
                     std::process::exit(1);
                 }
+                
+                let now = Instant::now();
+                // Wake up once more to end the visual bell flash
+                if bell_flash_until.is_some_and(|until| now >= until) {
+                    bell_flash_until = None;
+                    frame_pacer.request_frame(now, false);
+                }
+                // One chunk of a running search per pass, so input stays responsive
+                let mut searching = false;
+                if let Some(session) = search.session.as_mut().filter(|s| !s.is_done()) {
+                    let progress = session.poll(&terminal.lock().unwrap().grid);
+                    search.matches = session.matches().iter().rev().map(|m| (m.start.0, m.start.1, m.end.0, m.end.1)).collect();
+                    searching = matches!(progress, SearchProgress::Searching { .. });
+                    frame_pacer.request_frame(now, false);
+                }
+                if frame_pacer.take_due(now) {
+                    window.request_redraw();
+                }
+                let wake = [bell_flash_until, frame_pacer.deadline()].into_iter().flatten().min();
+                let wake = if searching { Some(now) } else { wake };
+                elwt.set_control_flow(match wake {
+                    Some(t) => ControlFlow::WaitUntil(t),
+                    None => ControlFlow::Wait,
+                });
             }
             
             _ => {}
@@ -991,10 +1534,303 @@ async fn run(args: Args) -> Result<()> {
     Ok(())
 }
 
-fn spawn_pty_reader(mut pty_rx: mpsc::UnboundedReceiver<Vec<u8>>, proxy: EventLoopProxy<UserEvent>) {
+// Heuristic for "the shell is waiting for input": the text left of the
+// cursor ends in a typical prompt character
+fn looks_like_prompt(grid: &Grid) -> bool {
+    if grid.x() == 0 {
+        return false;
+    }
+    let before_cursor = grid.get_text_in_region(0, grid.y(), grid.x() - 1, grid.y());
+    before_cursor
+        .trim_end()
+        .ends_with(['$', '%', '#', '>', '❯', '➜', 'λ'])
+}
+
+// Startup ends at the first prompt; after that the screen isn't scanned for one
+fn note_first_prompt(profile: &mut StartupProfile, grid: &Grid) {
+    if !profile.has_mark("first prompt output") && looks_like_prompt(grid) {
+        profile.mark("first prompt output");
+    }
+}
+
+// Collect PTY output into `inbox`; only the first chunk of a batch wakes
+// the event loop, which then parses the whole batch at once
+fn spawn_pty_reader(mut pty_rx: mpsc::UnboundedReceiver<Vec<u8>>, inbox: PtyInbox, proxy: EventLoopProxy<UserEvent>) {
     std::thread::spawn(move || {
         while let Some(data) = pty_rx.blocking_recv() {
-            let _ = proxy.send_event(UserEvent::PtyData(data));
+            if inbox.push(&data) {
+                let _ = proxy.send_event(UserEvent::PtyData);
+            }
         }
     });
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pixels_map_to_cells_past_the_padding() {
+        // 10x20 cells behind 20px of padding
+        assert_eq!(pixels_to_cell(20.0, 20.0, 10.0, 20.0, 20.0), (0, 0));
+        assert_eq!(pixels_to_cell(29.9, 39.9, 10.0, 20.0, 20.0), (0, 0));
+        assert_eq!(pixels_to_cell(30.0, 40.0, 10.0, 20.0, 20.0), (1, 1));
+        assert_eq!(pixels_to_cell(125.0, 95.0, 10.0, 20.0, 20.0), (10, 3));
+        // Inside the padding clamps to the first row and column
+        assert_eq!(pixels_to_cell(5.0, 5.0, 10.0, 20.0, 20.0), (0, 0));
+        assert_eq!(pixels_to_cell(55.0, 0.0, 10.0, 20.0, 20.0), (3, 0));
+    }
+
+    #[test]
+    fn pixels_map_to_cells_at_scale_two() {
+        // 9x18pt cells and 12pt padding at scale 2.0, all in physical pixels
+        let (cw, ch, pad) = (18.0, 36.0, 24.0);
+        // The point (40, 49) is inside cell (3, 2)
+        assert_eq!(pixels_to_cell(40.0 * 2.0, 49.0 * 2.0, cw, ch, pad), (3, 2));
+        // Logical coordinates against physical metrics would hit the wrong cell
+        assert_eq!(pixels_to_cell(40.0, 49.0, cw, ch, pad), (0, 0));
+        assert_eq!(pixels_to_cell(24.0 + 18.0 * 79.5, 24.0 + 36.0 * 23.5, cw, ch, pad), (79, 23));
+    }
+
+    #[test]
+    fn bells_coalesce_but_a_steady_stream_keeps_ringing() {
+        let start = Instant::now();
+        let ms = |n| start + Duration::from_millis(n);
+        assert!(bell_rings(None, start));
+        assert!(!bell_rings(Some(start), ms(100)));
+        assert!(bell_rings(Some(start), ms(150)));
+
+        // A bell every 100 ms: only rings are remembered, so every other one rings
+        let mut last = None;
+        let rang: Vec<u64> = (0..6).map(|i| i * 100).filter(|&t| {
+            let rings = bell_rings(last, ms(t));
+            if rings {
+                last = Some(ms(t));
+            }
+            rings
+        }).collect();
+        assert_eq!(rang, [0, 200, 400]);
+    }
+
+    #[test]
+    fn the_first_prompt_ends_startup_once() {
+        let mut term = Terminal::with_grid(Grid::new(20, 4, 100));
+        let mut profile = StartupProfile::new();
+        term.advance(b"Last login: today\r\n");
+        note_first_prompt(&mut profile, &term.grid);
+        assert!(!profile.has_mark("first prompt output"));
+
+        term.advance(b"~/src $ ");
+        assert!(looks_like_prompt(&term.grid));
+        note_first_prompt(&mut profile, &term.grid);
+        assert!(profile.has_mark("first prompt output"));
+
+        // Later prompts don't add another milestone
+        term.advance(b"ls\r\n~/src % ");
+        note_first_prompt(&mut profile, &term.grid);
+        assert_eq!(profile.report().matches("first prompt output").count(), 1);
+    }
+
+    #[test]
+    fn wheel_on_alt_screen_sends_capped_arrows_in_cursor_key_mode() {
+        assert_eq!(wheel_arrows(-3.0, false), b"\x1b[A".repeat(3));
+        assert_eq!(wheel_arrows(2.4, true), b"\x1bOB".repeat(2));
+        assert!(wheel_arrows(0.2, false).is_empty());
+        // A huge line delta from a free-spinning wheel is clamped
+        assert_eq!(wheel_arrows(-1e9, true), b"\x1bOA".repeat(MAX_WHEEL_ARROWS));
+        assert_eq!(wheel_arrows(f32::INFINITY, false), b"\x1b[B".repeat(MAX_WHEEL_ARROWS));
+    }
+
+    #[test]
+    fn only_known_url_schemes_are_opened() {
+        for url in ["http://example.com", "https://x.io/a?b=1&c=2", "HTTPS://EXAMPLE.COM", "ftp://h/f", "mailto:a@b.c"] {
+            assert!(is_allowed_url_scheme(url), "{url}");
+        }
+        for url in ["javascript:alert(1)", "ssh://host", "vscode://file/x", "data:text/html,x", "example.com", "", ":http",
+                    "file:///tmp/x", "FILE:///C:/Windows/System32/calc.exe", "file:payload.exe"] {
+            assert!(!is_allowed_url_scheme(url), "{url}");
+        }
+    }
+
+    #[test]
+    fn line_scroll_binding_moves_one_line_and_clamps() {
+        let cfg = the_dev_terminal_core::config::KeybindingsConfig {
+            scroll_line_up: "ctrl+shift+up".into(),
+            scroll_line_down: "ctrl+shift+down".into(),
+            ..Default::default()
+        };
+        let bindings = KeyBindings::from_config(&cfg);
+        let mods = ModifiersState::CONTROL | ModifiersState::SHIFT;
+        let scroll = |key, s: &mut ScrollState, grid: &Grid| match bindings.action_for(mods, key) {
+            Some(Action::Scroll(action)) => apply_scroll_action(*action, s, grid, 1.0),
+            other => panic!("{key:?} is bound to {other:?}"),
+        };
+
+        // 10 lines of history on a 5-row screen: tops run from 0 to 10
+        let mut term = Terminal::with_grid(Grid::new(10, 5, 100));
+        for i in 0..15 {
+            term.advance(format!("{i}\r\n").as_bytes());
+        }
+        let max_top = term.grid.scrollable_lines() - term.grid.rows;
+        let mut s = scroll_at(max_top, true);
+        scroll(KeyCode::ArrowUp, &mut s, &term.grid);
+        assert_eq!((s.top_abs, s.stick_to_bottom), (max_top - 1, false));
+        scroll(KeyCode::ArrowDown, &mut s, &term.grid);
+        assert_eq!((s.top_abs, s.stick_to_bottom), (max_top, true));
+        scroll(KeyCode::ArrowDown, &mut s, &term.grid);
+        assert_eq!(s.top_abs, max_top);
+
+        let mut s = scroll_at(1, false);
+        scroll(KeyCode::ArrowUp, &mut s, &term.grid);
+        scroll(KeyCode::ArrowUp, &mut s, &term.grid);
+        assert_eq!(s.top_abs, 0);
+    }
+
+    #[test]
+    fn page_scroll_uses_the_configured_fraction() {
+        let mut term = Terminal::with_grid(Grid::new(10, 10, 100));
+        for i in 0..40 {
+            term.advance(format!("{i}\r\n").as_bytes());
+        }
+        let mut s = scroll_at(20, false);
+        apply_scroll_action(ScrollAction::PageUp, &mut s, &term.grid, 0.5);
+        assert_eq!(s.top_abs, 15);
+        apply_scroll_action(ScrollAction::PageUp, &mut s, &term.grid, 2.0);
+        assert_eq!(s.top_abs, 0);
+        // Never less than a line
+        apply_scroll_action(ScrollAction::PageDown, &mut s, &term.grid, 0.01);
+        assert_eq!(s.top_abs, 1);
+    }
+
+    fn scroll_at(top_abs: usize, stick_to_bottom: bool) -> ScrollState {
+        ScrollState { top_abs, subrow: 0.5, vel_rows_per_s: 3.0, stick_to_bottom, last_t: Instant::now() }
+    }
+
+    #[test]
+    fn output_follows_per_policy() {
+        // At the bottom (old max_top 10), output grows the history to 12
+        let mut s = scroll_at(10, true);
+        follow_output(ScrollBehavior::Always, &mut s, 12, 0, false);
+        assert_eq!((s.top_abs, s.stick_to_bottom), (12, true));
+        let mut s = scroll_at(10, true);
+        follow_output(ScrollBehavior::WhenAtBottom, &mut s, 12, 0, false);
+        assert_eq!((s.top_abs, s.stick_to_bottom), (12, true));
+        let mut s = scroll_at(10, true);
+        follow_output(ScrollBehavior::OnKeystroke, &mut s, 12, 0, false);
+        assert_eq!((s.top_abs, s.stick_to_bottom), (10, false));
+
+        // Scrolled up
+        let mut s = scroll_at(4, false);
+        follow_output(ScrollBehavior::Always, &mut s, 12, 0, false);
+        assert_eq!((s.top_abs, s.stick_to_bottom), (12, true));
+        for policy in [ScrollBehavior::WhenAtBottom, ScrollBehavior::OnKeystroke] {
+            let mut s = scroll_at(4, false);
+            follow_output(policy, &mut s, 12, 0, false);
+            assert_eq!((s.top_abs, s.stick_to_bottom), (4, false), "{policy:?}");
+            // Evicted lines move the viewport with its text
+            follow_output(policy, &mut s, 12, 3, false);
+            assert_eq!(s.top_abs, 1, "{policy:?}");
+        }
+    }
+
+    #[test]
+    fn a_selection_drag_pins_the_viewport() {
+        for policy in [ScrollBehavior::Always, ScrollBehavior::WhenAtBottom, ScrollBehavior::OnKeystroke] {
+            let mut s = scroll_at(10, true);
+            follow_output(policy, &mut s, 12, 2, true);
+            assert_eq!((s.top_abs, s.stick_to_bottom), (8, false), "{policy:?}");
+        }
+    }
+
+    #[test]
+    fn keystrokes_follow_per_policy() {
+        for (policy, follows) in [
+            (ScrollBehavior::Always, true),
+            (ScrollBehavior::WhenAtBottom, false),
+            (ScrollBehavior::OnKeystroke, true),
+        ] {
+            let mut s = scroll_at(4, false);
+            follow_keystroke(policy, &mut s, 12);
+            if follows {
+                assert_eq!((s.top_abs, s.stick_to_bottom), (12, true), "{policy:?}");
+                assert_eq!((s.subrow, s.vel_rows_per_s), (0.0, 0.0));
+            } else {
+                assert_eq!((s.top_abs, s.stick_to_bottom), (4, false), "{policy:?}");
+            }
+        }
+    }
+
+    // A 10x4 screen holding one 15-character line that wrapped, then a prompt
+    fn wrapped_screen() -> Terminal {
+        let mut term = Terminal::with_grid(Grid::new(10, 4, 100));
+        term.advance(b"abcdefghijklmno\r\n$ ");
+        term
+    }
+
+    fn screen_text(g: &Grid) -> Vec<String> {
+        (0..g.rows).map(|r| g.row_text(r).trim_end().to_string()).collect()
+    }
+
+    #[test]
+    fn reflow_shrink_rewraps_lines() {
+        let mut term = wrapped_screen();
+        let mut sel = Selection::default();
+        assert!(resize_grid(&mut term.grid, 5, 4, true, &mut sel));
+        assert_eq!(screen_text(&term.grid), ["abcde", "fghij", "klmno", "$"]);
+        assert_eq!((term.grid.x(), term.grid.y()), (2, 3));
+    }
+
+    #[test]
+    fn reflow_grow_joins_wrapped_rows() {
+        let mut term = wrapped_screen();
+        let mut sel = Selection::default();
+        resize_grid(&mut term.grid, 20, 4, true, &mut sel);
+        assert_eq!(screen_text(&term.grid), ["abcdefghijklmno", "$", "", ""]);
+        assert_eq!((term.grid.x(), term.grid.y()), (2, 1));
+    }
+
+    #[test]
+    fn reflow_keeps_the_cursor_on_its_character() {
+        let mut term = wrapped_screen();
+        // Cursor on the 'm' of the wrapped line
+        term.advance(b"\x1b[2;3H");
+        let mut sel = Selection::default();
+        resize_grid(&mut term.grid, 4, 5, true, &mut sel);
+        let (x, y) = (term.grid.x(), term.grid.y());
+        assert_eq!(term.grid.row_text(y).chars().nth(x), Some('m'));
+        resize_grid(&mut term.grid, 30, 5, true, &mut sel);
+        assert_eq!((term.grid.x(), term.grid.y()), (12, 0));
+    }
+
+    #[test]
+    fn selection_keeps_its_text_across_width_changes() {
+        let mut term = wrapped_screen();
+        // "cdefghijklmn", across the wrap
+        let mut sel = Selection::single(Region { start: (2, 0), end: (3, 1) });
+        assert_eq!(sel.text(&term.grid), "cdefghijklmn");
+        for cols in [5, 3, 20, 10] {
+            assert!(resize_grid(&mut term.grid, cols, 4, true, &mut sel), "{cols} columns");
+            assert_eq!(sel.text(&term.grid), "cdefghijklmn", "{cols} columns");
+        }
+
+        // Without reflow, growing keeps rows whole
+        let mut term = wrapped_screen();
+        let mut sel = Selection::single(Region { start: (0, 2), end: (1, 2) });
+        assert!(resize_grid(&mut term.grid, 20, 6, false, &mut sel));
+        assert_eq!(sel.text(&term.grid), "$ ");
+    }
+
+    #[test]
+    fn without_reflow_or_on_the_alt_screen_rows_are_cut() {
+        let mut term = wrapped_screen();
+        let mut sel = Selection::default();
+        resize_grid(&mut term.grid, 5, 4, false, &mut sel);
+        assert_eq!(screen_text(&term.grid), ["abcde", "klmno", "$", ""]);
+
+        let mut term = wrapped_screen();
+        term.advance(b"\x1b[?1049h\x1b[Habcdefghijklm");
+        resize_grid(&mut term.grid, 5, 4, true, &mut sel);
+        assert!(term.grid.is_alt_screen());
+        assert_eq!(screen_text(&term.grid), ["abcde", "klm", "", ""]);
+    }
+}
@@ -0,0 +1,245 @@
+use std::collections::BTreeSet;
+use the_dev_terminal_core::grid::Grid;
+
+/// A selected span of cells from `start` to `end` as (col, absolute row),
+/// scrollback rows first; either end may come first
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Region {
+    pub start: (usize, usize),
+    pub end: (usize, usize),
+}
+
+impl Region {
+    /// Bounding box as (min_col, min_row, max_col, max_row)
+    pub fn bounds(&self) -> (usize, usize, usize, usize) {
+        let (x0, y0) = self.start;
+        let (x1, y1) = self.end;
+        (x0.min(x1), y0.min(y1), x0.max(x1), y0.max(y1))
+    }
+
+    /// `start` and `end` in reading order
+    pub fn ordered(&self) -> ((usize, usize), (usize, usize)) {
+        let key = |(col, row): (usize, usize)| (row, col);
+        if key(self.start) <= key(self.end) {
+            (self.start, self.end)
+        } else {
+            (self.end, self.start)
+        }
+    }
+
+    // Sort key in buffer (reading) order
+    fn order_key(&self) -> (usize, usize) {
+        let ((col, row), _) = self.ordered();
+        (row, col)
+    }
+}
+
+/// One or more disjoint regions kept in buffer order.
+/// Mouse selection holds a single region; "select all matches" (and later
+/// hint-mode picks) hold several. Copying joins the regions with newlines.
+#[derive(Default, Clone, Debug)]
+pub struct Selection {
+    regions: Vec<Region>,
+}
+
+impl Selection {
+    pub fn single(region: Region) -> Self {
+        Self { regions: vec![region] }
+    }
+
+    /// Build from any regions, sorted into buffer order with duplicates dropped
+    pub fn from_regions(regions: impl IntoIterator<Item = Region>) -> Self {
+        let mut regions: Vec<Region> = regions.into_iter().collect();
+        regions.sort_by_key(Region::order_key);
+        regions.dedup_by_key(|r| r.bounds());
+        Self { regions }
+    }
+
+    pub fn regions(&self) -> &[Region] {
+        &self.regions
+    }
+
+    /// The region being extended by a mouse drag
+    pub fn last_mut(&mut self) -> Option<&mut Region> {
+        self.regions.last_mut()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.regions.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.regions.len()
+    }
+
+    /// Move every anchor with `map`; if any anchor has no new position the
+    /// whole selection is cleared rather than highlighting the wrong cells.
+    /// Returns whether the selection survived.
+    pub fn remap(&mut self, map: impl Fn((usize, usize)) -> Option<(usize, usize)>) -> bool {
+        let remapped: Option<Vec<Region>> = self
+            .regions
+            .iter()
+            .map(|r| Some(Region { start: map(r.start)?, end: map(r.end)? }))
+            .collect();
+        match remapped {
+            Some(regions) => {
+                self.regions = regions;
+                true
+            }
+            None => {
+                self.regions.clear();
+                false
+            }
+        }
+    }
+
+    /// Follow the text after `n` lines were evicted from the top of the
+    /// scrollback, which moves every absolute row up by `n`. Returns whether
+    /// the selection survived; it is cleared once an end is evicted.
+    pub fn shift_up(&mut self, n: usize) -> bool {
+        n == 0 || self.remap(|(col, row)| Some((col, row.checked_sub(n)?)))
+    }
+
+    /// Text of every region in order, one region per line
+    pub fn text(&self, grid: &Grid) -> String {
+        self.regions
+            .iter()
+            .map(|r| grid.get_text_absolute(r.start, r.end))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Trim trailing whitespace from the raw region text; a blank selection yields nothing
+pub fn finalize_selection_text(raw: &str) -> Option<String> {
+    let text = raw.trim_end();
+    if text.is_empty() {
+        None
+    } else {
+        Some(text.to_string())
+    }
+}
+
+/// Rows touched by any search match `(start_col, start_row, end_col, end_row)`
+/// in absolute rows, deduplicated and in buffer order
+pub fn matching_rows(matches: &[(usize, usize, usize, usize)]) -> Vec<usize> {
+    let rows: BTreeSet<usize> = matches
+        .iter()
+        .flat_map(|&(_, r0, _, r1)| r0.min(r1)..=r0.max(r1))
+        .collect();
+    rows.into_iter().collect()
+}
+
+/// Full text of every row that contains a match, trailing blanks trimmed
+pub fn matching_lines_text(grid: &Grid, matches: &[(usize, usize, usize, usize)]) -> String {
+    matching_rows(matches)
+        .into_iter()
+        .filter(|&row| row < grid.scrollable_lines())
+        .map(|row| grid.row_text(row).trim_end().to_string())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Every search match as its own selection region
+pub fn selection_from_matches(matches: &[(usize, usize, usize, usize)]) -> Selection {
+    Selection::from_regions(matches.iter().map(|&(c0, r0, c1, r1)| Region {
+        start: (c0, r0),
+        end: (c1, r1),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use the_dev_terminal_core::Terminal;
+
+    // Rows "alpha beta", "gamma", "beta delta", "epsilon" on a 12x4 screen
+    fn grid() -> Grid {
+        let mut term = Terminal::with_grid(Grid::new(12, 4, 10));
+        term.advance(b"alpha beta\r\ngamma\r\nbeta delta\r\nepsilon");
+        term.grid
+    }
+
+    #[test]
+    fn selection_text_loses_only_its_trailing_blanks() {
+        assert_eq!(finalize_selection_text("abc   \n   \n").as_deref(), Some("abc"));
+        assert_eq!(finalize_selection_text("  indented\n\nnext\t ").as_deref(), Some("  indented\n\nnext"));
+        assert_eq!(finalize_selection_text(" \n\t\n"), None);
+        assert_eq!(finalize_selection_text(""), None);
+    }
+
+    #[test]
+    fn shift_up_follows_evicted_lines() {
+        let mut sel = Selection::from_regions([
+            Region { start: (2, 5), end: (4, 7) },
+            Region { start: (0, 9), end: (3, 9) },
+        ]);
+        assert!(sel.shift_up(0));
+        assert!(sel.shift_up(3));
+        assert_eq!(
+            sel.regions(),
+            [Region { start: (2, 2), end: (4, 4) }, Region { start: (0, 6), end: (3, 6) }]
+        );
+    }
+
+    #[test]
+    fn shift_up_past_the_start_clears_the_selection() {
+        // The end would survive, but half a selection would copy the wrong text
+        let mut sel = Selection::single(Region { start: (3, 2), end: (1, 6) });
+        assert!(!sel.shift_up(3));
+        assert!(sel.is_empty());
+
+        // Dragged upwards: the start is the lower anchor
+        let mut sel = Selection::single(Region { start: (1, 6), end: (3, 2) });
+        assert!(!sel.shift_up(3));
+        assert!(sel.is_empty());
+    }
+
+    #[test]
+    fn shift_up_past_the_whole_selection_clears_it() {
+        let mut sel = Selection::from_regions([
+            Region { start: (0, 1), end: (5, 2) },
+            Region { start: (0, 4), end: (2, 4) },
+        ]);
+        assert!(!sel.shift_up(10));
+        assert!(sel.is_empty());
+        // Nothing left to lose
+        assert!(sel.shift_up(1));
+    }
+
+    #[test]
+    fn matching_lines_are_deduplicated_and_in_order() {
+        // Two matches on row 2, given out of order, and one on row 0
+        let matches = [(5, 2, 9, 2), (0, 0, 4, 0), (0, 2, 3, 2)];
+        assert_eq!(matching_rows(&matches), [0, 2]);
+        assert_eq!(matching_lines_text(&grid(), &matches), "alpha beta\nbeta delta");
+        // A match spanning rows takes every row it touches
+        assert_eq!(matching_rows(&[(8, 1, 2, 3), (0, 2, 1, 2)]), [1, 2, 3]);
+        assert_eq!(matching_lines_text(&grid(), &[]), "");
+    }
+
+    #[test]
+    fn regions_sort_into_buffer_order_without_duplicates() {
+        let sel = Selection::from_regions([
+            Region { start: (0, 2), end: (3, 2) },
+            Region { start: (6, 0), end: (9, 0) },
+            // The same cells as the first, dragged the other way
+            Region { start: (3, 2), end: (0, 2) },
+            Region { start: (0, 0), end: (4, 0) },
+        ]);
+        let starts: Vec<_> = sel.regions().iter().map(|r| r.ordered().0).collect();
+        assert_eq!(starts, [(0, 0), (6, 0), (0, 2)]);
+    }
+
+    #[test]
+    fn every_region_is_extracted_on_its_own_line() {
+        let g = grid();
+        let sel = selection_from_matches(&[(0, 2, 3, 2), (6, 0, 9, 0), (0, 0, 4, 0)]);
+        assert_eq!(sel.len(), 3);
+        assert_eq!(sel.text(&g), "alpha\nbeta\nbeta");
+
+        // A region across rows keeps its line break
+        let sel = Selection::from_regions([Region { start: (6, 0), end: (4, 1) }, Region { start: (0, 3), end: (6, 3) }]);
+        assert_eq!(sel.text(&g), "beta\ngamma\nepsilon");
+    }
+}
@@ -0,0 +1,117 @@
+use global_hotkey::hotkey::HotKey;
+use global_hotkey::{GlobalHotKeyEvent, GlobalHotKeyManager, HotKeyState};
+use std::sync::Mutex;
+use tracing::warn;
+use winit::event_loop::EventLoopProxy;
+
+/// What the toggle hotkey should do to the window
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ToggleAction {
+    /// Hidden: show it and take focus
+    Show,
+    /// Visible behind other windows: bring it to the front
+    Focus,
+    /// Visible and focused: hide it
+    Hide,
+}
+
+/// Visibility of a window driven by the global toggle hotkey.
+/// Hiding only unmaps the window; the PTY and parser keep running.
+pub struct WindowToggle {
+    visible: bool,
+    focused: bool,
+}
+
+impl WindowToggle {
+    pub fn new() -> Self {
+        Self { visible: true, focused: true }
+    }
+
+    pub fn set_focused(&mut self, focused: bool) {
+        self.focused = focused;
+    }
+
+    /// Advance on a hotkey press and return what to do with the window
+    pub fn toggle(&mut self) -> ToggleAction {
+        if !self.visible {
+            self.visible = true;
+            self.focused = true;
+            ToggleAction::Show
+        } else if !self.focused {
+            self.focused = true;
+            ToggleAction::Focus
+        } else {
+            self.visible = false;
+            self.focused = false;
+            ToggleAction::Hide
+        }
+    }
+}
+
+/// Register `spec` (e.g. `cmd+\``) as a system-wide hotkey that sends
+/// `event` through `proxy` on every press. The returned manager must stay
+/// alive for the hotkey to remain registered.
+pub fn register<T: Clone + Send + Sync + 'static>(
+    spec: &str,
+    proxy: EventLoopProxy<T>,
+    event: T,
+) -> Option<GlobalHotKeyManager> {
+    if spec.trim().is_empty() {
+        return None;
+    }
+    let hotkey: HotKey = match spec.trim().parse() {
+        Ok(hotkey) => hotkey,
+        Err(e) => {
+            warn!("Ignoring invalid toggle_window hotkey {:?}: {}", spec, e);
+            return None;
+        }
+    };
+    let manager = match GlobalHotKeyManager::new() {
+        Ok(manager) => manager,
+        Err(e) => {
+            warn!("Global hotkeys unavailable: {}", e);
+            return None;
+        }
+    };
+    if let Err(e) = manager.register(hotkey) {
+        warn!("Failed to register toggle_window hotkey {:?}: {}", spec, e);
+        return None;
+    }
+
+    let id = hotkey.id();
+    // The handler runs on the hotkey backend's thread
+    let proxy = Mutex::new(proxy);
+    GlobalHotKeyEvent::set_event_handler(Some(move |e: GlobalHotKeyEvent| {
+        if e.id == id && e.state == HotKeyState::Pressed {
+            let _ = proxy.lock().unwrap().send_event(event.clone());
+        }
+    }));
+    Some(manager)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn show_hide_show() {
+        let mut toggle = WindowToggle::new();
+        assert_eq!(toggle.toggle(), ToggleAction::Hide);
+        assert_eq!(toggle.toggle(), ToggleAction::Show);
+        assert_eq!(toggle.toggle(), ToggleAction::Hide);
+        assert_eq!(toggle.toggle(), ToggleAction::Show);
+    }
+
+    #[test]
+    fn an_unfocused_window_is_brought_forward_first() {
+        let mut toggle = WindowToggle::new();
+        toggle.set_focused(false);
+        assert_eq!(toggle.toggle(), ToggleAction::Focus);
+        assert_eq!(toggle.toggle(), ToggleAction::Hide);
+        // Focus events while hidden don't make it visible
+        toggle.set_focused(true);
+        toggle.set_focused(false);
+        assert_eq!(toggle.toggle(), ToggleAction::Show);
+        assert_eq!(toggle.toggle(), ToggleAction::Hide);
+    }
+}
@@ -0,0 +1,298 @@
+//! End-to-end scenarios run headlessly: a real shell on a PTY, or a fake
+//! application on an in-memory one, driven through `HeadlessTerminal` the
+//! way the app's event loop drives a terminal, with no window.
+//!
+//! Each `tests/scenarios/<name>.scn` is a script of one command per line:
+//!
+//!     # comment
+//!     size 80 24                  screen size for the next start (default 80x24)
+//!     shell "/bin/sh"             start a program on a real PTY, on the wall clock
+//!     app                         start a fake application: in-memory PTY and a
+//!                                 manual clock, so waits never sleep
+//!     type "ls\r"                 input as typed
+//!     print "\e[?1049h"           output of the fake application
+//!     paste "a\nb"                user paste, bracketed if the app enabled it
+//!     resize 100 30               window resize, reported to the PTY
+//!     expect "text" 10            wait until a screen row contains the text
+//!                                 (seconds, default 10)
+//!     expect-timeout "text" 5     waiting for the text gives up after 5 s
+//!     expect-style "text" "fg=#2472c8 bold"   attributes of the first cell of
+//!                                 the last occurrence of the text on screen
+//!     expect-alt true             alternate screen shown or not
+//!     expect-received "\e[8;30;100t"  bytes the fake application received
+//!                                 since the last check
+//!     expect-size 100 30          size the fake application's PTY has
+//!     expect-search "line 1" 111  matches of an incremental search over the
+//!                                 screen and scrollback
+//!
+//! Strings take `\e \n \r \t \\ \" \xNN` escapes. Scenarios with `shell`
+//! need a Unix shell and are skipped elsewhere.
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use the_dev_terminal_core::clock::{ManualClock, SystemClock};
+use the_dev_terminal_core::grid::{Cell, CellColor, Grid};
+use the_dev_terminal_core::headless::HeadlessTerminal;
+use the_dev_terminal_core::pty::{FakeApp, FakePty, PtyHandle};
+use the_dev_terminal_core::search::{SearchProgress, SearchQuery, SearchSession};
+use the_dev_terminal_core::Terminal;
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+fn scenarios_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/scenarios")
+}
+
+fn unescape(s: &str) -> Result<Vec<u8>, String> {
+    let mut out = Vec::new();
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            let mut buf = [0; 4];
+            out.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+            continue;
+        }
+        match chars.next() {
+            Some('e') => out.push(0x1b),
+            Some('n') => out.push(b'\n'),
+            Some('r') => out.push(b'\r'),
+            Some('t') => out.push(b'\t'),
+            Some('\\') => out.push(b'\\'),
+            Some('"') => out.push(b'"'),
+            Some('x') => {
+                let hex: String = chars.by_ref().take(2).collect();
+                out.push(u8::from_str_radix(&hex, 16).map_err(|_| format!("bad \\x{hex}"))?);
+            }
+            other => return Err(format!("unknown escape \\{}", other.map(String::from).unwrap_or_default())),
+        }
+    }
+    Ok(out)
+}
+
+fn unescape_str(s: &str) -> Result<String, String> {
+    String::from_utf8(unescape(s)?).map_err(|e| e.to_string())
+}
+
+// Split a line into words and "quoted strings" (quotes removed, escapes kept)
+fn tokenize(line: &str) -> Result<Vec<String>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = line.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '"' {
+            chars.next();
+            let mut s = String::new();
+            loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some('\\') => {
+                        s.push('\\');
+                        s.extend(chars.next());
+                    }
+                    Some(c) => s.push(c),
+                    None => return Err("unterminated string".into()),
+                }
+            }
+            tokens.push(s);
+        } else {
+            let mut s = String::new();
+            while let Some(&c) = chars.peek() {
+                if c.is_whitespace() {
+                    break;
+                }
+                s.push(c);
+                chars.next();
+            }
+            tokens.push(s);
+        }
+    }
+    Ok(tokens)
+}
+
+// Same labels as the core scenarios, e.g. "fg=#cd3131 bold"
+fn attr_label(grid: &Grid, cell: &Cell) -> String {
+    let mut parts = Vec::new();
+    if cell.fg != CellColor::Default {
+        parts.push(format!("fg={}", grid.palette.fg(cell.fg).to_hex()));
+    }
+    if cell.bg != CellColor::Default {
+        parts.push(format!("bg={}", grid.palette.bg(cell.bg).to_hex()));
+    }
+    for (on, name) in [(cell.attrs().bold(), "bold"), (cell.attrs().italic(), "italic"), (cell.attrs().underline(), "underline")] {
+        if on {
+            parts.push(name.to_string());
+        }
+    }
+    parts.join(" ")
+}
+
+// Cell where the last occurrence of `text` on screen starts
+fn find_last(grid: &Grid, text: &str) -> Option<(usize, usize)> {
+    (0..grid.rows).rev().find_map(|row| {
+        let cells = &grid.cells[row * grid.cols..(row + 1) * grid.cols];
+        let line: Vec<char> = cells.iter().map(|c| c.ch()).collect();
+        let want: Vec<char> = text.chars().collect();
+        line.windows(want.len()).rposition(|w| w == want.as_slice()).map(|col| (col, row))
+    })
+}
+
+struct Scenario {
+    size: (usize, usize),
+    running: Option<HeadlessTerminal>,
+    // Set while the running application is the fake one
+    app: Option<FakeApp>,
+}
+
+impl Scenario {
+    fn new() -> Self {
+        Self { size: (80, 24), running: None, app: None }
+    }
+
+    fn term(&mut self) -> Result<&mut HeadlessTerminal, String> {
+        self.running.as_mut().ok_or_else(|| "nothing started; use `shell` or `app` first".to_string())
+    }
+
+    fn app(&mut self) -> Result<FakeApp, String> {
+        self.term()?.pump().map_err(|e| e.to_string())?;
+        self.app.clone().ok_or_else(|| "only a fake application (`app`) can do that".to_string())
+    }
+
+    fn run(&mut self, args: &[String]) -> Result<(), String> {
+        let arg = |i: usize| args.get(i).ok_or_else(|| format!("missing argument {i}"));
+        let num = |i: usize| -> Result<usize, String> {
+            arg(i)?.parse().map_err(|_| format!("not a number: {}", args[i]))
+        };
+        let secs = |i: usize| -> Result<Duration, String> {
+            match args.get(i) {
+                Some(s) => s.parse().map(Duration::from_secs).map_err(|_| format!("not a number: {s}")),
+                None => Ok(DEFAULT_TIMEOUT),
+            }
+        };
+        match args[0].as_str() {
+            "size" => self.size = (num(1)?, num(2)?),
+            "shell" => {
+                let (cols, rows) = self.size;
+                let program_args: Vec<&str> = args[2..].iter().map(String::as_str).collect();
+                let env = [("PS1", "$ ".to_string()), ("ENV", String::new())];
+                let (pty, output) = PtyHandle::spawn_program(arg(1)?, &program_args, rows as u16, cols as u16, &env)
+                    .map_err(|e| e.to_string())?;
+                let term = Terminal::new(cols, rows);
+                self.running = Some(HeadlessTerminal::new(term, Box::new(pty), output, Box::new(SystemClock)));
+                self.app = None;
+            }
+            "app" => {
+                let (cols, rows) = self.size;
+                let (pty, app, output) = FakePty::new();
+                let term = Terminal::new(cols, rows);
+                self.running = Some(HeadlessTerminal::new(term, Box::new(pty), output, Box::new(ManualClock::new())));
+                self.app = Some(app);
+            }
+            "type" => self.term()?.send(&unescape(arg(1)?)?).map_err(|e| e.to_string())?,
+            "print" => self.app()?.print(&unescape(arg(1)?)?),
+            "paste" => {
+                let text = unescape_str(arg(1)?)?;
+                let term = self.term()?;
+                term.pump().map_err(|e| e.to_string())?;
+                term.paste(&text).map_err(|e| e.to_string())?;
+            }
+            "resize" => {
+                let (cols, rows) = (num(1)?, num(2)?);
+                self.term()?.resize(cols, rows).map_err(|e| e.to_string())?;
+            }
+            "expect" => {
+                let (text, timeout) = (unescape_str(arg(1)?)?, secs(2)?);
+                self.term()?.expect(&text, timeout)?;
+            }
+            "expect-timeout" => {
+                let (text, timeout) = (unescape_str(arg(1)?)?, secs(2)?);
+                match self.term()?.expect(&text, timeout) {
+                    Ok(()) => return Err(format!("{text:?} showed up")),
+                    Err(e) if e.starts_with("timed out") => {}
+                    Err(e) => return Err(e),
+                }
+            }
+            "expect-style" => {
+                let (text, want) = (unescape_str(arg(1)?)?, arg(2)?);
+                let term = self.term()?;
+                term.pump().map_err(|e| e.to_string())?;
+                let grid = &term.term.grid;
+                let (col, row) = find_last(grid, &text).ok_or_else(|| format!("{text:?} is not on screen:\n{}", grid.to_string_lines()))?;
+                let got = attr_label(grid, &grid.cells[row * grid.cols + col]);
+                if got != *want {
+                    return Err(format!("{text:?} at {col},{row} is {got:?}, expected {want:?}"));
+                }
+            }
+            "expect-alt" => {
+                let want = arg(1)? == "true";
+                let term = self.term()?;
+                term.pump().map_err(|e| e.to_string())?;
+                if term.term.grid.is_alt_screen() != want {
+                    return Err(format!("alt screen is {}, expected {want}", !want));
+                }
+            }
+            "expect-received" => {
+                let want = unescape(arg(1)?)?;
+                let got = self.app()?.take_received();
+                if got != want {
+                    return Err(format!("received {:?}, expected {:?}", got.escape_ascii().to_string(), want.escape_ascii().to_string()));
+                }
+            }
+            "expect-size" => {
+                let want = Some((num(1)? as u16, num(2)? as u16));
+                let got = self.app()?.size();
+                if got != want {
+                    return Err(format!("PTY size is {got:?}, expected {want:?}"));
+                }
+            }
+            "expect-search" => {
+                let query = SearchQuery::Plain { text: unescape_str(arg(1)?)?, case_sensitive: true };
+                let want = num(2)?;
+                let term = self.term()?;
+                term.pump().map_err(|e| e.to_string())?;
+                let grid = &term.term.grid;
+                let mut session = SearchSession::new(&query, grid).map_err(|e| e.to_string())?;
+                while let SearchProgress::Searching { .. } = session.poll(grid) {}
+                if session.matches().len() != want {
+                    return Err(format!("{} matches for {:?}, expected {want}", session.matches().len(), args[1]));
+                }
+            }
+            other => return Err(format!("unknown command {other:?}")),
+        }
+        Ok(())
+    }
+}
+
+fn run_file(path: &Path) -> Result<(), String> {
+    let script = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let commands: Vec<(usize, &str)> = script
+        .lines()
+        .map(str::trim)
+        .enumerate()
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .collect();
+    if cfg!(not(unix)) && commands.iter().any(|(_, line)| line.starts_with("shell ")) {
+        return Ok(());
+    }
+    let mut scenario = Scenario::new();
+    for (n, line) in commands {
+        tokenize(line)
+            .and_then(|args| scenario.run(&args))
+            .map_err(|e| format!("{}:{}: {e}", path.display(), n + 1))?;
+    }
+    Ok(())
+}
+
+#[test]
+fn scenarios() {
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(scenarios_dir())
+        .expect("tests/scenarios")
+        .map(|e| e.expect("dir entry").path())
+        .filter(|p| p.extension().is_some_and(|e| e == "scn"))
+        .collect();
+    paths.sort();
+    assert!(!paths.is_empty(), "no scenarios found");
+
+    let failures: Vec<String> = paths.iter().filter_map(|p| run_file(p).err()).collect();
+    assert!(failures.is_empty(), "{} scenario(s) failed:\n{}", failures.len(), failures.join("\n"));
+}
@@ -0,0 +1,79 @@
+//! Bidirectional text policy.
+//!
+//! Cells are laid out strictly in logical order, left to right, like other
+//! terminals do. Explicit direction controls are dropped when printed so an
+//! RTL override in e.g. a file name can't visually reorder its neighbours.
+//! `BidiMode::IsolateRuns` optionally reverses each right-to-left run in
+//! place for readability; cursor and selection keep logical columns and the
+//! renderer maps them through `isolate_runs`.
+
+use crate::grid::{char_cells, Cell};
+
+/// Explicit directional formatting characters: marks (LRM, RLM, ALM),
+/// embeddings and overrides (LRE..RLO, PDF) and isolates (LRI..PDI)
+pub fn is_bidi_control(ch: char) -> bool {
+    matches!(
+        ch,
+        '\u{061C}' | '\u{200E}' | '\u{200F}' | '\u{202A}'..='\u{202E}' | '\u{2066}'..='\u{2069}'
+    )
+}
+
+/// Whether `ch` belongs to a right-to-left script (Hebrew, Arabic, Syriac, ...)
+pub fn is_rtl(ch: char) -> bool {
+    matches!(
+        ch,
+        '\u{0590}'..='\u{08FF}'
+            | '\u{FB1D}'..='\u{FDFF}'
+            | '\u{FE70}'..='\u{FEFF}'
+            | '\u{10800}'..='\u{10FFF}'
+            | '\u{1E800}'..='\u{1EFFF}'
+    )
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Class {
+    Rtl,
+    Ltr,
+    Neutral,
+}
+
+fn class(cell: &Cell) -> Class {
+    let ch = cell.ch();
+    if is_rtl(ch) {
+        Class::Rtl
+    } else if cell.is_wide_trailer() || char_cells(ch) == 2 || ch.is_alphanumeric() {
+        // Wide pairs must stay in order, so they never join a reversed run
+        Class::Ltr
+    } else {
+        Class::Neutral
+    }
+}
+
+/// Column each cell of a row is drawn at when every right-to-left run is
+/// reversed in place. A run starts and ends on an RTL character and takes
+/// in the spaces and punctuation between them. None when nothing moves.
+pub fn isolate_runs(cells: &[Cell]) -> Option<Vec<usize>> {
+    let classes: Vec<Class> = cells.iter().map(class).collect();
+    let mut map: Vec<usize> = (0..cells.len()).collect();
+    let mut moved = false;
+
+    let mut col = 0;
+    while col < classes.len() {
+        if classes[col] != Class::Rtl {
+            col += 1;
+            continue;
+        }
+        let start = col;
+        let mut end = col;
+        while col < classes.len() && classes[col] != Class::Ltr {
+            if classes[col] == Class::Rtl {
+                end = col;
+            }
+            col += 1;
+        }
+        map[start..=end].reverse();
+        moved |= end > start;
+        col = end + 1;
+    }
+    moved.then_some(map)
+}
@@ -0,0 +1,53 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Where code that waits gets the time from, so tests can run it without
+/// sleeping and with the same outcome every time
+pub trait Clock: Send {
+    fn now(&self) -> Instant;
+    fn sleep(&self, d: Duration);
+}
+
+/// Wall-clock time; `sleep` blocks the thread
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn sleep(&self, d: Duration) {
+        std::thread::sleep(d);
+    }
+}
+
+/// Time that only moves when told to; `sleep` advances it and returns at once
+pub struct ManualClock {
+    now: Mutex<Instant>,
+}
+
+impl ManualClock {
+    pub fn new() -> Self {
+        Self { now: Mutex::new(Instant::now()) }
+    }
+
+    pub fn advance(&self, d: Duration) {
+        *self.now.lock().unwrap() += d;
+    }
+}
+
+impl Default for ManualClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        *self.now.lock().unwrap()
+    }
+
+    fn sleep(&self, d: Duration) {
+        self.advance(d);
+    }
+}
@@ -0,0 +1,102 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+use unicode_width::UnicodeWidthChar;
+
+const ZWJ: char = '\u{200D}';
+
+fn is_regional_indicator(ch: char) -> bool {
+    ('\u{1F1E6}'..='\u{1F1FF}').contains(&ch)
+}
+
+fn is_skin_tone(ch: char) -> bool {
+    ('\u{1F3FB}'..='\u{1F3FF}').contains(&ch)
+}
+
+/// Whether `ch` continues the cluster `prev` instead of starting a new cell:
+/// zero-width combiners and variation selectors, anything after a ZWJ,
+/// skin-tone modifiers, and the second half of a regional-indicator flag.
+pub fn extends_cluster(prev: &str, ch: char) -> bool {
+    let Some(last) = prev.chars().last() else {
+        return false;
+    };
+    if UnicodeWidthChar::width(ch) == Some(0) || last == ZWJ || is_skin_tone(ch) {
+        return true;
+    }
+    // Flags are exactly two regional indicators
+    is_regional_indicator(ch) && prev.chars().count() == 1 && is_regional_indicator(last)
+}
+
+/// Cells a cluster occupies: its base character's width, or 2 for a flag
+pub fn cluster_cells(cluster: &str) -> usize {
+    let mut chars = cluster.chars();
+    let Some(base) = chars.next() else {
+        return 1;
+    };
+    if is_regional_indicator(base) && chars.next().is_some_and(is_regional_indicator) {
+        return 2;
+    }
+    crate::grid::char_cells(base)
+}
+
+/// Split text into clusters the same way `Grid::put` groups characters into cells
+pub fn split_clusters(text: &str) -> Vec<&str> {
+    let mut clusters: Vec<&str> = Vec::new();
+    let mut start = 0;
+    for (i, ch) in text.char_indices() {
+        if i > start && !extends_cluster(&text[start..i], ch) {
+            clusters.push(&text[start..i]);
+            start = i;
+        }
+    }
+    if start < text.len() {
+        clusters.push(&text[start..]);
+    }
+    clusters
+}
+
+// Cells holding more than one character keep an interned id for the whole
+// cluster so `Cell` stays small and `Copy`. Distinct clusters are few, so
+// nothing is freed.
+#[derive(Default)]
+struct Interner {
+    ids: HashMap<Arc<str>, u32>,
+    strings: Vec<Arc<str>>,
+}
+
+// Ids share the bits of a `Cell` that otherwise hold a character
+const MAX_IDS: usize = 1 << 21;
+
+fn interner() -> &'static Mutex<Interner> {
+    static INTERNER: OnceLock<Mutex<Interner>> = OnceLock::new();
+    INTERNER.get_or_init(Default::default)
+}
+
+/// Id for the cluster `text`; None once every id is taken
+pub fn intern(text: &str) -> Option<u32> {
+    let mut interner = interner().lock().unwrap();
+    if let Some(&id) = interner.ids.get(text) {
+        return Some(id);
+    }
+    if interner.strings.len() >= MAX_IDS {
+        return None;
+    }
+    let s: Arc<str> = Arc::from(text);
+    let id = interner.strings.len() as u32;
+    interner.strings.push(s.clone());
+    interner.ids.insert(s, id);
+    Some(id)
+}
+
+/// First character of the cluster behind `id`
+pub fn base(id: u32) -> char {
+    let interner = interner().lock().unwrap();
+    interner.strings.get(id as usize).and_then(|s| s.chars().next()).unwrap_or('\0')
+}
+
+/// Append the cluster behind `id` to `out`
+pub fn push_cluster(id: u32, out: &mut String) {
+    let interner = interner().lock().unwrap();
+    if let Some(s) = interner.strings.get(id as usize) {
+        out.push_str(s);
+    }
+}
@@ -1,7 +1,7 @@
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 #[serde(default)]
 pub struct Config {
     pub general: GeneralConfig,
@@ -16,10 +16,53 @@ pub struct Config {
 pub struct GeneralConfig {
     pub shell: String,
     pub shell_args: Vec<String>,
+    /// Lines of history; 0 keeps none, and a huge value (e.g. 1000000000)
+    /// leaves only `scrollback_max_bytes` as a limit
     pub scrollback_lines: usize,
+    /// Also cap scrollback at roughly this many bytes of cells; 0 = no byte cap
+    pub scrollback_max_bytes: usize,
     pub mouse_reports: bool,
     pub clipboard_access: bool,
     pub bracketed_paste: bool,
+    pub bell: BellConfig,
+    /// Fraction of the screen height scrolled by page up/down
+    pub page_scroll_fraction: f32,
+    /// When the viewport snaps back to the newest output
+    pub scroll_behavior: ScrollBehavior,
+    /// Clearing the screen (the clear-screen binding or an app's `CSI 2 J`) moves it into
+    /// scrollback instead of discarding it; ⌘K still clears both
+    pub clear_pushes_to_scrollback: bool,
+    /// Rewrap soft-wrapped lines (scrollback included) to the new width on resize
+    /// instead of truncating or keeping them at the old width
+    pub reflow_on_resize: bool,
+    /// Ask before closing the window while a command is running in the shell
+    pub confirm_close: bool,
+    /// Treat stray 8-bit C1 bytes (e.g. 0x9B) as controls; bytes inside
+    /// UTF-8 characters are never affected
+    pub c1_controls: bool,
+    /// Characters besides letters, digits and `_` that double-click keeps
+    /// inside a word, so paths and URLs select whole
+    pub word_chars: String,
+}
+
+/// Auto-scroll policy for the viewport
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ScrollBehavior {
+    /// Follow output and keystrokes, even when scrolled up
+    Always,
+    /// Follow output only while already at the bottom
+    #[default]
+    WhenAtBottom,
+    /// Never move on output; jump to the bottom on a keystroke (for tailing logs)
+    OnKeystroke,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BellConfig {
+    /// Request user attention (dock bounce) when the window is unfocused;
+    /// the flash or sound itself is `appearance.bell_style`
+    pub urgent: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -31,10 +74,42 @@ pub struct AppearanceConfig {
     pub cursor_style: CursorStyle,
     pub cursor_blink: bool,
     pub window_padding: f32,
+    /// Right-to-left text display: `logical` keeps strict cell order,
+    /// `isolate-runs` reverses each RTL run in place
+    pub bidi: BidiMode,
+    /// Draw bold text in ANSI colors 0-7 with their bright variants 8-15, like xterm
+    pub draw_bold_text_with_bright_colors: bool,
+    /// What a BEL does: `none`, `visual` (flash the background) or `audible`
+    pub bell_style: BellStyle,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// How right-to-left text is displayed; see `bidi`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BidiMode {
+    /// Cells in logical order, left to right
+    #[default]
+    Logical,
+    /// Each right-to-left run reversed in place; cursor and selection stay logical
+    IsolateRuns,
+}
+
+/// Response to a BEL from the shell; see `bell_style`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BellStyle {
+    /// Ignore it
+    None,
+    /// Flash the window background
+    #[default]
+    Visual,
+    /// Play the system alert sound
+    Audible,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub enum CursorStyle {
+    #[default]
     Block,
     Underline,
     Beam,
@@ -48,6 +123,10 @@ pub struct ThemeConfig {
     pub foreground: String,
     pub cursor: String,
     pub selection: String,
+    /// Background of every search match
+    pub search_match: String,
+    /// Background of the match search is on, drawn over `search_match`
+    pub search_current_match: String,
     pub black: String,
     pub red: String,
     pub green: String,
@@ -80,6 +159,41 @@ pub struct KeybindingsConfig {
     pub zoom_out: String,
     pub zoom_reset: String,
     pub clear_scrollback: String,
+    /// Blank the visible screen only; scrollback is kept
+    pub clear_screen: String,
+    pub scroll_page_up: String,
+    pub scroll_page_down: String,
+    pub scroll_line_up: String,
+    pub scroll_line_down: String,
+    pub scroll_to_top: String,
+    pub scroll_to_bottom: String,
+    /// Scroll the previous / next shell prompt to the top (needs OSC 133 shell integration)
+    pub scroll_to_prev_prompt: String,
+    pub scroll_to_next_prompt: String,
+    /// While searching: copy every line that contains a match
+    pub copy_matching_lines: String,
+    /// While searching: select all matches at once
+    pub select_all_matches: String,
+    /// Copy the output of the last finished command (needs OSC 133 shell integration)
+    pub copy_last_output: String,
+    /// Select the output of the command under the mouse pointer (needs OSC 133 shell integration)
+    pub select_command_output: String,
+    /// Save scrollback and screen as plain text to `~/Downloads`
+    pub save_scrollback: String,
+    /// The same, keeping colors and attributes as SGR sequences; unbound by default
+    pub save_scrollback_ansi: String,
+    /// System-wide hotkey that shows/hides the window from any app; empty disables it
+    pub toggle_window: String,
+    /// Keys that type a fixed string into the shell (macros)
+    pub send_keys: Vec<SendKeysConfig>,
+}
+
+/// A key that sends `text` to the shell, e.g. `{ key = "f5", text = 'make\n' }`.
+/// `text` understands `\n`, `\r`, `\t`, `\e`, `\\`, `\xNN` and `\u{N}` escapes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SendKeysConfig {
+    pub key: String,
+    pub text: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -92,27 +206,32 @@ pub struct PerformanceConfig {
     pub batch_rendering: bool,
 }
 
-impl Default for Config {
-    fn default() -> Self {
-        Self {
-            general: GeneralConfig::default(),
-            appearance: AppearanceConfig::default(),
-            theme: ThemeConfig::default(),
-            keybindings: KeybindingsConfig::default(),
-            performance: PerformanceConfig::default(),
-        }
-    }
-}
-
 impl Default for GeneralConfig {
     fn default() -> Self {
         Self {
             shell: std::env::var("SHELL").unwrap_or_else(|_| "/bin/zsh".to_string()),
             shell_args: vec![],
-            scrollback_lines: 10000,
+            scrollback_lines: crate::scrollback::DEFAULT_SCROLLBACK_LINES,
+            scrollback_max_bytes: 0,
             mouse_reports: true,
             clipboard_access: true,
             bracketed_paste: true,
+            bell: BellConfig::default(),
+            page_scroll_fraction: 1.0,
+            scroll_behavior: ScrollBehavior::default(),
+            clear_pushes_to_scrollback: false,
+            reflow_on_resize: false,
+            confirm_close: false,
+            c1_controls: false,
+            word_chars: "-./:~@".to_string(),
+        }
+    }
+}
+
+impl Default for BellConfig {
+    fn default() -> Self {
+        Self {
+            urgent: true,
         }
     }
 }
@@ -126,16 +245,13 @@ impl Default for AppearanceConfig {
             cursor_style: CursorStyle::Block,
             cursor_blink: false,
             window_padding: 12.0,
+            bidi: BidiMode::Logical,
+            draw_bold_text_with_bright_colors: true,
+            bell_style: BellStyle::Visual,
         }
     }
 }
 
-impl Default for CursorStyle {
-    fn default() -> Self {
-        CursorStyle::Block
-    }
-}
-
 impl Default for ThemeConfig {
     fn default() -> Self {
         // Default dark theme
@@ -145,6 +261,8 @@ impl Default for ThemeConfig {
             foreground: "#e5e5e5".to_string(),
             cursor: "#e5e5e5".to_string(),
             selection: "#3366cc44".to_string(),
+            search_match: "#806a00".to_string(),
+            search_current_match: "#e0a000".to_string(),
             black: "#000000".to_string(),
             red: "#cd3131".to_string(),
             green: "#0dbc79".to_string(),
@@ -179,6 +297,23 @@ impl Default for KeybindingsConfig {
             zoom_out: "cmd+-".to_string(),
             zoom_reset: "cmd+0".to_string(),
             clear_scrollback: "cmd+k".to_string(),
+            clear_screen: "cmd+shift+k".to_string(),
+            scroll_page_up: "pageup".to_string(),
+            scroll_page_down: "pagedown".to_string(),
+            scroll_line_up: "ctrl+shift+up".to_string(),
+            scroll_line_down: "ctrl+shift+down".to_string(),
+            scroll_to_top: "shift+home".to_string(),
+            scroll_to_bottom: "shift+end".to_string(),
+            scroll_to_prev_prompt: "cmd+up".to_string(),
+            scroll_to_next_prompt: "cmd+down".to_string(),
+            copy_matching_lines: "cmd+shift+l".to_string(),
+            select_all_matches: "cmd+shift+a".to_string(),
+            copy_last_output: "cmd+shift+o".to_string(),
+            select_command_output: "cmd+shift+u".to_string(),
+            save_scrollback: "cmd+shift+s".to_string(),
+            save_scrollback_ansi: String::new(),
+            toggle_window: String::new(),
+            send_keys: Vec::new(),
         }
     }
 }
@@ -226,7 +361,15 @@ impl Config {
     }
     
     fn config_path() -> Result<PathBuf, Box<dyn std::error::Error>> {
-        let home = std::env::var("HOME")?;
-        Ok(PathBuf::from(home).join(".config").join("the-dev-terminal").join("config.toml"))
+        Ok(crate::paths::config_dir()?.join("config.toml"))
+    }
+    
+    /// Config for this launch: built-in defaults only in safe mode, never touching the file
+    pub fn resolve(safe_mode: bool) -> Result<Self, Box<dyn std::error::Error>> {
+        if safe_mode {
+            Ok(Config::default())
+        } else {
+            Self::load()
+        }
     }
 }
\ No newline at end of file
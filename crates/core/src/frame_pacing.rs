@@ -0,0 +1,63 @@
+use std::time::{Duration, Instant};
+
+// Gap between frames at `fps`; 0 fps leaves frames uncapped
+fn frame_interval(fps: u32) -> Duration {
+    match fps {
+        0 => Duration::ZERO,
+        fps => Duration::from_secs(1) / fps,
+    }
+}
+
+/// Earliest moment a frame may be drawn when the previous one was drawn at
+/// `last` and frames are paced to `target_fps`; never before `now`.
+/// A `target_fps` of 0 doesn't hold frames back.
+pub fn next_frame_deadline(now: Instant, last: Instant, target_fps: u32) -> Instant {
+    (last + frame_interval(target_fps)).max(now)
+}
+
+/// Paces redraws the event loop asks for: at most `performance.max_fps` a
+/// second while something is moving (scroll inertia, PTY output, a drag),
+/// `performance.idle_fps` for anything else
+pub struct FramePacer {
+    max_fps: u32,
+    idle_fps: u32,
+    last_frame: Option<Instant>,
+    // When the next requested frame may be drawn
+    due: Option<Instant>,
+}
+
+impl FramePacer {
+    pub fn new(max_fps: u32, idle_fps: u32) -> Self {
+        Self { max_fps, idle_fps, last_frame: None, due: None }
+    }
+
+    /// A frame was drawn at `now`, whatever asked for it
+    pub fn frame_drawn(&mut self, now: Instant) {
+        self.last_frame = Some(now);
+    }
+
+    /// Ask for a frame, paced to `max_fps` when `active`, else to `idle_fps`.
+    /// An earlier pending request keeps its deadline.
+    pub fn request_frame(&mut self, now: Instant, active: bool) {
+        let fps = if active { self.max_fps } else { self.idle_fps };
+        let deadline = self.last_frame.map_or(now, |last| next_frame_deadline(now, last, fps));
+        self.due = Some(self.due.map_or(deadline, |due| due.min(deadline)));
+    }
+
+    /// Whether the requested frame is due at `now`; if so it is handed out
+    /// and the request cleared
+    pub fn take_due(&mut self, now: Instant) -> bool {
+        match self.due {
+            Some(due) if now >= due => {
+                self.due = None;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// When to wake up for the requested frame, if one is pending
+    pub fn deadline(&self) -> Option<Instant> {
+        self.due
+    }
+}
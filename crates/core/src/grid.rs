@@ -1,7 +1,18 @@
+use std::cell::RefCell;
+use std::ops::Range;
+use std::sync::Arc;
 use unicode_width::UnicodeWidthChar;
 use crate::scrollback::ScrollbackBuffer;
+use crate::marks::{MarkKind, MarkList};
+use crate::keyboard::KeyboardModes;
+use crate::cluster;
+use crate::links::LinkCache;
+use crate::bidi;
+use crate::palette::Palette;
+use crate::perf::HeapUsage;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Color {
     pub r: u8,
     pub g: u8,
@@ -28,6 +39,8 @@ impl Color {
     pub const BRIGHT_CYAN: Color = Color { r: 41, g: 184, b: 219 };
     pub const BRIGHT_WHITE: Color = Color { r: 255, g: 255, b: 255 };
     
+    /// Built-in color for palette index `n`. Cells keep the index and resolve
+    /// it through a `Palette`, where a theme can replace 0-15
     pub fn from_ansi(n: u8) -> Color {
         match n {
             0 => Color::BLACK,
@@ -65,211 +78,1151 @@ impl Color {
     }
 }
 
+impl Color {
+    /// Parse a `#rrggbb` (or `rrggbb`) theme color
+    pub fn from_hex(s: &str) -> Option<Color> {
+        let hex = s.trim().trim_start_matches('#');
+        if hex.len() != 6 || !hex.is_ascii() {
+            return None;
+        }
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+        Some(Color { r: channel(0)?, g: channel(2)?, b: channel(4)? })
+    }
+    
+    /// `#rrggbb`, the inverse of `from_hex`
+    pub fn to_hex(&self) -> String {
+        format!("#{:02x}{:02x}{:02x}", self.r, self.g, self.b)
+    }
+    
+    /// XParseColor form used in OSC color reports (`rgb:rrrr/gggg/bbbb`)
+    pub fn to_xterm_rgb(&self) -> String {
+        let wide = |c: u8| c as u16 * 0x101;
+        format!("rgb:{:04x}/{:04x}/{:04x}", wide(self.r), wide(self.g), wide(self.b))
+    }
+}
+
 impl Default for Color {
     fn default() -> Self {
         Color::WHITE
     }
 }
 
-#[derive(Clone, Copy, Default)]
-pub struct Cell { 
-    pub ch: char,
-    pub fg: Color,
-    pub bg: Color,
-    pub bold: bool,
-    pub italic: bool,
-    pub underline: bool,
+/// A cell's color as the application set it; turned into RGB by a
+/// `Palette` when drawn, so theme changes reach existing content
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum CellColor {
+    /// The theme foreground or background (SGR 39/49, or never set)
+    #[default]
+    Default,
+    /// Palette entry: 0-15 the ANSI colors (SGR 30-37/90-97), then the 256-color cube and grays
+    Indexed(u8),
+    /// Direct color (SGR 38;2 / 48;2)
+    Rgb(Color),
 }
 
-pub struct Grid {
+/// SGR renditions, one bit each
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AttrFlags(u8);
+
+impl AttrFlags {
+    pub const BOLD: AttrFlags = AttrFlags(1);
+    pub const ITALIC: AttrFlags = AttrFlags(1 << 1);
+    pub const UNDERLINE: AttrFlags = AttrFlags(1 << 2);
+    
+    pub fn contains(self, flag: AttrFlags) -> bool {
+        self.0 & flag.0 == flag.0
+    }
+    
+    pub fn set(&mut self, flag: AttrFlags, on: bool) {
+        if on {
+            self.0 |= flag.0;
+        } else {
+            self.0 &= !flag.0;
+        }
+    }
+}
+
+/// SGR attributes: what a cell is drawn with, and the pen new cells get
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct CellAttrs {
+    pub fg: CellColor,
+    pub bg: CellColor,
+    pub flags: AttrFlags,
+}
+
+impl CellAttrs {
+    pub fn bold(&self) -> bool {
+        self.flags.contains(AttrFlags::BOLD)
+    }
+    
+    pub fn italic(&self) -> bool {
+        self.flags.contains(AttrFlags::ITALIC)
+    }
+    
+    pub fn underline(&self) -> bool {
+        self.flags.contains(AttrFlags::UNDERLINE)
+    }
+}
+
+// `Cell::glyph` layout: the character in the low 21 bits (or, with CLUSTER,
+// the intern id of a multi-character cluster), then the AttrFlags, then
+// whether the cell is the right half of a wide character
+const GLYPH_MASK: u32 = (1 << 21) - 1;
+const FLAGS_SHIFT: u32 = 21;
+const FLAGS_MASK: u32 = 0b111 << FLAGS_SHIFT;
+const TRAILER: u32 = 1 << 24;
+const CLUSTER: u32 = 1 << 25;
+
+/// One screen cell. Kept to 12 bytes since the screen and every scrollback
+/// line are arrays of these.
+#[derive(Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(into = "CellRepr", from = "CellRepr")]
+pub struct Cell {
+    glyph: u32,
+    pub fg: CellColor,
+    pub bg: CellColor,
+}
+
+const _: () = assert!(std::mem::size_of::<Cell>() <= 12);
+
+impl Cell {
+    /// `ch` drawn with `attrs`
+    pub fn new(ch: char, attrs: CellAttrs) -> Cell {
+        Cell { glyph: ch as u32 | (attrs.flags.0 as u32) << FLAGS_SHIFT, fg: attrs.fg, bg: attrs.bg }
+    }
+    
+    /// Right half of the wide character in `self`: no text, same attributes
+    pub fn trailer(self) -> Cell {
+        Cell { glyph: (self.glyph & FLAGS_MASK) | TRAILER, ..self }
+    }
+    
+    /// The character, or a cluster's first one; '\0' for empty cells and trailers
+    pub fn ch(&self) -> char {
+        if self.is_cluster() {
+            return cluster::base(self.glyph & GLYPH_MASK);
+        }
+        char::from_u32(self.glyph & GLYPH_MASK).unwrap_or('\0')
+    }
+    
+    pub fn attrs(&self) -> CellAttrs {
+        CellAttrs { fg: self.fg, bg: self.bg, flags: AttrFlags(((self.glyph & FLAGS_MASK) >> FLAGS_SHIFT) as u8) }
+    }
+    
+    pub fn set_attrs(&mut self, attrs: CellAttrs) {
+        self.glyph = (self.glyph & !FLAGS_MASK) | (attrs.flags.0 as u32) << FLAGS_SHIFT;
+        self.fg = attrs.fg;
+        self.bg = attrs.bg;
+    }
+    
+    /// Right half of a wide character; the character itself is in the cell to the left
+    pub fn is_wide_trailer(&self) -> bool {
+        self.glyph & TRAILER != 0
+    }
+    
+    /// Whether more characters are drawn together with `ch()` (combining
+    /// marks, ZWJ sequences, the second half of a flag)
+    pub fn is_cluster(&self) -> bool {
+        self.glyph & CLUSTER != 0
+    }
+    
+    /// Nothing to draw: empty, or a plain space
+    pub fn is_empty(&self) -> bool {
+        matches!(self.glyph & (GLYPH_MASK | TRAILER | CLUSTER), 0 | 0x20)
+    }
+    
+    /// This cell showing `text` (a whole cluster) instead of its character
+    pub fn with_text(self, text: &str) -> Cell {
+        let mut chars = text.chars();
+        let keep = self.glyph & FLAGS_MASK;
+        let glyph = match (chars.next(), chars.next()) {
+            (None, _) => keep,
+            (Some(ch), None) => keep | ch as u32,
+            (Some(ch), Some(_)) => match cluster::intern(text) {
+                Some(id) => keep | CLUSTER | id,
+                // Out of ids: keep the base character alone
+                None => keep | ch as u32,
+            },
+        };
+        Cell { glyph, ..self }
+    }
+    
+    /// Append the cell's text: nothing for a trailer, a space for an empty cell
+    pub fn push_text(&self, s: &mut String) {
+        if self.is_wide_trailer() {
+            return;
+        }
+        if self.is_cluster() {
+            cluster::push_cluster(self.glyph & GLYPH_MASK, s);
+            return;
+        }
+        let ch = self.ch();
+        s.push(if ch == '\0' { ' ' } else { ch });
+    }
+}
+
+// Serialized form of a `Cell`: intern ids are only meaningful within one
+// process, so clusters are stored as text
+#[derive(Serialize, Deserialize)]
+struct CellRepr {
+    text: String,
+    attrs: CellAttrs,
+    wide_trailer: bool,
+}
+
+impl From<Cell> for CellRepr {
+    fn from(cell: Cell) -> Self {
+        let mut text = String::new();
+        if !cell.is_wide_trailer() && cell.ch() != '\0' {
+            cell.push_text(&mut text);
+        }
+        CellRepr { text, attrs: cell.attrs(), wide_trailer: cell.is_wide_trailer() }
+    }
+}
+
+impl From<CellRepr> for Cell {
+    fn from(repr: CellRepr) -> Self {
+        let cell = Cell::new('\0', repr.attrs);
+        if repr.wide_trailer {
+            cell.trailer()
+        } else {
+            cell.with_text(&repr.text)
+        }
+    }
+}
+
+/// Number of cells `ch` occupies: 2 for wide (CJK, most emoji), otherwise 1
+pub fn char_cells(ch: char) -> usize {
+    UnicodeWidthChar::width(ch).unwrap_or(1).clamp(1, 2)
+}
+
+/// Where to break a logical line into rows of `cols` cells: one range per
+/// row, never between a wide character and its trailer. An empty line is one empty row.
+pub fn wrap_ranges(line: &[Cell], cols: usize) -> Vec<Range<usize>> {
+    let cols = cols.max(1);
+    let mut ranges = Vec::new();
+    let mut start = 0;
+    loop {
+        let mut end = (start + cols).min(line.len());
+        if end < line.len() && line[end].is_wide_trailer() && end > start + 1 {
+            end -= 1;
+        }
+        ranges.push(start..end);
+        if end >= line.len() {
+            return ranges;
+        }
+        start = end;
+    }
+}
+
+/// Text of row-major `cells`, `cols` per row, each row ending in a newline
+pub fn cells_text(cells: &[Cell], cols: usize) -> String {
+    let mut s = String::with_capacity(cells.len() + cells.len() / cols.max(1));
+    for row in cells.chunks(cols.max(1)) {
+        for cell in row {
+            cell.push_text(&mut s);
+        }
+        s.push('\n');
+    }
+    s
+}
+
+/// Word around `col` of `line`, as (start, end) columns. Letters, digits,
+/// `_` and the characters in `word_chars` make up words; off a word the
+/// span is just `col`.
+pub fn find_word_boundaries(line: &[Cell], col: usize, word_chars: &str) -> (usize, usize) {
+    let is_word_char = |ch: char| ch.is_alphanumeric() || ch == '_' || word_chars.contains(ch);
+    if col >= line.len() || !is_word_char(line[col].ch()) {
+        return (col, col);
+    }
+    let mut start = col;
+    while start > 0 && is_word_char(line[start - 1].ch()) {
+        start -= 1;
+    }
+    let mut end = col;
+    while end + 1 < line.len() && is_word_char(line[end + 1].ch()) {
+        end += 1;
+    }
+    (start, end)
+}
+
+/// What a viewport shows: `rows` rows of `cols` cells, row-major, and where
+/// the cursor is among them (None when its row is scrolled out of view)
+#[derive(Clone, Default)]
+pub struct DisplaySnapshot {
+    pub cells: Vec<Cell>,
     pub cols: usize,
     pub rows: usize,
-    pub cells: Vec<Cell>,
+    pub cursor: Option<(usize, usize)>,
+}
+
+impl DisplaySnapshot {
+    /// Cells of one row, empty past the last
+    pub fn row(&self, row: usize) -> &[Cell] {
+        self.cells.get(row * self.cols..(row + 1) * self.cols).unwrap_or(&[])
+    }
+    
+    /// Text of the cells, one line per row
+    pub fn text(&self) -> String {
+        cells_text(&self.cells, self.cols)
+    }
+}
+
+/// Where the next character goes and the attributes it is printed with
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+pub struct CursorState {
     pub x: usize,
     pub y: usize,
+    pub attrs: CellAttrs,
+    /// The last column was just printed: the cursor stays on it, and the
+    /// next printable character wraps to a new line first. Any cursor
+    /// movement clears it without wrapping.
+    pub pending_wrap: bool,
+}
+
+/// Modes applications switch that change how output is applied or input encoded
+#[derive(Default)]
+pub struct TerminalModes {
+    // IRM (CSI 4 h): printing shifts the rest of the line right instead of overwriting
+    pub insert: bool,
+    // DECCKM (CSI ? 1 h): cursor keys send SS3 (ESC O A) instead of CSI (ESC [ A)
+    pub app_cursor_keys: bool,
+    // Kitty keyboard protocol enhancement stack
+    pub keyboard: KeyboardModes,
+}
+
+/// Primary screen contents stashed while the alternate screen is shown
+#[derive(Serialize, Deserialize)]
+struct SavedScreen {
+    #[serde(with = "crate::session::cell_runs")]
+    cells: Vec<Cell>,
+    wrapped: Vec<bool>,
+    // Position and SGR attributes when the alt screen was entered
+    cursor: CursorState,
+    // Each screen has its own keyboard enhancement stack
+    #[serde(skip)]
+    keyboard: KeyboardModes,
+}
+
+// Serialized fields come first and in the same order as in `GridState`,
+// which is what a dump deserializes into
+#[derive(Serialize, Deserialize)]
+#[serde(from = "GridState")]
+pub struct Grid {
+    pub cols: usize,
+    pub rows: usize,
+    #[serde(with = "crate::session::cell_runs")]
+    pub cells: Vec<Cell>,
+    /// Per row: soft-wrapped, i.e. its text continues on the next row
+    pub wrapped: Vec<bool>,
+    // Cursor position and the SGR pen
+    pub cursor: CursorState,
     pub scrollback: ScrollbackBuffer,
-    // Current text attributes
-    pub current_fg: Color,
-    pub current_bg: Color,
-    pub current_bold: bool,
-    pub current_italic: bool,
-    pub current_underline: bool,
+    // Shell-integration marks (absolute rows)
+    pub marks: MarkList,
+    // Some while the alternate screen is active
+    saved_primary: Option<SavedScreen>,
+    // Horizontal tab stops, one flag per column (HTS sets, TBC clears)
+    tab_stops: Vec<bool>,
+    // Scroll region set by DECSTBM, top and bottom rows inclusive; None is
+    // the whole screen
+    #[serde(skip_serializing)]
+    scroll_margins: Option<(usize, usize)>,
+    // OSC 1337 subcommands we don't handle, for diagnostics
+    #[serde(skip_serializing)]
+    pub unknown_osc_1337: usize,
+    // BELs received since the embedder last called `take_bells`
+    #[serde(skip_serializing)]
+    pub bells: usize,
+    // Keep the screen in scrollback when it is cleared (general.clear_pushes_to_scrollback)
+    #[serde(skip_serializing)]
+    pub clear_pushes_to_scrollback: bool,
+    #[serde(skip_serializing)]
+    pub modes: TerminalModes,
+    // Theme colors, for answering color queries; the renderer keeps its own copy
+    #[serde(skip_serializing)]
+    pub palette: Palette,
+    // Leading cell of the last printed character, which combining characters attach to
+    #[serde(skip_serializing)]
+    last_cell: Option<(usize, usize)>,
+    // Per visible row: changed since the last `take_dirty_rows`
+    #[serde(skip_serializing)]
+    dirty: Vec<bool>,
+    // Bumped whenever a row is dirtied, so cached display content can tell it's stale
+    #[serde(skip_serializing)]
+    generation: u64,
+    #[serde(skip_serializing)]
+    display_cache: RefCell<DisplayCache>,
+    #[serde(skip_serializing)]
+    pub(crate) link_cache: RefCell<LinkCache>,
+}
+
+// What a dumped grid holds; the rest starts fresh on load
+#[derive(Deserialize)]
+struct GridState {
+    cols: usize,
+    rows: usize,
+    #[serde(with = "crate::session::cell_runs")]
+    cells: Vec<Cell>,
+    wrapped: Vec<bool>,
+    cursor: CursorState,
+    scrollback: ScrollbackBuffer,
+    marks: MarkList,
+    saved_primary: Option<SavedScreen>,
+    tab_stops: Vec<bool>,
+}
+
+impl From<GridState> for Grid {
+    fn from(state: GridState) -> Self {
+        let mut grid = Grid::new(state.cols, state.rows, 0);
+        grid.cells = state.cells;
+        grid.wrapped = state.wrapped;
+        grid.cursor = state.cursor;
+        grid.scrollback = state.scrollback;
+        grid.marks = state.marks;
+        grid.saved_primary = state.saved_primary;
+        grid.tab_stops = state.tab_stops;
+        grid
+    }
+}
+
+/// Last `get_cells_for_display` / `get_display_content` results, valid for
+/// the generation, view top and size in `key`
+#[derive(Default)]
+struct DisplayCache {
+    key: Option<(u64, usize, usize, usize)>,
+    cells: Option<Arc<[Cell]>>,
+    text: Option<Arc<str>>,
+}
+
+// Default stops every 8 columns
+const TAB_WIDTH: usize = 8;
+
+fn is_default_tab_stop(col: usize) -> bool {
+    col > 0 && col.rem_euclid(TAB_WIDTH) == 0
 }
 
 impl Grid {
-    pub fn new(cols: usize, rows: usize) -> Self {
+    /// Blank grid keeping up to `scrollback_lines` lines of history (0 for
+    /// none). A grid is at least one cell; zero sizes are a caller bug and become 1
+    pub fn new(cols: usize, rows: usize, scrollback_lines: usize) -> Self {
+        debug_assert!(cols > 0 && rows > 0, "Grid::new({}, {}): grids need at least one cell", cols, rows);
+        let (cols, rows) = (cols.max(1), rows.max(1));
         Self { 
             cols, 
             rows, 
             cells: vec![Cell::default(); cols * rows], 
-            x: 0, 
-            y: 0,
-            scrollback: ScrollbackBuffer::new(10000), // 10k lines of scrollback
-            current_fg: Color::default(),
-            current_bg: Color::BLACK,
-            current_bold: false,
-            current_italic: false,
-            current_underline: false,
+            wrapped: vec![false; rows],
+            cursor: CursorState::default(),
+            scrollback: ScrollbackBuffer::new(scrollback_lines),
+            marks: MarkList::new(),
+            scroll_margins: None,
+            unknown_osc_1337: 0,
+            bells: 0,
+            clear_pushes_to_scrollback: false,
+            modes: TerminalModes::default(),
+            palette: Palette::default(),
+            saved_primary: None,
+            tab_stops: (0..cols).map(is_default_tab_stop).collect(),
+            last_cell: None,
+            dirty: vec![true; rows],
+            generation: 0,
+            display_cache: RefCell::default(),
+            link_cache: RefCell::default(),
         }
     }
     
     pub fn resize(&mut self, cols: usize, rows: usize) {
+        let (cols, rows) = (cols.max(1), rows.max(1));
+        self.resize_tab_stops(cols);
+        self.last_cell = None;
         self.cols = cols; 
         self.rows = rows;
-        self.cells.resize(cols * rows, Cell::default());
+        self.scroll_margins = None;
+        self.cells.resize(cols * rows, self.blank_cell());
+        self.wrapped = vec![false; rows];
+        self.dirty = vec![true; rows];
         self.clear_all();
-        self.x = 0; 
-        self.y = 0;
+        self.cursor.x = 0; 
+        self.cursor.y = 0;
+        self.cursor.pending_wrap = false;
+        let blank = self.blank_cell();
+        if let Some(saved) = &mut self.saved_primary {
+            saved.cells = vec![blank; cols * rows];
+            saved.wrapped = vec![false; rows];
+            saved.cursor.x = 0;
+            saved.cursor.y = 0;
+            saved.cursor.pending_wrap = false;
+        }
     }
     
-    pub fn resize_preserve(&mut self, new_cols: usize, new_rows: usize) {
+    /// Resize keeping content. On the primary screen every row keeps its
+    /// absolute position: when shrinking, blank rows below the cursor go
+    /// first and then the top rows move into scrollback; when growing, rows
+    /// come back out of scrollback above the screen. The alternate screen
+    /// has no scrollback and stays bottom-aligned.
+    pub fn resize_preserve(&mut self, new_cols: usize, new_rows: usize) -> Resize {
+        let (new_cols, new_rows) = (new_cols.max(1), new_rows.max(1));
         if new_cols == self.cols && new_rows == self.rows { 
-            return; 
+            return Resize { shift: 0, cols: new_cols, lines: self.scrollable_lines() };
         }
 
-        let old_cols = self.cols;
-        let old_rows = self.rows;
-        let old_cells = std::mem::take(&mut self.cells);
-
+        let blank = self.blank_cell();
+        if self.cells.len() != self.cols * self.rows {
+            tracing::warn!(
+                "Grid has {} cells for {}x{}; missing cells become blank",
+                self.cells.len(), self.cols, self.rows
+            );
+            self.cells.resize(self.cols * self.rows, blank);
+        }
+        let old_size = (self.cols, self.rows);
+        let new_size = (new_cols, new_rows);
+        let mut screen = ScreenRows {
+            cells: std::mem::take(&mut self.cells),
+            wrapped: std::mem::take(&mut self.wrapped),
+            cursor: (self.cursor.x, self.cursor.y),
+        };
+        // Absolute rows only move when scrollback lines are evicted, or on
+        // the alt screen (no scrollback), which stays bottom-aligned
+        let shift = if let Some(saved) = &mut self.saved_primary {
+            let mut primary = ScreenRows {
+                cells: std::mem::take(&mut saved.cells),
+                wrapped: std::mem::take(&mut saved.wrapped),
+                cursor: (saved.cursor.x, saved.cursor.y),
+            };
+            let evicted = primary.resize_against(&mut self.scrollback, old_size, new_size, blank);
+            self.marks.discard_evicted(evicted);
+            (saved.cells, saved.wrapped, (saved.cursor.x, saved.cursor.y)) = (primary.cells, primary.wrapped, primary.cursor);
+            saved.cursor.pending_wrap = false;
+            screen.resize_bottom_aligned(old_size, new_size, blank)
+        } else {
+            let evicted = screen.resize_against(&mut self.scrollback, old_size, new_size, blank);
+            self.marks.discard_evicted(evicted);
+            evicted as isize
+        };
+        (self.cells, self.wrapped, (self.cursor.x, self.cursor.y)) = (screen.cells, screen.wrapped, screen.cursor);
+        // The cursor column no longer means the same thing at a new width
+        self.cursor.pending_wrap = false;
+        self.dirty = vec![true; new_rows];
+        self.resize_tab_stops(new_cols);
+        self.last_cell = None;
         self.cols = new_cols;
         self.rows = new_rows;
-        self.cells = vec![Cell::default(); new_cols * new_rows];
+        self.scroll_margins = None;
+        Resize { shift, cols: new_cols, lines: self.scrollable_lines() }
+    }
+    
+    /// Resize by rewrapping: soft-wrapped rows (scrollback included) are joined
+    /// back into logical lines and broken again at `new_cols`, and the cursor
+    /// stays on the character it was on. The returned map moves positions held
+    /// outside the grid, like a selection. Only for the primary screen; the
+    /// alternate screen belongs to an app that redraws on resize.
+    pub fn reflow(&mut self, new_cols: usize, new_rows: usize) -> Reflow {
+        let new_cols = new_cols.max(1);
+        let new_rows = new_rows.max(1);
+        let blank = self.blank_cell();
+        let is_blank = |c: &Cell| c.is_empty() && c.bg == CellColor::Default;
 
-        let keep_rows = old_rows.min(new_rows);
-        let keep_cols = old_cols.min(new_cols);
+        // Scrollback, then the screen down to the cursor or the last text
+        let mut rows = self.scrollback.drain_lines();
+        let old_scrollback = rows.len();
+        let cols = self.cols;
+        let last = (0..self.rows)
+            .rev()
+            .find(|&r| self.wrapped[r] || !self.cells[r * cols..(r + 1) * cols].iter().all(is_blank))
+            .unwrap_or(0)
+            .max(self.cursor.y);
+        for r in 0..=last {
+            rows.push((self.cells[r * cols..(r + 1) * cols].to_vec(), self.wrapped[r]));
+        }
+
+        // Join wrapped rows into logical lines
+        let cursor_abs = old_scrollback + self.cursor.y;
+        let mut old_rows = Vec::with_capacity(rows.len());
+        let mut logical: Vec<Vec<Cell>> = Vec::new();
+        let mut cursor = (0, 0);
+        let mut continues = false;
+        for (abs, (cells, wrapped)) in rows.into_iter().enumerate() {
+            if !continues || logical.is_empty() {
+                logical.push(Vec::new());
+            }
+            let line_idx = logical.len() - 1;
+            let line = &mut logical[line_idx];
+            old_rows.push((line_idx, line.len()));
+            if abs == cursor_abs {
+                cursor = (line_idx, line.len() + self.cursor.x);
+            }
+            line.extend(cells);
+            continues = wrapped;
+        }
 
-        // Copy overlapping area, bottom-aligned like real terminals
-        for r in 0..keep_rows {
-            let src_r = old_rows - keep_rows + r;
-            let dst_r = new_rows - keep_rows + r;
+        // Drop trailing padding, but keep the cursor's cell
+        for (i, line) in logical.iter_mut().enumerate() {
+            let keep = if i == cursor.0 { cursor.1 + 1 } else { 0 };
+            while line.len() > keep && line.last().is_some_and(is_blank) {
+                line.pop();
+            }
+            line.resize(line.len().max(keep), blank);
+        }
 
-            // Copy only the overlapping width (left aligned)
-            for c in 0..keep_cols {
-                let src_idx = src_r * old_cols + c;
-                let dst_idx = dst_r * new_cols + c;
-                self.cells[dst_idx] = old_cells[src_idx];
+        // Break each line at the new width, never between a wide character and its trailer
+        let mut out: Vec<(Vec<Cell>, bool)> = Vec::new();
+        let mut lines = Vec::with_capacity(logical.len());
+        for line in &logical {
+            let first = out.len();
+            let ranges = wrap_ranges(line, new_cols);
+            let starts = ranges.iter().map(|r| r.start).collect();
+            for range in ranges {
+                let more = range.end < line.len();
+                let mut row = line[range].to_vec();
+                row.resize(new_cols, blank);
+                out.push((row, more));
             }
-            // Remaining columns (if any) are already spaces
+            lines.push((first, starts));
         }
 
-        // Clamp cursor into bounds, don't reset it
-        if self.y >= self.rows { 
-            self.y = self.rows.saturating_sub(1); 
+        let mut reflow = Reflow {
+            old_rows,
+            lines,
+            old_scrollback,
+            new_scrollback: 0,
+            evicted: 0,
+            cols: new_cols,
+            rows: new_rows,
+        };
+        let (cursor_col, cursor_row) = reflow.place(cursor.0, cursor.1);
+
+        // The screen shows the newest rows but never starts below the cursor
+        let top = out.len().saturating_sub(new_rows).min(cursor_row);
+        let mut out = out.into_iter();
+        for (cells, wrapped) in out.by_ref().take(top) {
+            reflow.evicted += self.scrollback.push_line(cells, wrapped);
         }
-        if self.x >= self.cols { 
-            self.x = self.cols.saturating_sub(1); 
+        reflow.new_scrollback = self.scrollback.len();
+        self.cells = vec![blank; new_cols * new_rows];
+        self.wrapped = vec![false; new_rows];
+        self.dirty = vec![true; new_rows];
+        for (r, (cells, wrapped)) in out.take(new_rows).enumerate() {
+            self.cells[r * new_cols..(r + 1) * new_cols].copy_from_slice(&cells);
+            self.wrapped[r] = wrapped;
+        }
+        // A continuation that didn't fit below the screen is gone
+        self.wrapped[new_rows - 1] = false;
+
+        self.marks.remap(|pos| reflow.abs_position(pos));
+        self.cursor.x = cursor_col.min(new_cols - 1);
+        self.cursor.y = cursor_row - top;
+        self.cursor.pending_wrap = false;
+        self.resize_tab_stops(new_cols);
+        self.last_cell = None;
+        self.cols = new_cols;
+        self.rows = new_rows;
+        self.scroll_margins = None;
+        reflow
+    }
+    
+    fn mark_dirty(&mut self, row: usize) {
+        if let Some(d) = self.dirty.get_mut(row) {
+            *d = true;
+        }
+        self.generation += 1;
+    }
+    
+    fn mark_all_dirty(&mut self) {
+        self.dirty.fill(true);
+        self.generation += 1;
+    }
+    
+    /// Bytes held by the screen cells (both screens while the alternate one
+    /// is up) and by scrollback
+    pub fn heap_usage(&self) -> HeapUsage {
+        let saved = self.saved_primary.as_ref().map_or(0, |s| s.cells.len());
+        HeapUsage {
+            cells: (self.cells.len() + saved) * std::mem::size_of::<Cell>(),
+            scrollback: self.scrollback.bytes(),
         }
     }
     
+    /// Changes with every mutation of visible content (through `Grid` methods)
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+    
+    /// Whether any visible row changed since the last `take_dirty_rows`
+    pub fn has_dirty_rows(&self) -> bool {
+        self.dirty.contains(&true)
+    }
+    
+    /// Rows changed since the last call, top to bottom; clears the record.
+    /// Scrolling, resizes and screen switches mark every row dirty.
+    pub fn take_dirty_rows(&mut self) -> Vec<usize> {
+        let rows = (0..self.dirty.len()).filter(|&r| self.dirty[r]).collect();
+        self.dirty.fill(false);
+        rows
+    }
+    
+    // Keep stops in surviving columns; new columns get the defaults
+    fn resize_tab_stops(&mut self, cols: usize) {
+        let old = self.tab_stops.len();
+        self.tab_stops.truncate(cols);
+        self.tab_stops.extend((old..cols).map(is_default_tab_stop));
+    }
+    
+    /// Column the next tab moves to: the next stop right of the cursor, or the last column
+    pub fn next_tab_stop(&self) -> usize {
+        let last = self.cols.saturating_sub(1);
+        (self.cursor.x + 1..last).find(|&c| self.tab_stops[c]).unwrap_or(last)
+    }
+    
+    /// HTS: set a tab stop at the cursor column
+    pub fn set_tab_stop(&mut self) {
+        if let Some(stop) = self.tab_stops.get_mut(self.cursor.x) {
+            *stop = true;
+        }
+    }
+    
+    /// TBC 0: clear the tab stop at the cursor column
+    pub fn clear_tab_stop(&mut self) {
+        if let Some(stop) = self.tab_stops.get_mut(self.cursor.x) {
+            *stop = false;
+        }
+    }
+    
+    /// TBC 3: clear every tab stop
+    pub fn clear_all_tab_stops(&mut self) {
+        self.tab_stops.fill(false);
+    }
+    
     fn idx(&self, x: usize, y: usize) -> usize { 
         y * self.cols + x 
     }
     
+    /// An empty cell in the theme's default colors
+    pub fn blank_cell(&self) -> Cell {
+        Cell::default()
+    }
+    
+    /// Switch the theme colors. Cells keep palette references, so nothing
+    /// is rewritten; every row is redrawn with the new colors
+    pub fn set_palette(&mut self, palette: Palette) {
+        self.palette = palette;
+        self.mark_all_dirty();
+    }
+    
+    /// Resolved colors as JSON for `TDT_THEME_JSON`:
+    /// `{"foreground":"#rrggbb","background":"#rrggbb","palette":[256 × "#rrggbb"]}`
+    pub fn theme_json(&self) -> String {
+        let palette: Vec<String> = self.palette.ansi.iter().map(Color::to_hex).collect();
+        serde_json::json!({
+            "foreground": self.palette.foreground.to_hex(),
+            "background": self.palette.background.to_hex(),
+            "palette": palette,
+        })
+        .to_string()
+    }
+    
     pub fn clear_all(&mut self) { 
+        let blank = self.blank_cell();
         for c in &mut self.cells { 
-            *c = Cell::default(); 
+            *c = blank; 
         } 
+        self.wrapped.fill(false);
+        self.mark_all_dirty();
+    }
+    
+    /// Blank the visible screen and home the cursor; scrollback is untouched
+    pub fn clear_screen(&mut self) {
+        self.clear_all();
+        self.cursor.x = 0;
+        self.cursor.y = 0;
+        self.cursor.pending_wrap = false;
+    }
+    
+    /// Fill every cell with `ch` in the default colors (DECALN)
+    pub fn fill_with(&mut self, ch: char) {
+        let cell = Cell::new(ch, CellAttrs::default());
+        for c in &mut self.cells { 
+            *c = cell; 
+        } 
+        self.wrapped.fill(false);
+        self.mark_all_dirty();
     }
     
     pub fn clear_eol(&mut self) {
-        let start = self.idx(self.x, self.y);
-        let end = self.idx(self.cols - 1, self.y) + 1;
+        self.split_wide_pair(self.cursor.x, self.cursor.y);
+        let blank = self.blank_cell();
+        let start = self.idx(self.cursor.x, self.cursor.y);
+        let end = self.idx(self.cols - 1, self.cursor.y) + 1;
         for i in start..end { 
-            self.cells[i] = Cell::default(); 
+            self.cells[i] = blank; 
         }
+        self.mark_dirty(self.cursor.y);
     }
     
     pub fn clear_line(&mut self, row: usize) {
         let row = row.min(self.rows.saturating_sub(1));
         let start = row * self.cols;
         let end = start + self.cols;
+        let blank = self.blank_cell();
         for c in &mut self.cells[start..end] { 
-            *c = Cell::default(); 
+            *c = blank; 
         }
+        self.wrapped[row] = false;
+        self.mark_dirty(row);
     }
     
     pub fn clear_eol_from_cursor(&mut self) {
-        let row = self.y.min(self.rows.saturating_sub(1));
-        let start = row * self.cols + self.x.min(self.cols.saturating_sub(1));
+        let row = self.cursor.y.min(self.rows.saturating_sub(1));
+        self.split_wide_pair(self.cursor.x, row);
+        let start = row * self.cols + self.cursor.x.min(self.cols.saturating_sub(1));
         let end = row * self.cols + self.cols;
+        let blank = self.blank_cell();
         for c in &mut self.cells[start..end] { 
-            *c = Cell::default(); 
+            *c = blank; 
         }
+        self.mark_dirty(row);
     }
     
     pub fn clear_bol_to_cursor(&mut self) {
-        let row = self.y.min(self.rows.saturating_sub(1));
+        let row = self.cursor.y.min(self.rows.saturating_sub(1));
+        self.split_wide_pair(self.cursor.x, row);
         let start = row * self.cols;
-        let end = row * self.cols + self.x.min(self.cols.saturating_sub(1)) + 1;
+        let end = row * self.cols + self.cursor.x.min(self.cols.saturating_sub(1)) + 1;
+        let blank = self.blank_cell();
         for c in &mut self.cells[start..end] { 
-            *c = Cell::default(); 
+            *c = blank; 
         }
+        self.mark_dirty(row);
     }
     
     pub fn put(&mut self, ch: char) {
-        let w = UnicodeWidthChar::width(ch).unwrap_or(1).max(1).min(2);
-        if self.x >= self.cols { 
-            self.wrap(); 
+        // Direction controls could visually reorder other cells; drop them
+        if bidi::is_bidi_control(ch) {
+            return;
+        }
+        if self.extend_last_cell(ch) {
+            return;
+        }
+        // A single column can't hold a wide character; it takes the one cell
+        let w = if self.cols < 2 { 1 } else { char_cells(ch) };
+        if self.cursor.pending_wrap {
+            self.wrap();
+        }
+        // A wide character that doesn't fit in the last column starts the next row
+        if w == 2 && self.cursor.x + 1 >= self.cols {
+            self.wrap();
+        }
+        
+        if self.modes.insert {
+            self.insert_blanks(w);
+        }
+        
+        // Overwriting either half of a wide character blanks the other half
+        self.split_wide_pair(self.cursor.x, self.cursor.y);
+        if w == 2 {
+            self.split_wide_pair(self.cursor.x + 1, self.cursor.y);
+        }
+        
+        let idx = self.cursor.y * self.cols + self.cursor.x;
+        let cell = Cell::new(ch, self.cursor.attrs);
+        self.cells[idx] = cell;
+        if w == 2 {
+            self.cells[idx + 1] = cell.trailer();
         }
-        let idx = self.y * self.cols + self.x;
-        self.cells[idx].ch = ch;
-        self.cells[idx].fg = self.current_fg;
-        self.cells[idx].bg = self.current_bg;
-        self.cells[idx].bold = self.current_bold;
-        self.cells[idx].italic = self.current_italic;
-        self.cells[idx].underline = self.current_underline;
-        self.x = (self.x + w).min(self.cols.saturating_sub(1));
+        self.mark_dirty(self.cursor.y);
+        self.last_cell = Some((self.cursor.x, self.cursor.y));
+        if self.cursor.x + w >= self.cols {
+            self.cursor.x = self.cols.saturating_sub(1);
+            self.cursor.pending_wrap = true;
+        } else {
+            self.cursor.x += w;
+        }
+    }
+    
+    // Shift the cells from the cursor to the end of the row right by `n`,
+    // dropping what falls off the edge; a wide character cut in half there is blanked
+    fn insert_blanks(&mut self, n: usize) {
+        let (x, y) = (self.cursor.x, self.cursor.y);
+        if x >= self.cols {
+            return;
+        }
+        self.split_wide_pair(x, y);
+        let row_end = self.idx(0, y) + self.cols;
+        let start = self.idx(x, y);
+        let n = n.min(row_end - start);
+        self.cells[start..row_end].rotate_right(n);
+        let blank = self.blank_cell();
+        for c in &mut self.cells[start..start + n] {
+            *c = blank;
+        }
+        let last = row_end - 1;
+        if !self.cells[last].is_wide_trailer() && char_cells(self.cells[last].ch()) == 2 {
+            self.cells[last] = blank;
+        }
+        self.mark_dirty(y);
+    }
+    
+    // Append `ch` to the character in the last printed cell if it belongs to
+    // that cluster (see `cluster::extends_cluster`). Returns false otherwise.
+    fn extend_last_cell(&mut self, ch: char) -> bool {
+        let Some((col, row)) = self.last_cell else {
+            return false;
+        };
+        if row != self.cursor.y || col >= self.cols {
+            return false;
+        }
+        let idx = self.idx(col, row);
+        let cell = self.cells[idx];
+        if cell.ch() == '\0' || cell.is_wide_trailer() {
+            return false;
+        }
+        let mut text = String::new();
+        cell.push_text(&mut text);
+        if !cluster::extends_cluster(&text, ch) {
+            return false;
+        }
+        
+        let was_cells = cluster::cluster_cells(&text);
+        text.push(ch);
+        self.cells[idx] = cell.with_text(&text);
+        
+        // A flag's second regional indicator turns one cell into a wide pair
+        if was_cells == 1 && cluster::cluster_cells(&text) == 2 && col + 1 < self.cols {
+            self.split_wide_pair(col + 1, row);
+            self.cells[idx + 1] = cell.trailer();
+            if self.cursor.x == col + 1 {
+                if col + 2 < self.cols {
+                    self.cursor.x = col + 2;
+                } else {
+                    self.cursor.pending_wrap = true;
+                }
+            }
+        }
+        self.mark_dirty(row);
+        true
+    }
+    
+    // If (col, row) is half of a wide character, blank the other half, so a
+    // write or erase that covers only this cell can't leave half a character
+    fn split_wide_pair(&mut self, col: usize, row: usize) {
+        if col >= self.cols || row >= self.rows {
+            return;
+        }
+        let blank = self.blank_cell();
+        let idx = self.idx(col, row);
+        if self.cells[idx].is_wide_trailer() && col > 0 {
+            self.cells[idx - 1] = blank;
+            self.cells[idx] = blank;
+        }
+        if col + 1 < self.cols && self.cells[idx + 1].is_wide_trailer() {
+            self.cells[idx + 1] = blank;
+        }
+        self.mark_dirty(row);
     }
     
+    /// Continue on the next row, marking this one as soft-wrapped
     pub fn wrap(&mut self) { 
         self.cr(); 
-        self.lf(); 
+        self.wrapped[self.cursor.y] = true;
+        self.index();
     }
     
     pub fn cr(&mut self) { 
-        self.x = 0; 
+        self.cursor.x = 0; 
+        self.cursor.pending_wrap = false;
     }
     
+    /// Explicit line feed: the current row ends its line
     pub fn lf(&mut self) {
-        if self.y + 1 < self.rows { 
-            self.y += 1; 
-        } else {
-            // Save the top line to scrollback before scrolling
-            let mut line = Vec::with_capacity(self.cols);
-            for c in 0..self.cols {
-                line.push(self.cells[c]);
-            }
-            self.scrollback.push_line(line);
-            
-            // scroll up by 1
-            let cols = self.cols;
-            self.cells.rotate_left(cols);
-            let start = (self.rows - 1) * self.cols;
-            for i in start..self.cells.len() { 
-                self.cells[i] = Cell::default(); 
+        self.wrapped[self.cursor.y] = false;
+        self.index();
+    }
+    
+    /// Scroll region as (top, bottom) rows, inclusive: what DECSTBM set, or
+    /// the whole screen
+    pub fn scroll_margins(&self) -> (usize, usize) {
+        match self.scroll_margins {
+            Some((top, bottom)) if top < bottom && bottom < self.rows => (top, bottom),
+            _ => (0, self.rows - 1),
+        }
+    }
+    
+    /// DECSTBM: scroll only rows `top..=bottom` from now on. A region of
+    /// fewer than two rows or past the screen resets to the whole screen.
+    /// The cursor goes home, as on a VT100.
+    pub fn set_scroll_margins(&mut self, top: usize, bottom: usize) {
+        self.scroll_margins = (top < bottom && bottom < self.rows).then_some((top, bottom));
+        self.cursor.x = 0;
+        self.cursor.y = 0;
+        self.cursor.pending_wrap = false;
+    }
+    
+    // Move down a row, scrolling the region at its bottom margin. Only a
+    // whole-screen scroll on the primary screen feeds the scrollback.
+    fn index(&mut self) {
+        self.cursor.pending_wrap = false;
+        let (top, bottom) = self.scroll_margins();
+        if self.cursor.y == bottom {
+            // Full-screen apps own the alternate screen; nothing goes to scrollback
+            let to_scrollback = !self.is_alt_screen() && (top, bottom) == (0, self.rows - 1);
+            self.scroll_region_up(top, bottom, 1, CellColor::Default, to_scrollback);
+        } else if self.cursor.y + 1 < self.rows {
+            self.cursor.y += 1;
+        }
+    }
+    
+    /// Reverse index: move up a row, scrolling the region down at its top
+    /// margin; the row pushed past the bottom margin is lost.
+    pub fn reverse_lf(&mut self) {
+        self.cursor.pending_wrap = false;
+        let (top, bottom) = self.scroll_margins();
+        if self.cursor.y == top {
+            self.scroll_region_down(top, bottom, 1, CellColor::Default);
+        } else if self.cursor.y > 0 {
+            self.cursor.y -= 1;
+        }
+    }
+    
+    /// Shift screen rows `top..=bottom` up by `n`, blanking the `n` rows
+    /// vacated at the bottom with background `bg`. `n` saturates at the
+    /// region height and `bottom` at the last row; an empty region is left
+    /// alone. With `to_scrollback` the rows shifted off the top are kept in
+    /// the scrollback, as when the whole screen scrolls on the primary screen.
+    pub fn scroll_region_up(&mut self, top: usize, bottom: usize, n: usize, bg: CellColor, to_scrollback: bool) {
+        let bottom = bottom.min(self.rows.saturating_sub(1));
+        if top > bottom || self.rows == 0 {
+            return;
+        }
+        let n = n.min(bottom - top + 1);
+        let cols = self.cols;
+        if to_scrollback {
+            for r in top..top + n {
+                let line = self.cells[r * cols..(r + 1) * cols].to_vec();
+                let evicted = self.scrollback.push_line(line, self.wrapped[r]);
+                self.marks.discard_evicted(evicted);
             }
         }
+        self.cells[top * cols..(bottom + 1) * cols].rotate_left(n * cols);
+        self.wrapped[top..=bottom].rotate_left(n);
+        self.blank_rows(bottom + 1 - n..bottom + 1, bg);
+        for r in top..=bottom {
+            self.mark_dirty(r);
+        }
+    }
+    
+    /// Shift screen rows `top..=bottom` down by `n`, blanking the `n` rows
+    /// vacated at the top with background `bg`; rows pushed past `bottom`
+    /// are lost. Counts saturate as in `scroll_region_up`.
+    pub fn scroll_region_down(&mut self, top: usize, bottom: usize, n: usize, bg: CellColor) {
+        let bottom = bottom.min(self.rows.saturating_sub(1));
+        if top > bottom || self.rows == 0 {
+            return;
+        }
+        let n = n.min(bottom - top + 1);
+        let cols = self.cols;
+        self.cells[top * cols..(bottom + 1) * cols].rotate_right(n * cols);
+        self.wrapped[top..=bottom].rotate_right(n);
+        self.blank_rows(top..top + n, bg);
+        for r in top..=bottom {
+            self.mark_dirty(r);
+        }
+    }
+    
+    // Blank whole rows with background `bg`; they end their lines
+    fn blank_rows(&mut self, rows: std::ops::Range<usize>, bg: CellColor) {
+        let blank = Cell { bg, ..Cell::default() };
+        self.cells[rows.start * self.cols..rows.end * self.cols].fill(blank);
+        self.wrapped[rows].fill(false);
+    }
+    
+    /// Move the visible rows, up to the last non-blank one, into scrollback so a
+    /// clear doesn't destroy them. The alternate screen never feeds scrollback.
+    pub fn push_screen_to_scrollback(&mut self) {
+        if self.is_alt_screen() {
+            return;
+        }
+        let cols = self.cols;
+        let is_blank = |row: &[Cell]| row.iter().all(Cell::is_empty);
+        let Some(last) = (0..self.rows).rev().find(|&r| !is_blank(&self.cells[r * cols..(r + 1) * cols])) else {
+            return;
+        };
+        for r in 0..=last {
+            let line = self.cells[r * cols..(r + 1) * cols].to_vec();
+            let evicted = self.scrollback.push_line(line, self.wrapped[r]);
+            self.marks.discard_evicted(evicted);
+        }
+        self.mark_all_dirty();
+    }
+    
+    /// Whether the alternate screen (DECSET 47/1047/1049) is shown
+    pub fn is_alt_screen(&self) -> bool {
+        self.saved_primary.is_some()
+    }
+    
+    /// Switch to a blank alternate screen, stashing the primary screen and cursor
+    pub fn enter_alt_screen(&mut self) {
+        if self.is_alt_screen() {
+            return;
+        }
+        let blank = vec![self.blank_cell(); self.cols * self.rows];
+        let cells = std::mem::replace(&mut self.cells, blank);
+        let wrapped = std::mem::replace(&mut self.wrapped, vec![false; self.rows]);
+        let keyboard = std::mem::take(&mut self.modes.keyboard);
+        self.saved_primary = Some(SavedScreen { cells, wrapped, cursor: self.cursor, keyboard });
+        self.last_cell = None;
+        self.mark_all_dirty();
+    }
+    
+    /// Restore the primary screen exactly as it was when the alt screen was
+    /// entered: its cells, cursor and SGR attributes
+    pub fn exit_alt_screen(&mut self) {
+        if let Some(saved) = self.saved_primary.take() {
+            self.cells = saved.cells;
+            self.wrapped = saved.wrapped;
+            self.cursor = saved.cursor;
+            self.modes.keyboard = saved.keyboard;
+            self.last_cell = None;
+            // A region the full-screen app set doesn't carry over to the shell
+            self.scroll_margins = None;
+            self.mark_all_dirty();
+        }
+    }
+    
+    /// Lines the viewport can scroll through: the alt screen has no scrollback
+    pub fn scrollable_lines(&self) -> usize {
+        if self.is_alt_screen() {
+            self.rows
+        } else {
+            self.scrollback.len() + self.rows
+        }
     }
     
     pub fn to_string_lines(&self) -> String {
-        let mut s = String::with_capacity(self.rows * (self.cols + 1));
-        for r in 0..self.rows {
-            for c in 0..self.cols { 
-                let ch = self.cells[self.idx(c, r)].ch;
-                s.push(if ch == '\0' { ' ' } else { ch });
+        cells_text(&self.cells, self.cols)
+    }
+    
+    pub fn get_text_in_region(&self, x0: usize, y0: usize, x1: usize, y1: usize) -> String {
+        let mut s = String::new();
+        for row in y0..=y1 {
+            for col in x0..=x1 {
+                let idx = self.idx(col.min(self.cols.saturating_sub(1)), row.min(self.rows.saturating_sub(1)));
+                self.cells[idx].push_text(&mut s);
+            }
+            if row < y1 {
+                s.push('\n');
             }
-            s.push('\n');
         }
         s
     }
     
-    pub fn get_text_in_region(&self, x0: usize, y0: usize, x1: usize, y1: usize) -> String {
+    /// `get_text_in_region` for a viewport whose row 0 is absolute row
+    /// `top_abs`, so rows scrolled back into history read from scrollback.
+    /// Scrollback lines narrower than the region give only the cells they have.
+    pub fn get_text_in_absolute_region(&self, top_abs: usize, x0: usize, y0: usize, x1: usize, y1: usize) -> String {
         let mut s = String::new();
         for row in y0..=y1 {
-            for col in x0..=x1 {
-                let idx = self.idx(col.min(self.cols-1), row.min(self.rows-1));
-                let ch = self.cells[idx].ch;
-                s.push(if ch == '\0' { ' ' } else { ch });
+            let line = self.row_cells(top_abs + row);
+            for cell in line.iter().take(x1 + 1).skip(x0) {
+                cell.push_text(&mut s);
             }
             if row < y1 {
                 s.push('\n');
@@ -278,6 +1231,234 @@ impl Grid {
         s
     }
     
+    /// Text of a linear selection from `start` to `end` (col, row), in either
+    /// order. Soft-wrapped rows are joined without a newline; blanks at the
+    /// end of a line are dropped, as is the padding a wrapped row gets when
+    /// a wide character moves to the next row.
+    pub fn get_text_between(&self, start: (usize, usize), end: (usize, usize)) -> String {
+        let top = self.history_len();
+        self.get_text_absolute((start.0, start.1 + top), (end.0, end.1 + top))
+    }
+    
+    /// `get_text_between` with absolute rows, so the selection may reach
+    /// back into scrollback
+    pub fn get_text_absolute(&self, start: (usize, usize), end: (usize, usize)) -> String {
+        let (start, end) = if (start.1, start.0) <= (end.1, end.0) { (start, end) } else { (end, start) };
+        let last_row = end.1.min(self.history_len() + self.rows - 1);
+        let mut s = String::new();
+        for row in start.1..=last_row {
+            let Some((line, wrapped)) = self.abs_row(row) else { continue };
+            // Scrollback lines keep the width they were pushed with, or less
+            // once compressed; a line with no cells is still a line break
+            let Some(last_col) = line.len().checked_sub(1) else {
+                if row < last_row && !wrapped {
+                    s.push('\n');
+                }
+                continue;
+            };
+            let c0 = if row == start.1 { start.0.min(last_col) } else { 0 };
+            let c1 = if row == last_row { end.0.min(last_col) } else { last_col };
+            let cells = &line[c0..=c1.max(c0)];
+            let mut n = cells.len();
+            if c1 == last_col {
+                // Spaces count as text inside a wrapped line but not at a line's end
+                let is_blank = |c: &Cell| {
+                    c.is_empty() && (c.ch() == '\0' || !wrapped)
+                };
+                while n > 0 && is_blank(&cells[n - 1]) {
+                    n -= 1;
+                }
+            }
+            for cell in &cells[..n] {
+                cell.push_text(&mut s);
+            }
+            if row < last_row && !wrapped {
+                s.push('\n');
+            }
+        }
+        s
+    }
+    
+    /// Absolute row of screen row 0: scrollback lines come first, except on
+    /// the alternate screen, which has none
+    pub fn history_len(&self) -> usize {
+        if self.is_alt_screen() { 0 } else { self.scrollback.len() }
+    }
+    
+    /// Cells of absolute row `abs` and whether its text continues on the next row
+    pub fn abs_row(&self, abs: usize) -> Option<(&[Cell], bool)> {
+        let history = self.history_len();
+        if abs < history {
+            Some((self.scrollback.line(abs)?, self.scrollback.is_wrapped(abs)))
+        } else if abs - history < self.rows {
+            let row = abs - history;
+            let start = self.idx(0, row);
+            Some((&self.cells[start..start + self.cols], self.wrapped[row]))
+        } else {
+            None
+        }
+    }
+    
+    /// Cells of absolute row `abs`; empty past the last row. Scrollback
+    /// lines keep the width they were pushed with
+    pub fn row_cells(&self, abs: usize) -> &[Cell] {
+        self.abs_row(abs).map_or(&[], |(line, _)| line)
+    }
+    
+    /// Text of absolute row `abs`, empty cells as spaces. Wide characters
+    /// appear once, so character index and column agree up to the first one
+    pub fn row_text(&self, abs: usize) -> String {
+        let mut s = String::new();
+        for cell in self.row_cells(abs) {
+            cell.push_text(&mut s);
+        }
+        s
+    }
+    
+    /// The `rows` rows of a viewport starting at absolute row `top_abs`,
+    /// across scrollback and screen; rows past the end are empty
+    pub fn visible_rows(&self, top_abs: usize, rows: usize) -> impl Iterator<Item = &[Cell]> + '_ {
+        (top_abs..top_abs + rows).map(move |abs| self.row_cells(abs))
+    }
+    
+    // Scrollback then the screen as logical lines, handed to `f` one at a
+    // time: wrapped rows joined and trailing blank cells dropped, without the
+    // blank lines after the last text. Only one line is held at once.
+    fn for_each_export_line(&self, mut f: impl FnMut(&[Cell]) -> std::io::Result<()>) -> std::io::Result<()> {
+        let history = self.scrollback.iter_lines().enumerate().map(|(i, line)| (line, self.scrollback.is_wrapped(i)));
+        let screen = self.cells.chunks(self.cols).zip(self.wrapped.iter().copied());
+        let is_blank = |c: &Cell| c.is_empty() && c.bg == CellColor::Default;
+        let mut line: Vec<Cell> = Vec::new();
+        // Blank lines seen since the last one with text, emitted once more text follows
+        let mut blank_run = 0;
+        for (cells, wrapped) in history.chain(screen) {
+            // Only the padding before a wide character that moved on is dropped inside a wrapped line
+            let end = if wrapped {
+                cells.iter().rposition(|c| c.ch() != '\0' || c.is_wide_trailer()).map_or(0, |i| i + 1)
+            } else {
+                cells.len()
+            };
+            line.extend_from_slice(&cells[..end]);
+            if !wrapped {
+                let end = line.iter().rposition(|c| !is_blank(c)).map_or(0, |i| i + 1);
+                line.truncate(end);
+                if line.is_empty() {
+                    blank_run += 1;
+                } else {
+                    for _ in 0..std::mem::take(&mut blank_run) {
+                        f(&[])?;
+                    }
+                    f(&line)?;
+                    line.clear();
+                }
+            }
+        }
+        if !line.is_empty() {
+            for _ in 0..blank_run {
+                f(&[])?;
+            }
+            f(&line)?;
+        }
+        Ok(())
+    }
+    
+    /// The whole history as plain text: scrollback then the screen, one
+    /// logical line per line. Wrapped rows are joined, trailing blanks are
+    /// trimmed and blank rows after the last text are left out.
+    pub fn export_text(&self) -> String {
+        let mut out = Vec::new();
+        self.write_all_text(&mut out).expect("writing to a Vec can't fail");
+        String::from_utf8(out).expect("cell text is UTF-8")
+    }
+    
+    /// `export_text` streamed to `out` a line at a time, so histories of
+    /// hundreds of megabytes are never held as one string
+    pub fn write_all_text(&self, out: &mut impl std::io::Write) -> std::io::Result<()> {
+        let mut s = String::new();
+        let mut any = false;
+        self.for_each_export_line(|line| {
+            s.clear();
+            for cell in line {
+                cell.push_text(&mut s);
+            }
+            let end = s.trim_end().len();
+            s.truncate(end);
+            s.push('\n');
+            any = true;
+            out.write_all(s.as_bytes())
+        })?;
+        if !any {
+            out.write_all(b"\n")?;
+        }
+        Ok(())
+    }
+    
+    /// `export_text` keeping colors and attributes as SGR sequences, for
+    /// output that looks the same when catted back into a terminal. SGR is
+    /// only emitted where attributes change, and each line ends reset.
+    pub fn export_ansi(&self) -> String {
+        let mut out = Vec::new();
+        self.write_all_ansi(&mut out).expect("writing to a Vec can't fail");
+        String::from_utf8(out).expect("cell text is UTF-8")
+    }
+    
+    /// `export_ansi` streamed to `out` a line at a time
+    pub fn write_all_ansi(&self, out: &mut impl std::io::Write) -> std::io::Result<()> {
+        let mut s = String::new();
+        let plain = CellAttrs::default();
+        self.for_each_export_line(|line| {
+            s.clear();
+            let mut pen = plain;
+            for cell in line {
+                self.push_sgr_change(&mut s, &pen, &cell.attrs());
+                pen = cell.attrs();
+                cell.push_text(&mut s);
+            }
+            if self.push_sgr_change(&mut String::new(), &pen, &plain) {
+                s.push_str("\x1b[0m");
+            }
+            s.push('\n');
+            out.write_all(s.as_bytes())
+        })
+    }
+    
+    // SGR taking the attributes of `from` to those of `to`; returns whether any changed
+    fn push_sgr_change(&self, s: &mut String, from: &CellAttrs, to: &CellAttrs) -> bool {
+        let mut codes: Vec<String> = Vec::new();
+        for (was, is, on, off) in [
+            (from.bold(), to.bold(), "1", "22"),
+            (from.italic(), to.italic(), "3", "23"),
+            (from.underline(), to.underline(), "4", "24"),
+        ] {
+            if was != is {
+                codes.push(if is { on } else { off }.to_string());
+            }
+        }
+        if from.fg != to.fg {
+            codes.push(match to.fg {
+                CellColor::Default => "39".to_string(),
+                CellColor::Indexed(n @ 0..=7) => (30 + n).to_string(),
+                CellColor::Indexed(n @ 8..=15) => (90 + n - 8).to_string(),
+                CellColor::Indexed(n) => format!("38;5;{}", n),
+                CellColor::Rgb(c) => format!("38;2;{};{};{}", c.r, c.g, c.b),
+            });
+        }
+        if from.bg != to.bg {
+            codes.push(match to.bg {
+                CellColor::Default => "49".to_string(),
+                CellColor::Indexed(n @ 0..=7) => (40 + n).to_string(),
+                CellColor::Indexed(n @ 8..=15) => (100 + n - 8).to_string(),
+                CellColor::Indexed(n) => format!("48;5;{}", n),
+                CellColor::Rgb(c) => format!("48;2;{};{};{}", c.r, c.g, c.b),
+            });
+        }
+        if codes.is_empty() {
+            return false;
+        }
+        s.push_str(&format!("\x1b[{}m", codes.join(";")));
+        true
+    }
+    
     pub fn selection_bounds(&self, start: (usize, usize), end: (usize, usize)) -> (usize, usize, usize, usize) {
         let (x0, y0) = start;
         let (x1, y1) = end;
@@ -288,95 +1469,339 @@ impl Grid {
         (minx, miny, maxx, maxy)
     }
     
-    /// Get display content including scrollback if scrolled
-    pub fn get_cells_for_display(&self) -> Vec<Cell> {
-        if self.scrollback.scroll_offset > 0 {
-            // We're scrolled - show scrollback content
-            let scrollback_lines = self.scrollback.get_visible_lines(self.rows);
-            let mut cells = Vec::new();
-            
-            for line in scrollback_lines {
-                for cell in line {
-                    cells.push(cell);
-                }
-            }
-            
-            // If we have fewer scrollback lines than viewport, show current grid too
-            let remaining_rows = self.rows.saturating_sub(self.scrollback.len());
-            if remaining_rows > 0 && self.scrollback.scroll_offset < self.scrollback.len() {
-                for r in 0..remaining_rows.min(self.rows) {
-                    for c in 0..self.cols {
-                        cells.push(self.cells[self.idx(c, r)]);
-                    }
-                }
-            }
-            
-            cells
+    /// Absolute row shown at the top of the viewport when scrolled through `scroll_up`/`page_up`
+    pub fn view_top(&self) -> usize {
+        if self.is_scrolled() {
+            self.scrollback.len().saturating_sub(self.scrollback.scroll_offset)
         } else {
-            // Normal view - return current grid cells
-            self.cells.clone()
+            self.scrollable_lines() - self.rows
         }
     }
     
-    pub fn get_display_content(&self) -> String {
-        if self.scrollback.scroll_offset > 0 {
-            // We're scrolled - show scrollback content
-            let scrollback_lines = self.scrollback.get_visible_lines(self.rows);
-            let mut s = String::new();
-            
-            for line in scrollback_lines {
-                for cell in line {
-                    s.push(if cell.ch == '\0' { ' ' } else { cell.ch });
-                }
-                s.push('\n');
-            }
-            
-            // If we have fewer scrollback lines than viewport, show current grid too
-            let remaining_rows = self.rows.saturating_sub(self.scrollback.len());
-            if remaining_rows > 0 && self.scrollback.scroll_offset < self.scrollback.len() {
-                for r in 0..remaining_rows.min(self.rows) {
-                    for c in 0..self.cols {
-                        let ch = self.cells[self.idx(c, r)].ch;
-                        s.push(if ch == '\0' { ' ' } else { ch });
-                    }
-                    s.push('\n');
-                }
-            }
-            
-            s
-        } else {
-            // Normal view - show current grid
-            self.to_string_lines()
+    /// Cells of `rows` rows starting at absolute row `top_abs` (scrollback
+    /// lines first, then the screen), row-major and `cols` wide. Rows past
+    /// the end are blank. Everything drawn is derived from this.
+    pub fn viewport_cells(&self, top_abs: usize, rows: usize) -> Vec<Cell> {
+        let blank = self.blank_cell();
+        let mut cells = Vec::with_capacity(rows * self.cols);
+        for line in self.visible_rows(top_abs, rows) {
+            // Scrollback lines keep the width they were pushed with
+            let n = line.len().min(self.cols);
+            cells.extend_from_slice(&line[..n]);
+            cells.resize(cells.len() + self.cols - n, blank);
+        }
+        cells
+    }
+    
+    /// Text of `viewport_cells`, one line per row
+    pub fn viewport_text(&self, top_abs: usize, rows: usize) -> String {
+        cells_text(&self.viewport_cells(top_abs, rows), self.cols)
+    }
+    
+    /// Cursor (col, row) within `rows` rows starting at absolute row `top_abs`,
+    /// or None when the cursor row is outside them
+    pub fn viewport_cursor(&self, top_abs: usize, rows: usize) -> Option<(usize, usize)> {
+        let row = (self.history_len() + self.cursor.y).checked_sub(top_abs).filter(|&r| r < rows)?;
+        Some((self.cursor.x, row))
+    }
+    
+    /// Cells and cursor of `rows` rows starting at absolute row `top_abs`;
+    /// the renderer draws nothing else
+    pub fn snapshot_viewport(&self, top_abs: usize, rows: usize) -> DisplaySnapshot {
+        DisplaySnapshot {
+            cells: self.viewport_cells(top_abs, rows),
+            cols: self.cols,
+            rows,
+            cursor: self.viewport_cursor(top_abs, rows),
         }
     }
     
+    /// Get display content including scrollback if scrolled. Cached until
+    /// the grid changes or scrolls, so repeated calls share one allocation
+    pub fn get_cells_for_display(&self) -> Arc<[Cell]> {
+        let mut cache = self.display_cache();
+        cache.cells.get_or_insert_with(|| self.viewport_cells(self.view_top(), self.rows).into()).clone()
+    }
+    
+    /// Text of `get_cells_for_display`, cached the same way
+    pub fn get_display_content(&self) -> Arc<str> {
+        let cells = self.get_cells_for_display();
+        let mut cache = self.display_cache();
+        cache.text.get_or_insert_with(|| cells_text(&cells, self.cols).into()).clone()
+    }
+    
+    // The display cache, emptied first if it was filled for other content
+    fn display_cache(&self) -> std::cell::RefMut<'_, DisplayCache> {
+        let key = Some((self.generation, self.view_top(), self.cols, self.rows));
+        let mut cache = self.display_cache.borrow_mut();
+        if cache.key != key {
+            *cache = DisplayCache { key, ..DisplayCache::default() };
+        }
+        cache
+    }
+    
+    /// Cursor column
+    pub fn x(&self) -> usize {
+        self.cursor.x
+    }
+    
+    /// Cursor row on the screen
+    pub fn y(&self) -> usize {
+        self.cursor.y
+    }
+    
+    /// Kitty keyboard protocol flags and stack of the current screen
+    pub fn keyboard(&self) -> &KeyboardModes {
+        &self.modes.keyboard
+    }
+    
+    /// Number of BELs since the last call, resetting the count
+    pub fn take_bells(&mut self) -> usize {
+        std::mem::take(&mut self.bells)
+    }
+    
+    /// Absolute row of the cursor (scrollback lines come first)
+    pub fn cursor_abs_row(&self) -> usize {
+        self.scrollback.len() + self.cursor.y
+    }
+    
+    /// Record a shell-integration mark on the cursor row
+    pub fn add_mark(&mut self, kind: MarkKind) {
+        let row = self.cursor_abs_row();
+        // A new prompt ends output whose command never reported 133 ; D
+        if kind == MarkKind::Prompt {
+            self.marks.end_output((self.cursor.x, row));
+        }
+        self.marks.add(row, kind);
+    }
+    
+    /// Command output starts at the cursor (OSC 133 ; C)
+    pub fn start_command_output(&mut self) {
+        self.marks.start_output((self.cursor.x, self.cursor_abs_row()));
+    }
+    
+    /// Command output ends at the cursor (OSC 133 ; D)
+    pub fn end_command_output(&mut self) {
+        self.marks.end_output((self.cursor.x, self.cursor_abs_row()));
+    }
+    
+    /// Text of the most recently finished command's output, without leading
+    /// blank lines or trailing whitespace; None until a command has finished
+    /// (or once its output left the scrollback)
+    pub fn last_command_output(&self) -> Option<String> {
+        let span = *self.marks.last_output()?;
+        // `end` is exclusive: step back one cell, onto the previous row at column 0
+        let end = match span.end {
+            (0, 0) => return Some(String::new()),
+            (0, row) => (usize::MAX, row - 1),
+            (col, row) => (col - 1, row),
+        };
+        if (end.1, end.0) < (span.start.1, span.start.0) {
+            return Some(String::new());
+        }
+        let text = self.get_text_absolute(span.start, end);
+        Some(text.trim_start_matches('\n').trim_end().to_string())
+    }
+    
+    /// First and last absolute rows of the finished command output (OSC 133
+    /// ; C up to ; D or the next prompt) that `abs_row` is in
+    pub fn output_block_for_row(&self, abs_row: usize) -> Option<(usize, usize)> {
+        let span = self.marks.output_at(abs_row)?;
+        Some((span.start.1, span.last_row()))
+    }
+    
+    /// Absolute row of the nearest mark above `abs_row`
+    pub fn prev_mark(&self, abs_row: usize) -> Option<usize> {
+        self.marks.prev(abs_row).map(|m| m.row)
+    }
+    
+    /// Absolute row of the nearest mark below `abs_row`
+    pub fn next_mark(&self, abs_row: usize) -> Option<usize> {
+        self.marks.next(abs_row).map(|m| m.row)
+    }
+    
     /// Scroll up in the scrollback
     pub fn scroll_up(&mut self, lines: usize) {
         self.scrollback.scroll_up(lines);
+        self.mark_all_dirty();
     }
     
     /// Scroll down in the scrollback
     pub fn scroll_down(&mut self, lines: usize) {
         self.scrollback.scroll_down(lines);
+        self.mark_all_dirty();
     }
     
     /// Page up
     pub fn page_up(&mut self) {
         self.scrollback.page_up(self.rows);
+        self.mark_all_dirty();
     }
     
     /// Page down
     pub fn page_down(&mut self) {
         self.scrollback.page_down(self.rows);
+        self.mark_all_dirty();
     }
     
-    /// Check if we're viewing scrollback
+    /// Check if we're viewing scrollback (never on the alt screen)
     pub fn is_scrolled(&self) -> bool {
-        self.scrollback.scroll_offset > 0
+        !self.is_alt_screen() && self.scrollback.scroll_offset > 0
     }
     
     /// Jump to bottom (exit scrollback view)
     pub fn scroll_to_bottom(&mut self) {
         self.scrollback.scroll_to_bottom();
+        self.mark_all_dirty();
+    }
+}
+
+/// Where cells went in a `Grid::reflow`
+pub struct Reflow {
+    // Per old absolute row: (logical line, offset of its first cell in the line)
+    old_rows: Vec<(usize, usize)>,
+    // Per logical line: its first new row (before eviction) and the offset each of its rows starts at
+    lines: Vec<(usize, Vec<usize>)>,
+    old_scrollback: usize,
+    new_scrollback: usize,
+    // Scrollback lines pushed out by the reflow
+    evicted: usize,
+    cols: usize,
+    rows: usize,
+}
+
+impl Reflow {
+    // (col, row before eviction) of the cell at `offset` in logical line `line`
+    fn place(&self, line: usize, offset: usize) -> (usize, usize) {
+        let (first, starts) = &self.lines[line];
+        let i = starts.partition_point(|&s| s <= offset).saturating_sub(1);
+        ((offset - starts[i]).min(self.cols - 1), first + i)
+    }
+
+    /// New absolute (col, row) of the cell at absolute `pos`, or None if it was dropped
+    pub fn abs_position(&self, pos: (usize, usize)) -> Option<(usize, usize)> {
+        let (col, row) = pos;
+        let &(line, base) = self.old_rows.get(row)?;
+        let (col, row) = self.place(line, base + col);
+        Some((col, row.checked_sub(self.evicted)?))
+    }
+
+    /// New screen (col, row) of the cell at screen `pos`, or None if it left the screen
+    pub fn position(&self, pos: (usize, usize)) -> Option<(usize, usize)> {
+        let (col, row) = self.abs_position((pos.0, self.old_scrollback + pos.1))?;
+        let row = row.checked_sub(self.new_scrollback)?;
+        (row < self.rows).then_some((col, row))
+    }
+}
+
+/// Where cells went in a `Grid::resize_preserve`
+pub struct Resize {
+    // Absolute rows the content moved up; negative when it moved down
+    shift: isize,
+    cols: usize,
+    // Scrollback plus screen rows after the resize
+    lines: usize,
+}
+
+impl Resize {
+    /// New absolute (col, row) of the cell at absolute `pos`, or None if it was cut off
+    pub fn abs_position(&self, pos: (usize, usize)) -> Option<(usize, usize)> {
+        let (col, row) = pos;
+        let row = usize::try_from(row as isize - self.shift).ok()?;
+        (col < self.cols && row < self.lines).then_some((col, row))
     }
-}
\ No newline at end of file
+}
+
+// One screen's rows and cursor while it is being resized
+struct ScreenRows {
+    cells: Vec<Cell>,
+    wrapped: Vec<bool>,
+    cursor: (usize, usize),
+}
+
+impl ScreenRows {
+    // Keep the bottom rows; returns how many rows the content moved up
+    fn resize_bottom_aligned(&mut self, old: (usize, usize), new: (usize, usize), blank: Cell) -> isize {
+        self.cells = copy_bottom_aligned(&self.cells, old.0, old.1, new.0, new.1, blank);
+        self.wrapped = copy_bottom_aligned(&self.wrapped, 1, old.1, 1, new.1, false);
+        self.cursor = resized_cursor(self.cursor, old, new);
+        old.1 as isize - new.1 as isize
+    }
+
+    // Trade rows with the scrollback so each keeps its absolute row; returns
+    // how many scrollback lines were evicted to make room
+    fn resize_against(&mut self, scrollback: &mut ScrollbackBuffer, old: (usize, usize), new: (usize, usize), blank: Cell) -> usize {
+        let (old_cols, old_rows) = old;
+        let (new_cols, new_rows) = new;
+        let (x, y) = self.cursor;
+        let row = |r: usize| &self.cells[r * old_cols..(r + 1) * old_cols];
+        let is_blank = |c: &Cell| c.is_empty() && c.bg == blank.bg;
+
+        let mut evicted = 0;
+        let pulled = if new_rows > old_rows { scrollback.pop_lines(new_rows - old_rows) } else { Vec::new() };
+        let mut rows: Vec<(&[Cell], bool)> = Vec::with_capacity(new_rows);
+        if new_rows < old_rows {
+            // Nothing is lost by dropping empty rows under the cursor
+            let excess = old_rows - new_rows;
+            let mut bottom = old_rows;
+            while old_rows - bottom < excess && bottom - 1 > y && !self.wrapped[bottom - 1] && row(bottom - 1).iter().all(is_blank) {
+                bottom -= 1;
+            }
+            let top = excess - (old_rows - bottom);
+            for r in 0..top {
+                evicted += scrollback.push_line(row(r).to_vec(), self.wrapped[r]);
+            }
+            rows.extend((top..bottom).map(|r| (row(r), self.wrapped[r])));
+            self.cursor = (x, y.saturating_sub(top));
+        } else {
+            rows.extend(pulled.iter().map(|(line, wrapped)| (line.as_slice(), *wrapped)));
+            rows.extend((0..old_rows).map(|r| (row(r), self.wrapped[r])));
+            self.cursor = (x, y + pulled.len());
+        }
+
+        let mut cells = Vec::with_capacity(new_cols * new_rows);
+        let mut wrapped = Vec::with_capacity(new_rows);
+        for (line, w) in rows {
+            // Scrollback lines may be of another width; columns stay left-aligned
+            let n = line.len().min(new_cols);
+            cells.extend_from_slice(&line[..n]);
+            cells.resize(cells.len() + new_cols - n, blank);
+            wrapped.push(w);
+        }
+        cells.resize(new_cols * new_rows, blank);
+        wrapped.resize(new_rows, false);
+        self.cells = cells;
+        self.wrapped = wrapped;
+        self.cursor = (self.cursor.0.min(new_cols.saturating_sub(1)), self.cursor.1.min(new_rows.saturating_sub(1)));
+        evicted
+    }
+}
+
+// Cursor position after a resize, clamped into the new grid
+fn resized_cursor(pos: (usize, usize), old: (usize, usize), new: (usize, usize)) -> (usize, usize) {
+    let (col, row) = pos;
+    let row = (row + new.1).saturating_sub(old.1);
+    (col.min(new.0.saturating_sub(1)), row.min(new.1.saturating_sub(1)))
+}
+
+// Copy the overlapping area into a new size, bottom-aligned like real terminals
+fn copy_bottom_aligned<T: Copy>(old: &[T], old_cols: usize, old_rows: usize, new_cols: usize, new_rows: usize, blank: T) -> Vec<T> {
+    let mut cells = vec![blank; new_cols * new_rows];
+    let keep_rows = old_rows.min(new_rows);
+    let keep_cols = old_cols.min(new_cols);
+
+    for r in 0..keep_rows {
+        let src_r = old_rows - keep_rows + r;
+        let dst_r = new_rows - keep_rows + r;
+
+        // Copy only the overlapping width (left aligned). Indexing is guarded
+        // so a buffer that doesn't match its dimensions loses cells, not the app.
+        for c in 0..keep_cols {
+            if let (Some(dst), Some(src)) = (cells.get_mut(dst_r * new_cols + c), old.get(src_r * old_cols + c)) {
+                *dst = *src;
+            }
+        }
+        // Remaining columns (if any) are already spaces
+    }
+    cells
+}
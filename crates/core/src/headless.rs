@@ -0,0 +1,86 @@
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+use tokio::sync::mpsc::{self, error::TryRecvError};
+use crate::clock::Clock;
+use crate::pty::Pty;
+use crate::terminal::{encode_paste, Terminal};
+
+/// How often `expect` looks for new output while it waits
+pub const EXPECT_POLL: Duration = Duration::from_millis(10);
+
+/// A terminal wired to a PTY and a clock with no window: output is parsed
+/// and query replies are written back as the app's event loop does, and
+/// `expect` waits for text on the screen. With a `FakePty` and a
+/// `ManualClock` a run never sleeps and always ends the same way.
+pub struct HeadlessTerminal {
+    pub term: Terminal,
+    pty: Box<dyn Pty>,
+    output: mpsc::UnboundedReceiver<Vec<u8>>,
+    clock: Box<dyn Clock>,
+    // The application closed its end of the PTY
+    closed: bool,
+}
+
+impl HeadlessTerminal {
+    pub fn new(term: Terminal, pty: Box<dyn Pty>, output: mpsc::UnboundedReceiver<Vec<u8>>, clock: Box<dyn Clock>) -> Self {
+        Self { term, pty, output, clock, closed: false }
+    }
+
+    /// Parse all output received so far, answering queries in it; false when
+    /// there was none
+    pub fn pump(&mut self) -> anyhow::Result<bool> {
+        let mut any = false;
+        loop {
+            match self.output.try_recv() {
+                Ok(chunk) => {
+                    any = true;
+                    self.term.advance(&chunk);
+                    let replies = self.term.take_replies();
+                    if !replies.is_empty() {
+                        self.pty.write(&replies)?;
+                    }
+                }
+                Err(TryRecvError::Empty) => return Ok(any),
+                Err(TryRecvError::Disconnected) => {
+                    self.closed = true;
+                    return Ok(any);
+                }
+            }
+        }
+    }
+
+    /// Input as if typed
+    pub fn send(&self, bytes: &[u8]) -> anyhow::Result<()> {
+        self.pty.write(bytes)
+    }
+
+    /// A user paste, bracketed when the application enabled it
+    pub fn paste(&self, text: &str) -> anyhow::Result<()> {
+        let bracketed = self.term.bracketed_paste().load(Ordering::Relaxed);
+        self.pty.write(&encode_paste(text, bracketed))
+    }
+
+    /// Resize the screen and tell the application, like a window resize
+    pub fn resize(&mut self, cols: usize, rows: usize) -> anyhow::Result<()> {
+        self.term.grid.resize_preserve(cols, rows);
+        self.pty.resize(rows as u16, cols as u16)
+    }
+
+    /// Wait until some screen row contains `text`, for at most `timeout` on
+    /// the clock; the error shows the screen it gave up on
+    pub fn expect(&mut self, text: &str, timeout: Duration) -> Result<(), String> {
+        let deadline = self.clock.now() + timeout;
+        loop {
+            self.pump().map_err(|e| e.to_string())?;
+            let screen = self.term.grid.to_string_lines();
+            if screen.lines().any(|line| line.contains(text)) {
+                return Ok(());
+            }
+            if self.closed || self.clock.now() >= deadline {
+                let why = if self.closed { "the application exited" } else { "timed out" };
+                return Err(format!("{why} waiting for {text:?}; screen:\n{screen}"));
+            }
+            self.clock.sleep(EXPECT_POLL);
+        }
+    }
+}
@@ -0,0 +1,50 @@
+/// Kitty keyboard protocol: disambiguate escape codes (`CSI code ; mods u`)
+pub const KITTY_DISAMBIGUATE: u8 = 0b1;
+/// Enhancement flags we implement; requests for others are masked off
+pub const KITTY_SUPPORTED: u8 = KITTY_DISAMBIGUATE;
+
+// Apps that push without popping shouldn't grow the stack forever
+const MAX_DEPTH: usize = 64;
+
+/// Progressive-enhancement stack set by `CSI > flags u` / `CSI < n u`.
+/// Empty means legacy key encoding.
+#[derive(Default, Debug, Clone)]
+pub struct KeyboardModes {
+    stack: Vec<u8>,
+}
+
+impl KeyboardModes {
+    /// Flags currently in effect
+    pub fn flags(&self) -> u8 {
+        self.stack.last().copied().unwrap_or(0)
+    }
+
+    pub fn push(&mut self, flags: u8) {
+        if self.stack.len() == MAX_DEPTH {
+            self.stack.remove(0);
+        }
+        self.stack.push(flags & KITTY_SUPPORTED);
+    }
+
+    pub fn pop(&mut self, n: usize) {
+        self.stack.truncate(self.stack.len().saturating_sub(n));
+    }
+
+    /// `CSI = flags ; mode u`: 1 replaces, 2 sets bits, 3 clears bits
+    pub fn set(&mut self, flags: u8, mode: u16) {
+        let cur = self.flags();
+        let new = match mode {
+            2 => cur | flags,
+            3 => cur & !flags,
+            _ => flags,
+        } & KITTY_SUPPORTED;
+        match self.stack.last_mut() {
+            Some(top) => *top = new,
+            None => self.stack.push(new),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.stack.clear();
+    }
+}
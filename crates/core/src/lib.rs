@@ -1,8 +1,22 @@
 pub mod grid;
+pub mod palette;
 pub mod pty;
 pub mod vt;
 pub mod scrollback;
+pub mod session;
 pub mod config;
 pub mod perf;
+pub mod frame_pacing;
+pub mod marks;
+pub mod terminal;
+pub mod paths;
+pub mod keyboard;
+pub mod cluster;
+pub mod bidi;
+pub mod links;
+pub mod search;
+pub mod clock;
+pub mod headless;
 
-pub use pty::PtyHandle;
\ No newline at end of file
+pub use pty::PtyHandle;
+pub use terminal::Terminal;
\ No newline at end of file
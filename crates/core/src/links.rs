@@ -0,0 +1,130 @@
+use std::collections::HashMap;
+use crate::grid::Grid;
+
+/// A link on screen: its target and the cells it covers, (col, absolute row)
+/// from `start` to `end` inclusive
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LinkRef {
+    pub uri: String,
+    pub start: (usize, usize),
+    pub end: (usize, usize),
+}
+
+/// Links found in logical lines, by the line's first absolute row; only
+/// valid for the grid generation it was filled at
+#[derive(Default)]
+pub(crate) struct LinkCache {
+    generation: Option<u64>,
+    lines: HashMap<usize, Vec<LinkRef>>,
+}
+
+// What starts a URL, and the scheme bare hosts get
+const PREFIXES: &[&str] = &["https://", "http://", "ftp://", "file://", "mailto:", "www."];
+
+// Characters that can't be part of a URL in running text
+fn ends_url(ch: char) -> bool {
+    ch.is_whitespace() || ch.is_control() || matches!(ch, '"' | '\'' | '`' | '<' | '>' | '{' | '}' | '|' | '\\' | '^')
+}
+
+/// URLs in `text` as (first char index, char count, uri). Trailing
+/// punctuation and closing brackets with no opening one in the URL are left
+/// out; bare `www.` hosts get `https://`.
+pub fn find_urls(text: &str) -> Vec<(usize, usize, String)> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut found = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        // A URL starts a word
+        if i > 0 && (chars[i - 1].is_alphanumeric() || chars[i - 1] == '.') {
+            i += 1;
+            continue;
+        }
+        let prefix = PREFIXES.iter().find(|p| {
+            let n = p.chars().count();
+            i + n <= chars.len() && chars[i..i + n].iter().zip(p.chars()).all(|(a, b)| a.eq_ignore_ascii_case(&b))
+        });
+        let Some(prefix) = prefix else {
+            i += 1;
+            continue;
+        };
+        let body_start = i + prefix.chars().count();
+        let mut end = body_start;
+        while end < chars.len() && !ends_url(chars[end]) {
+            end += 1;
+        }
+        while end > body_start {
+            let last = chars[end - 1];
+            let unbalanced = |open: char| {
+                let opened = chars[i..end].iter().filter(|&&c| c == open).count();
+                let closed = chars[i..end].iter().filter(|&&c| c == last).count();
+                closed > opened
+            };
+            let trim = match last {
+                '.' | ',' | ';' | ':' | '!' | '?' => true,
+                ')' => unbalanced('('),
+                ']' => unbalanced('['),
+                _ => false,
+            };
+            if !trim {
+                break;
+            }
+            end -= 1;
+        }
+        if end == body_start {
+            i = body_start;
+            continue;
+        }
+        let matched: String = chars[i..end].iter().collect();
+        let uri = if prefix.starts_with("www") { format!("https://{}", matched) } else { matched };
+        found.push((i, end - i, uri));
+        i = end;
+    }
+    found
+}
+
+impl Grid {
+    /// Link covering cell (col, abs_row): a URL detected in the logical line
+    /// the row belongs to, so URLs broken across wrapped rows are whole.
+    /// Scans are cached per line until the grid changes.
+    pub fn link_at(&self, col: usize, abs_row: usize) -> Option<LinkRef> {
+        let mut first = abs_row;
+        while first > 0 && self.abs_row(first - 1).is_some_and(|(_, wrapped)| wrapped) {
+            first -= 1;
+        }
+
+        let mut cache = self.link_cache.borrow_mut();
+        if cache.generation != Some(self.generation()) {
+            cache.generation = Some(self.generation());
+            cache.lines.clear();
+        }
+        let links = cache.lines.entry(first).or_insert_with(|| self.scan_line(first));
+        links
+            .iter()
+            .find(|link| (link.start.1, link.start.0) <= (abs_row, col) && (abs_row, col) <= (link.end.1, link.end.0))
+            .cloned()
+    }
+
+    // URLs in the logical line starting at absolute row `first`
+    fn scan_line(&self, first: usize) -> Vec<LinkRef> {
+        // Line text, and the cell each of its characters came from
+        let mut text = String::new();
+        let mut cells = Vec::new();
+        let mut row = first;
+        while let Some((line, wrapped)) = self.abs_row(row) {
+            for (col, cell) in line.iter().enumerate() {
+                let len = text.len();
+                cell.push_text(&mut text);
+                cells.extend(text[len..].chars().map(|_| (col, row)));
+            }
+            if !wrapped {
+                break;
+            }
+            row += 1;
+        }
+
+        find_urls(&text)
+            .into_iter()
+            .map(|(start, len, uri)| LinkRef { uri, start: cells[start], end: cells[start + len - 1] })
+            .collect()
+    }
+}
@@ -0,0 +1,153 @@
+use serde::{Deserialize, Serialize};
+
+/// Where a shell-integration mark came from
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MarkKind {
+    /// OSC 133 ; A prompt start (FinalTerm / shell integration)
+    Prompt,
+    /// iTerm2 OSC 1337 ; SetMark - position only, no command or exit-code spans
+    SetMark,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Mark {
+    /// Absolute row (scrollback lines first, then the live grid)
+    pub row: usize,
+    pub kind: MarkKind,
+}
+
+/// Output of one command, from OSC 133 ; C to 133 ; D or the next prompt.
+/// Positions are (col, absolute row); `end` is exclusive.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OutputSpan {
+    pub start: (usize, usize),
+    pub end: (usize, usize),
+}
+
+impl OutputSpan {
+    /// Row of the last cell in the span: an `end` at column 0 stops at the row above
+    pub fn last_row(&self) -> usize {
+        match self.end {
+            (0, row) if row > self.start.1 => row - 1,
+            (_, row) => row,
+        }
+    }
+}
+
+/// Ordered set of marks, at most one per row.
+/// Prompt and SetMark marks are merged (union) so navigation treats them the same.
+#[derive(Default, Serialize, Deserialize)]
+pub struct MarkList {
+    marks: Vec<Mark>,
+    // Finished command outputs, oldest first
+    outputs: Vec<OutputSpan>,
+    // Start of the output still being written
+    open_output: Option<(usize, usize)>,
+}
+
+impl MarkList {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a mark at an absolute row, keeping the list sorted
+    pub fn add(&mut self, row: usize, kind: MarkKind) {
+        match self.marks.binary_search_by_key(&row, |m| m.row) {
+            Ok(i) => {
+                // A prompt mark carries more information than a bare SetMark
+                if kind == MarkKind::Prompt {
+                    self.marks[i].kind = kind;
+                }
+            }
+            Err(i) => self.marks.insert(i, Mark { row, kind }),
+        }
+    }
+
+    /// Output starts at `pos` (OSC 133 ; C); an unfinished one is dropped
+    pub fn start_output(&mut self, pos: (usize, usize)) {
+        self.open_output = Some(pos);
+    }
+
+    /// Close the output being written, if any, at `pos`
+    pub fn end_output(&mut self, pos: (usize, usize)) {
+        if let Some(start) = self.open_output.take() {
+            self.outputs.push(OutputSpan { start, end: pos });
+        }
+    }
+
+    /// Most recently finished command output
+    pub fn last_output(&self) -> Option<&OutputSpan> {
+        self.outputs.last()
+    }
+
+    /// Finished output with a cell on absolute `row`; outputs that printed nothing cover no row
+    pub fn output_at(&self, row: usize) -> Option<&OutputSpan> {
+        self.outputs.iter().rev().find(|o| o.start != o.end && o.start.1 <= row && row <= o.last_row())
+    }
+
+    /// Nearest mark strictly above `row`
+    pub fn prev(&self, row: usize) -> Option<&Mark> {
+        self.marks.iter().rev().find(|m| m.row < row)
+    }
+
+    /// Nearest mark strictly below `row`
+    pub fn next(&self, row: usize) -> Option<&Mark> {
+        self.marks.iter().find(|m| m.row > row)
+    }
+
+    /// Shift marks after `n` lines were evicted from the top of the scrollback
+    pub fn discard_evicted(&mut self, n: usize) {
+        if n == 0 {
+            return;
+        }
+        self.marks.retain(|m| m.row >= n);
+        for m in &mut self.marks {
+            m.row -= n;
+        }
+        // Output whose first row is gone can no longer be copied whole
+        self.outputs.retain(|o| o.start.1 >= n);
+        for o in &mut self.outputs {
+            o.start.1 -= n;
+            o.end.1 -= n;
+        }
+        self.open_output = self.open_output.and_then(|(col, row)| Some((col, row.checked_sub(n)?)));
+    }
+
+    /// Move every mark and output to the (col, row) position `f` maps it to,
+    /// dropping those it maps to None. Marks that land on one row merge like
+    /// `add` does: a prompt wins over a SetMark.
+    pub fn remap(&mut self, f: impl Fn((usize, usize)) -> Option<(usize, usize)>) {
+        let mut marks: Vec<Mark> = self
+            .marks
+            .iter()
+            .filter_map(|m| Some(Mark { row: f((0, m.row))?.1, kind: m.kind }))
+            .collect();
+        marks.sort_by_key(|m| (m.row, m.kind != MarkKind::Prompt));
+        marks.dedup_by_key(|m| m.row);
+        self.marks = marks;
+        self.outputs = self
+            .outputs
+            .iter()
+            .filter_map(|o| Some(OutputSpan { start: f(o.start)?, end: f(o.end)? }))
+            .collect();
+        self.open_output = self.open_output.and_then(&f);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Mark> {
+        self.marks.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.marks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.marks.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.marks.clear();
+        self.outputs.clear();
+        self.open_output = None;
+    }
+}
@@ -0,0 +1,90 @@
+//! Theme colors that cell colors resolve against at draw time.
+
+use crate::config::ThemeConfig;
+use crate::grid::{CellAttrs, CellColor, Color};
+
+/// Default foreground/background plus the 256 indexed colors. Cells keep
+/// `CellColor`s, so swapping the palette recolors everything already on screen.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Palette {
+    pub foreground: Color,
+    pub background: Color,
+    pub ansi: [Color; 256],
+    /// Bold text in ANSI colors 0-7 takes the bright entry 8-15
+    pub bold_as_bright: bool,
+    /// Search highlights: every match, and the one search is on
+    pub search_match: Color,
+    pub search_current_match: Color,
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        let mut ansi = [Color::BLACK; 256];
+        for (n, c) in ansi.iter_mut().enumerate() {
+            *c = Color::from_ansi(n as u8);
+        }
+        Palette {
+            foreground: Color::default(),
+            background: Color::BLACK,
+            ansi,
+            bold_as_bright: true,
+            search_match: Color { r: 0x80, g: 0x6a, b: 0x00 },
+            search_current_match: Color { r: 0xe0, g: 0xa0, b: 0x00 },
+        }
+    }
+}
+
+impl Palette {
+    /// Theme colors for the 16 ANSI slots, defaults and search highlights; the
+    /// 256-color cube and grays stay standard. Bad hex values warn and keep the
+    /// built-in color
+    pub fn from_theme(theme: &ThemeConfig) -> Palette {
+        let mut palette = Palette::default();
+        let parse = |hex: &str, fallback: Color| {
+            Color::from_hex(hex).unwrap_or_else(|| {
+                tracing::warn!("Invalid theme color {:?}, using fallback", hex);
+                fallback
+            })
+        };
+        palette.foreground = parse(&theme.foreground, palette.foreground);
+        palette.background = parse(&theme.background, palette.background);
+        palette.search_match = parse(&theme.search_match, palette.search_match);
+        palette.search_current_match = parse(&theme.search_current_match, palette.search_current_match);
+        let named = [
+            &theme.black, &theme.red, &theme.green, &theme.yellow,
+            &theme.blue, &theme.magenta, &theme.cyan, &theme.white,
+            &theme.bright_black, &theme.bright_red, &theme.bright_green, &theme.bright_yellow,
+            &theme.bright_blue, &theme.bright_magenta, &theme.bright_cyan, &theme.bright_white,
+        ];
+        for (slot, hex) in palette.ansi.iter_mut().zip(named) {
+            *slot = parse(hex, *slot);
+        }
+        palette
+    }
+    
+    /// RGB for a foreground color
+    pub fn fg(&self, color: CellColor) -> Color {
+        self.resolve(color, self.foreground)
+    }
+    
+    /// RGB a cell's text is drawn in, brightening bold ANSI colors when enabled
+    pub fn text_fg(&self, attrs: &CellAttrs) -> Color {
+        match attrs.fg {
+            CellColor::Indexed(n @ 0..=7) if attrs.bold() && self.bold_as_bright => self.ansi[n as usize + 8],
+            fg => self.fg(fg),
+        }
+    }
+    
+    /// RGB for a background color
+    pub fn bg(&self, color: CellColor) -> Color {
+        self.resolve(color, self.background)
+    }
+    
+    fn resolve(&self, color: CellColor, default: Color) -> Color {
+        match color {
+            CellColor::Default => default,
+            CellColor::Indexed(n) => self.ansi[n as usize],
+            CellColor::Rgb(c) => c,
+        }
+    }
+}
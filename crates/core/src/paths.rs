@@ -0,0 +1,157 @@
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+const APP_DIR: &str = "the-dev-terminal";
+
+/// Where the owning process can't be checked, a launch that hasn't reached its
+/// first frame within this long is treated as crashed
+pub const STARTUP_GRACE: Duration = Duration::from_secs(30);
+
+/// Consecutive failed launches after which safe mode engages on its own
+pub const SAFE_MODE_AFTER_FAILURES: usize = 2;
+
+fn home() -> Result<PathBuf, std::env::VarError> {
+    std::env::var("HOME").map(PathBuf::from)
+}
+
+/// `~/.config/the-dev-terminal`
+pub fn config_dir() -> Result<PathBuf, std::env::VarError> {
+    Ok(home()?.join(".config").join(APP_DIR))
+}
+
+/// `$XDG_STATE_HOME/the-dev-terminal`, falling back to `~/.local/state/the-dev-terminal`
+pub fn state_dir() -> Result<PathBuf, std::env::VarError> {
+    match std::env::var("XDG_STATE_HOME") {
+        Ok(dir) if !dir.is_empty() => Ok(PathBuf::from(dir).join(APP_DIR)),
+        _ => Ok(home()?.join(".local").join("state").join(APP_DIR)),
+    }
+}
+
+/// `~/Downloads`, where scrollback dumps are saved
+pub fn downloads_dir() -> Result<PathBuf, std::env::VarError> {
+    Ok(home()?.join("Downloads"))
+}
+
+/// File name for a scrollback dump taken at `at`, e.g.
+/// `terminal-2024-03-09-142501.txt` (UTC)
+pub fn dump_file_name(at: SystemTime) -> String {
+    let secs = at.duration_since(SystemTime::UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    let (days, rem) = (secs / 86_400, secs % 86_400);
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm)
+    let z = days + 719_468;
+    let (era, doe) = (z / 146_097, z % 146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+    format!(
+        "terminal-{:04}-{:02}-{:02}-{:02}{:02}{:02}.txt",
+        year,
+        month,
+        day,
+        rem / 3600,
+        rem / 60 % 60,
+        rem % 60
+    )
+}
+
+/// Marks a launch as in progress until the first frame is presented.
+///
+/// Each instance writes its own `launch-<pid>.starting` file, so concurrent
+/// instances never clobber each other. Sentinels left behind by launches that
+/// died before `mark_started` are counted in `failed-launches`.
+pub struct LaunchSentinel {
+    dir: PathBuf,
+    own: PathBuf,
+}
+
+/// Outcome of checking the previous launches
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LaunchHistory {
+    /// Consecutive launches that crashed before showing a frame
+    pub failures: usize,
+}
+
+impl LaunchHistory {
+    pub fn should_engage_safe_mode(&self) -> bool {
+        self.failures >= SAFE_MODE_AFTER_FAILURES
+    }
+}
+
+impl LaunchSentinel {
+    /// Record that this process is starting and tally crashed launches
+    pub fn begin(dir: &Path) -> std::io::Result<(Self, LaunchHistory)> {
+        std::fs::create_dir_all(dir)?;
+        let now = SystemTime::now();
+
+        let mut crashed = 0;
+        for entry in std::fs::read_dir(dir)?.flatten() {
+            let path = entry.path();
+            let Some(pid) = sentinel_pid(&path) else {
+                continue;
+            };
+            // Another instance that is still starting up is not a crash
+            let modified = entry.metadata().and_then(|m| m.modified()).unwrap_or(now);
+            // Only the instance that removes a dead sentinel counts it
+            if pid != std::process::id()
+                && launch_crashed(pid, modified, now)
+                && std::fs::remove_file(&path).is_ok()
+            {
+                crashed += 1;
+            }
+        }
+
+        let failures = read_failures(dir) + crashed;
+        if crashed > 0 {
+            write_failures(dir, failures)?;
+        }
+
+        let own = dir.join(format!("launch-{}.starting", std::process::id()));
+        std::fs::write(&own, b"")?;
+
+        Ok((Self { dir: dir.to_path_buf(), own }, LaunchHistory { failures }))
+    }
+
+    /// The app came up; clear this launch's sentinel and the failure count
+    pub fn mark_started(self) -> std::io::Result<()> {
+        let _ = std::fs::remove_file(&self.own);
+        write_failures(&self.dir, 0)
+    }
+}
+
+// `launch-<pid>.starting` -> pid
+fn sentinel_pid(path: &Path) -> Option<u32> {
+    path.file_name()?
+        .to_str()?
+        .strip_prefix("launch-")?
+        .strip_suffix(".starting")?
+        .parse()
+        .ok()
+}
+
+#[cfg(unix)]
+fn launch_crashed(pid: u32, _modified: SystemTime, _now: SystemTime) -> bool {
+    // Signal 0 only checks that the process exists; EPERM means it does
+    let alive = unsafe { libc::kill(pid as libc::pid_t, 0) } == 0
+        || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM);
+    !alive
+}
+
+// A sentinel from the future (clock skew) is never stale
+#[cfg(not(unix))]
+fn launch_crashed(_pid: u32, modified: SystemTime, now: SystemTime) -> bool {
+    now.duration_since(modified).is_ok_and(|age| age > STARTUP_GRACE)
+}
+
+fn read_failures(dir: &Path) -> usize {
+    std::fs::read_to_string(dir.join("failed-launches"))
+        .ok()
+        .and_then(|s| s.trim().parse().ok())
+        .unwrap_or(0)
+}
+
+fn write_failures(dir: &Path, n: usize) -> std::io::Result<()> {
+    std::fs::write(dir.join("failed-launches"), n.to_string())
+}
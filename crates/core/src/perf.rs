@@ -7,6 +7,7 @@ pub struct PerfMonitor {
     frame_times: Arc<Mutex<VecDeque<Duration>>>,
     input_latencies: Arc<Mutex<VecDeque<Duration>>>,
     render_times: Arc<Mutex<VecDeque<Duration>>>,
+    heap: Arc<Mutex<HeapUsage>>,
     max_samples: usize,
     enabled: bool,
 }
@@ -19,6 +20,20 @@ pub struct PerfStats {
     pub avg_input_latency_ms: f32,
     pub avg_render_time_ms: f32,
     pub memory_usage_mb: f32,
+    pub heap: HeapUsage,
+}
+
+/// Bytes held by terminal content, from `Grid::heap_usage`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HeapUsage {
+    pub cells: usize,
+    pub scrollback: usize,
+}
+
+impl Default for PerfMonitor {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl PerfMonitor {
@@ -27,6 +42,7 @@ impl PerfMonitor {
             frame_times: Arc::new(Mutex::new(VecDeque::with_capacity(120))),
             input_latencies: Arc::new(Mutex::new(VecDeque::with_capacity(120))),
             render_times: Arc::new(Mutex::new(VecDeque::with_capacity(120))),
+            heap: Arc::new(Mutex::new(HeapUsage::default())),
             max_samples: 120,
             enabled: cfg!(debug_assertions), // Enable in debug builds by default
         }
@@ -70,6 +86,12 @@ impl PerfMonitor {
         times.push_back(duration);
     }
     
+    pub fn record_heap(&self, usage: HeapUsage) {
+        if !self.enabled { return; }
+        
+        *self.heap.lock().unwrap() = usage;
+    }
+    
     pub fn get_stats(&self) -> PerfStats {
         let frame_times = self.frame_times.lock().unwrap();
         let input_latencies = self.input_latencies.lock().unwrap();
@@ -124,6 +146,7 @@ impl PerfMonitor {
             avg_input_latency_ms,
             avg_render_time_ms,
             memory_usage_mb,
+            heap: *self.heap.lock().unwrap(),
         }
     }
     
@@ -202,6 +225,70 @@ impl PerfTimer {
     pub fn elapsed_ms(&self) -> f32 {
         self.elapsed().as_secs_f32() * 1000.0
     }
+    
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+/// Startup breakdown: how long each phase took, and when milestones
+/// (window shown, first frame, first prompt) were reached since launch.
+/// Phases may overlap, so their durations don't add up to the total.
+pub struct StartupProfile {
+    start: Instant,
+    phases: Vec<(String, Duration)>,
+    milestones: Vec<(String, Duration)>,
+}
+
+impl Default for StartupProfile {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StartupProfile {
+    pub fn new() -> Self {
+        Self {
+            start: Instant::now(),
+            phases: Vec::new(),
+            milestones: Vec::new(),
+        }
+    }
+    
+    /// Record a finished phase under the timer's name
+    pub fn record(&mut self, timer: &PerfTimer) {
+        self.record_duration(timer.name(), timer.elapsed());
+    }
+    
+    /// Record a phase timed elsewhere (e.g. on a worker thread)
+    pub fn record_duration(&mut self, name: &str, duration: Duration) {
+        self.phases.push((name.to_string(), duration));
+    }
+    
+    /// Note that `name` was reached now; only the first time counts
+    pub fn mark(&mut self, name: &str) {
+        if !self.milestones.iter().any(|(n, _)| n == name) {
+            self.milestones.push((name.to_string(), self.start.elapsed()));
+        }
+    }
+    
+    pub fn has_mark(&self, name: &str) -> bool {
+        self.milestones.iter().any(|(n, _)| n == name)
+    }
+    
+    /// Human-readable breakdown, one phase or milestone per line
+    pub fn report(&self) -> String {
+        let ms = |d: &Duration| d.as_secs_f32() * 1000.0;
+        let mut out = String::from("Startup phases:\n");
+        for (name, d) in &self.phases {
+            out.push_str(&format!("  {:<24} {:>8.1} ms\n", name, ms(d)));
+        }
+        out.push_str("Milestones (since launch):\n");
+        for (name, d) in &self.milestones {
+            out.push_str(&format!("  {:<24} {:>8.1} ms\n", name, ms(d)));
+        }
+        out
+    }
 }
 
 impl Drop for PerfTimer {
@@ -5,14 +5,34 @@ use std::io::Write;
 use tokio::sync::mpsc;
 use tracing::{info, error};
 
+/// The application side of a terminal: where typed input goes and what a
+/// resize is reported to. `PtyHandle` is the real one; tests inject a `FakePty`.
+pub trait Pty: Send {
+    fn write(&self, data: &[u8]) -> Result<()>;
+    fn resize(&self, rows: u16, cols: u16) -> Result<()>;
+}
+
 pub struct PtyHandle {
     master: Box<dyn MasterPty + Send>,
     _child: Box<dyn Child + Send + Sync>,
+    shell_pid: Option<u32>,
     writer: Arc<Mutex<Box<dyn Write + Send>>>,
 }
 
 impl PtyHandle {
-    pub fn spawn(rows: u16, cols: u16) -> Result<(Self, mpsc::UnboundedReceiver<Vec<u8>>)> {
+    /// Start the shell; `env` is added to its environment after TERM/COLORTERM
+    pub fn spawn(rows: u16, cols: u16, env: &[(&str, String)]) -> Result<(Self, mpsc::UnboundedReceiver<Vec<u8>>)> {
+        Self::spawn_program("/bin/zsh", &[], rows, cols, env)
+    }
+    
+    /// Start `program` with `args` on a new PTY, like `spawn` does the shell
+    pub fn spawn_program(
+        program: &str,
+        args: &[&str],
+        rows: u16,
+        cols: u16,
+        env: &[(&str, String)],
+    ) -> Result<(Self, mpsc::UnboundedReceiver<Vec<u8>>)> {
         let pty_system = native_pty_system();
         
         let pty_size = PtySize {
@@ -23,12 +43,17 @@ impl PtyHandle {
         };
         
         let pair = pty_system.openpty(pty_size)?;
-        let mut cmd = CommandBuilder::new("/bin/zsh");
+        let mut cmd = CommandBuilder::new(program);
+        cmd.args(args);
         cmd.env("TERM", "xterm-256color");
         cmd.env("COLORTERM", "truecolor");
+        for (key, value) in env {
+            cmd.env(key, value);
+        }
         
         let child = pair.slave.spawn_command(cmd)?;
-        info!("Spawned zsh with PID: {:?}", child.process_id());
+        let shell_pid = child.process_id();
+        info!("Spawned {} with PID: {:?}", program, shell_pid);
         
         let writer = Arc::new(Mutex::new(pair.master.take_writer()?));
         let mut reader = pair.master.try_clone_reader()?;
@@ -61,6 +86,7 @@ impl PtyHandle {
             Self {
                 master: pair.master,
                 _child: child,
+                shell_pid,
                 writer,
             },
             rx,
@@ -74,6 +100,22 @@ impl PtyHandle {
         Ok(())
     }
     
+    /// Whether a job other than the shell owns the terminal, e.g. a build or
+    /// an editor. The shell leads its own process group, so any other
+    /// foreground group is something it started.
+    #[cfg(unix)]
+    pub fn has_foreground_job(&self) -> bool {
+        match (self.master.process_group_leader(), self.shell_pid) {
+            (Some(leader), Some(shell)) => leader as i64 != shell as i64,
+            _ => false,
+        }
+    }
+    
+    #[cfg(not(unix))]
+    pub fn has_foreground_job(&self) -> bool {
+        false
+    }
+    
     pub fn resize(&self, rows: u16, cols: u16) -> Result<()> {
         self.master.resize(PtySize {
             rows,
@@ -83,4 +125,97 @@ impl PtyHandle {
         })?;
         Ok(())
     }
-}
\ No newline at end of file
+}
+impl Pty for PtyHandle {
+    fn write(&self, data: &[u8]) -> Result<()> {
+        PtyHandle::write(self, data)
+    }
+    
+    fn resize(&self, rows: u16, cols: u16) -> Result<()> {
+        PtyHandle::resize(self, rows, cols)
+    }
+}
+
+/// In-memory PTY whose other end is a `FakeApp` the test plays: what the
+/// terminal writes is collected, and what the app prints arrives on the
+/// same kind of channel `PtyHandle::spawn` returns
+pub struct FakePty {
+    app: FakeApp,
+}
+
+/// The application end of a `FakePty`
+#[derive(Clone)]
+pub struct FakeApp {
+    received: Arc<Mutex<Vec<u8>>>,
+    size: Arc<Mutex<Option<(u16, u16)>>>,
+    output: mpsc::UnboundedSender<Vec<u8>>,
+}
+
+impl FakePty {
+    pub fn new() -> (Self, FakeApp, mpsc::UnboundedReceiver<Vec<u8>>) {
+        let (output, rx) = mpsc::unbounded_channel();
+        let app = FakeApp {
+            received: Arc::default(),
+            size: Arc::default(),
+            output,
+        };
+        (Self { app: app.clone() }, app, rx)
+    }
+}
+
+impl Pty for FakePty {
+    fn write(&self, data: &[u8]) -> Result<()> {
+        self.app.received.lock().unwrap().extend_from_slice(data);
+        Ok(())
+    }
+    
+    fn resize(&self, rows: u16, cols: u16) -> Result<()> {
+        *self.app.size.lock().unwrap() = Some((cols, rows));
+        Ok(())
+    }
+}
+
+impl FakeApp {
+    /// Output, as if the application wrote it to the PTY
+    pub fn print(&self, bytes: &[u8]) {
+        let _ = self.output.send(bytes.to_vec());
+    }
+    
+    /// Everything the terminal wrote since the last call
+    pub fn take_received(&self) -> Vec<u8> {
+        std::mem::take(&mut *self.received.lock().unwrap())
+    }
+    
+    /// Last size the PTY was resized to, as (cols, rows)
+    pub fn size(&self) -> Option<(u16, u16)> {
+        *self.size.lock().unwrap()
+    }
+}
+
+/// PTY output waiting to be parsed. The reader thread appends chunks as
+/// they arrive and the UI takes everything at once, so a burst (`yes`, a
+/// big `cat`) is parsed in one go instead of one 4 KiB chunk per event.
+#[derive(Clone, Default)]
+pub struct PtyInbox {
+    pending: Arc<Mutex<Vec<u8>>>,
+}
+
+impl PtyInbox {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Queue a chunk; true when the inbox was empty, i.e. the consumer
+    /// has to be woken up for this batch
+    pub fn push(&self, chunk: &[u8]) -> bool {
+        let mut pending = self.pending.lock().unwrap();
+        let was_empty = pending.is_empty();
+        pending.extend_from_slice(chunk);
+        was_empty && !chunk.is_empty()
+    }
+    
+    /// Everything queued since the last take, in arrival order
+    pub fn take(&self) -> Vec<u8> {
+        std::mem::take(&mut *self.pending.lock().unwrap())
+    }
+}
@@ -1,60 +1,173 @@
 use std::collections::VecDeque;
-use crate::grid::Cell;
+use std::ops::Range;
+use crate::grid::{self, Cell, CellColor};
+use serde::{Deserialize, Serialize};
+
+/// Lines of history kept when the config doesn't set `scrollback_lines`
+pub const DEFAULT_SCROLLBACK_LINES: usize = 10_000;
+
+/// Lines at the bottom of the scrollback kept exactly as pushed; older ones
+/// are compressed
+pub const HOT_LINES: usize = 500;
+
+// Memory a stored line accounts for: its cells plus the Vec header
+fn line_bytes(line: &[Cell]) -> usize {
+    std::mem::size_of_val(line) + std::mem::size_of::<Vec<Cell>>()
+}
 
 /// Efficient scrollback buffer with configurable history size
+#[derive(Serialize, Deserialize)]
 pub struct ScrollbackBuffer {
     /// Stored lines in the scrollback (older lines)
+    #[serde(with = "crate::session::line_runs")]
     lines: VecDeque<Vec<Cell>>,
+    /// Per line: soft-wrapped, i.e. its text continues on the next line
+    wrapped: VecDeque<bool>,
+    /// Per line: the width it was pushed with, which compression may trim
+    widths: VecDeque<u32>,
     /// Maximum number of lines to store
     max_lines: usize,
+    /// Approximate memory cap for the stored cells; 0 = lines cap only
+    max_bytes: usize,
+    /// Approximate memory held by the stored cells
+    bytes: usize,
     /// Current scroll offset (0 = viewing latest, >0 = scrolled up)
+    #[serde(skip)]
     pub scroll_offset: usize,
+    /// Lines dropped from the top so far
+    #[serde(skip)]
+    evicted: usize,
 }
 
 impl ScrollbackBuffer {
+    /// Buffer keeping up to `max_lines` lines: 0 keeps none, and a huge
+    /// value such as `usize::MAX` is limited only by the byte cap
     pub fn new(max_lines: usize) -> Self {
+        // A large history fills in over time; don't reserve it all up front
+        let capacity = max_lines.min(4096);
         Self {
-            lines: VecDeque::with_capacity(max_lines),
+            lines: VecDeque::with_capacity(capacity),
+            wrapped: VecDeque::with_capacity(capacity),
+            widths: VecDeque::with_capacity(capacity),
             max_lines,
+            max_bytes: 0,
+            bytes: 0,
             scroll_offset: 0,
+            evicted: 0,
         }
     }
     
-    /// Push a line to the scrollback buffer
-    pub fn push_line(&mut self, line: Vec<Cell>) {
-        // If at capacity, remove oldest line
-        if self.lines.len() >= self.max_lines {
-            self.lines.pop_front();
+    /// Keep up to `max_lines` lines from now on; the oldest go if there
+    /// are more already
+    pub fn set_max_lines(&mut self, max_lines: usize) {
+        self.max_lines = max_lines;
+        if self.evict_over_limits(0, 0) > 0 {
+            self.lines.shrink_to_fit();
+            self.wrapped.shrink_to_fit();
+            self.widths.shrink_to_fit();
+        }
+    }
+    
+    /// Also cap the history at roughly `max_bytes` of cells, so very wide
+    /// lines can't grow it without bound; 0 keeps only the line cap
+    pub fn set_max_bytes(&mut self, max_bytes: usize) {
+        self.max_bytes = max_bytes;
+        self.evict_over_limits(0, 0);
+    }
+    
+    /// Approximate memory held by the stored cells
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+    
+    /// What `bytes` would be with every line stored at the width it was
+    /// pushed with, for measuring what compression saves
+    pub fn uncompressed_bytes(&self) -> usize {
+        let per_cell = std::mem::size_of::<Cell>();
+        self.widths.iter().map(|&w| w as usize * per_cell + std::mem::size_of::<Vec<Cell>>()).sum()
+    }
+    
+    /// Lines dropped from the top so far, by either cap or by `clear`. Every
+    /// absolute row moves up by however much this grew between two readings.
+    pub fn evicted(&self) -> usize {
+        self.evicted
+    }
+    
+    /// Push a line to the scrollback buffer; `wrapped` if its text continues
+    /// on the next line. Returns how many of the oldest lines were evicted to make room.
+    pub fn push_line(&mut self, line: Vec<Cell>, wrapped: bool) -> usize {
+        // Without scrollback the line is gone as soon as it leaves the screen
+        if self.max_lines == 0 {
+            self.evicted += 1;
+            return 1;
         }
+        // Oldest lines go until the new one fits under both caps
+        let evicted = self.evict_over_limits(1, line_bytes(&line));
+        self.bytes += line_bytes(&line);
+        self.widths.push_back(line.len() as u32);
         self.lines.push_back(line);
+        self.wrapped.push_back(wrapped);
+        // The line leaving the hot window is compressed
+        if let Some(i) = self.lines.len().checked_sub(HOT_LINES + 1) {
+            self.compress(i);
+        }
         
         // Auto-scroll to bottom when new content arrives (unless user is scrolling)
         if self.scroll_offset > 0 {
             self.scroll_offset += 1;
         }
+        
+        evicted
     }
     
-    /// Get lines for display (from scroll position)
-    pub fn get_visible_lines(&self, viewport_height: usize) -> Vec<Vec<Cell>> {
-        let total_lines = self.lines.len();
-        
-        if total_lines == 0 {
-            return vec![];
+    // Store line `i` without its trailing default cells, which read as blank
+    // like any column past a line's end; `widths` keeps what to pad back to
+    fn compress(&mut self, i: usize) {
+        let line = &mut self.lines[i];
+        let end = line.iter().rposition(|c| *c != Cell::default()).map_or(0, |n| n + 1);
+        if end == line.len() {
+            return;
         }
-        
-        // Calculate the starting line based on scroll offset
-        let start = if self.scroll_offset >= total_lines {
-            0
-        } else {
-            total_lines - self.scroll_offset - viewport_height.min(total_lines - self.scroll_offset)
-        };
-        
-        let end = (start + viewport_height).min(total_lines);
-        
-        self.lines
-            .range(start..end)
-            .map(|line| line.clone())
-            .collect()
+        self.bytes -= line_bytes(line);
+        line.truncate(end);
+        line.shrink_to_fit();
+        self.bytes += line_bytes(line);
+    }
+    
+    // `line` padded back to the width it was pushed with
+    fn decompress(mut line: Vec<Cell>, width: u32) -> Vec<Cell> {
+        line.resize((width as usize).max(line.len()), Cell::default());
+        line
+    }
+    
+    // Drop the oldest lines until `lines` more lines of `bytes` fit under
+    // both caps; returns how many were dropped
+    fn evict_over_limits(&mut self, lines: usize, bytes: usize) -> usize {
+        let mut evicted = 0;
+        while let Some(oldest) = self.lines.front() {
+            let over_lines = self.lines.len() + lines > self.max_lines;
+            let over_bytes = self.max_bytes > 0 && self.bytes + bytes > self.max_bytes;
+            if !over_lines && !over_bytes {
+                break;
+            }
+            self.bytes -= line_bytes(oldest);
+            self.lines.pop_front();
+            self.wrapped.pop_front();
+            self.widths.pop_front();
+            evicted += 1;
+        }
+        self.evicted += evicted;
+        self.scroll_offset = self.scroll_offset.min(self.lines.len());
+        evicted
+    }
+    
+    /// Scrollback lines in a `viewport_height` window whose top is
+    /// `scroll_offset` lines above the newest, as `Grid::view_top` places it;
+    /// the screen fills the rest of the window, so at the bottom this is empty.
+    /// Borrowed, so scrolling through history copies no lines.
+    pub fn get_visible_lines(&self, viewport_height: usize) -> impl Iterator<Item = &[Cell]> + '_ {
+        let top = self.lines.len() - self.scroll_offset.min(self.lines.len());
+        self.range(top..top + viewport_height)
     }
     
     /// Scroll up by n lines
@@ -95,8 +208,93 @@ impl ScrollbackBuffer {
     
     /// Clear scrollback buffer
     pub fn clear(&mut self) {
+        self.evicted += self.lines.len();
+        self.bytes = 0;
         self.lines.clear();
+        self.wrapped.clear();
+        self.widths.clear();
+        self.scroll_offset = 0;
+    }
+    
+    /// Line `i`, 0 = oldest. Scrollback comes first in a grid's absolute
+    /// rows, so this is also absolute row `i`. Lines older than the last
+    /// `HOT_LINES` come without their trailing default cells; those read as
+    /// blank like any column past the end, so display and search need no
+    /// decompression, and lines taken back out are padded again.
+    pub fn line(&self, i: usize) -> Option<&[Cell]> {
+        self.lines.get(i).map(Vec::as_slice)
+    }
+    
+    /// Lines `rows`, oldest first; the part past the newest line is left out
+    pub fn range(&self, rows: Range<usize>) -> impl Iterator<Item = &[Cell]> + '_ {
+        let end = rows.end.min(self.lines.len());
+        let start = rows.start.min(end);
+        self.lines.range(start..end).map(Vec::as_slice)
+    }
+    
+    /// Every line, oldest first
+    pub fn iter_lines(&self) -> impl Iterator<Item = &[Cell]> + '_ {
+        self.lines.iter().map(Vec::as_slice)
+    }
+    
+    /// Whether line `i` (0 = oldest) continues on the next line
+    pub fn is_wrapped(&self, i: usize) -> bool {
+        self.wrapped.get(i).copied().unwrap_or(false)
+    }
+    
+    /// Every line broken again at `target_cols`, oldest first, with wrapped
+    /// flags: soft-wrapped lines are joined, trailing padding is dropped and
+    /// rows are padded with blanks to exactly `target_cols`. The stored lines
+    /// keep whatever width they were pushed with; this only reads them.
+    pub fn lines_rewrapped(&self, target_cols: usize) -> Vec<(Vec<Cell>, bool)> {
+        let target_cols = target_cols.max(1);
+        let is_blank = |c: &Cell| c.is_empty() && c.bg == CellColor::Default;
+        let mut out = Vec::with_capacity(self.lines.len());
+        let mut logical: Vec<Cell> = Vec::new();
+        for (i, line) in self.lines.iter().enumerate() {
+            logical.extend_from_slice(line);
+            // A wrapped last line has its continuation on the screen, not here
+            if self.is_wrapped(i) && i + 1 < self.lines.len() {
+                continue;
+            }
+            while logical.last().is_some_and(is_blank) {
+                logical.pop();
+            }
+            let ranges = grid::wrap_ranges(&logical, target_cols);
+            let last = ranges.len() - 1;
+            for (n, range) in ranges.into_iter().enumerate() {
+                let mut row = logical[range].to_vec();
+                row.resize(target_cols, Cell::default());
+                out.push((row, n < last || self.is_wrapped(i)));
+            }
+            logical.clear();
+        }
+        out
+    }
+    
+    /// Take up to `n` of the newest lines out, oldest first, with their wrapped flags
+    pub fn pop_lines(&mut self, n: usize) -> Vec<(Vec<Cell>, bool)> {
+        let start = self.lines.len().saturating_sub(n);
+        self.scroll_offset = self.scroll_offset.min(start);
+        self.bytes -= self.lines.range(start..).map(|line| line_bytes(line)).sum::<usize>();
+        self.lines
+            .drain(start..)
+            .zip(self.widths.drain(start..))
+            .map(|(line, width)| Self::decompress(line, width))
+            .zip(self.wrapped.drain(start..))
+            .collect()
+    }
+    
+    /// Take every line out, oldest first, with its wrapped flag
+    pub fn drain_lines(&mut self) -> Vec<(Vec<Cell>, bool)> {
         self.scroll_offset = 0;
+        self.bytes = 0;
+        self.lines
+            .drain(..)
+            .zip(self.widths.drain(..))
+            .map(|(line, width)| Self::decompress(line, width))
+            .zip(self.wrapped.drain(..))
+            .collect()
     }
     
     /// Get total number of lines in scrollback
@@ -104,36 +302,8 @@ impl ScrollbackBuffer {
         self.lines.len()
     }
     
-    /// Search for text in scrollback
-    pub fn search(&self, query: &str, case_sensitive: bool) -> Vec<(usize, usize, usize)> {
-        let mut matches = Vec::new();
-        let query_lower = if !case_sensitive { 
-            query.to_lowercase() 
-        } else { 
-            query.to_string() 
-        };
-        
-        for (line_idx, line) in self.lines.iter().enumerate() {
-            let line_text: String = line.iter()
-                .map(|cell| if cell.ch == '\0' { ' ' } else { cell.ch })
-                .collect();
-            
-            let search_text = if !case_sensitive {
-                line_text.to_lowercase()
-            } else {
-                line_text.clone()
-            };
-            
-            // Find all matches in this line
-            let mut start = 0;
-            while let Some(pos) = search_text[start..].find(&query_lower) {
-                let match_start = start + pos;
-                let match_end = match_start + query.len();
-                matches.push((line_idx, match_start, match_end));
-                start = match_start + 1;
-            }
-        }
-        
-        matches
+    /// Check if the scrollback holds no lines
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,246 @@
+//! Finding text in the screen and scrollback, by substring or regex, all at
+//! once or a chunk at a time.
+
+use std::collections::VecDeque;
+use std::ops::Range;
+use regex::{Regex, RegexBuilder};
+use crate::grid::{Cell, Grid};
+use crate::scrollback::ScrollbackBuffer;
+
+/// What to look for
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SearchQuery {
+    /// The text itself
+    Plain { text: String, case_sensitive: bool },
+    /// A pattern in `regex` crate syntax; `(?i)` makes it case-insensitive
+    Regex(String),
+}
+
+impl SearchQuery {
+    /// Compile once to run over many lines; an invalid pattern is an error
+    /// the UI can show as is
+    pub fn compile(&self) -> Result<Regex, regex::Error> {
+        match self {
+            SearchQuery::Plain { text, case_sensitive } => {
+                RegexBuilder::new(&regex::escape(text)).case_insensitive(!case_sensitive).build()
+            }
+            SearchQuery::Regex(pattern) => Regex::new(pattern),
+        }
+    }
+}
+
+/// A match covering cells `start` to `end` inclusive, as (col, row)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SearchMatch {
+    pub start: (usize, usize),
+    pub end: (usize, usize),
+}
+
+/// Matches of `regex` in `rows`, given as (cells, wrapped) from row 0 on.
+/// Wrapped rows are joined into their logical line first, so a match may
+/// span rows. Empty matches are skipped.
+pub fn search_rows<'a>(regex: &Regex, rows: impl IntoIterator<Item = (&'a [Cell], bool)>) -> Vec<SearchMatch> {
+    let mut found = Vec::new();
+    // Line text, and the cell each of its bytes came from
+    let mut text = String::new();
+    let mut cells: Vec<(usize, usize)> = Vec::new();
+    let mut rows = rows.into_iter().enumerate().peekable();
+    while let Some((row, (line, wrapped))) = rows.next() {
+        push_row(line, row, &mut text, Some(&mut cells));
+        // A wrapped last row has nothing to continue with
+        if wrapped && rows.peek().is_some() {
+            continue;
+        }
+        for m in regex.find_iter(&text).filter(|m| !m.is_empty()) {
+            found.push(SearchMatch { start: cells[m.start()], end: cells[m.end() - 1] });
+        }
+        text.clear();
+        cells.clear();
+    }
+    found
+}
+
+impl Grid {
+    /// Matches of `query` in scrollback and on the screen, in absolute rows
+    pub fn search(&self, query: &SearchQuery) -> Result<Vec<SearchMatch>, regex::Error> {
+        let regex = query.compile()?;
+        let rows = (0..self.scrollable_lines()).map_while(|abs| self.abs_row(abs));
+        Ok(search_rows(&regex, rows))
+    }
+}
+
+impl ScrollbackBuffer {
+    /// Matches of `query` in the scrollback only; rows are line indexes, 0 = oldest
+    pub fn search(&self, query: &SearchQuery) -> Result<Vec<SearchMatch>, regex::Error> {
+        let regex = query.compile()?;
+        let rows = self.iter_lines().enumerate().map(|(i, line)| (line, self.is_wrapped(i)));
+        Ok(search_rows(&regex, rows))
+    }
+}
+
+// Append a row's text, and with `cells` the (col, `row`) each byte came from
+fn push_row(line: &[Cell], row: usize, text: &mut String, mut cells: Option<&mut Vec<(usize, usize)>>) {
+    for (col, cell) in line.iter().enumerate() {
+        cell.push_text(text);
+        if let Some(cells) = cells.as_deref_mut() {
+            cells.resize(text.len(), (col, row));
+        }
+    }
+}
+
+/// Rows an incremental search looks at per `SearchSession::poll`
+pub const SEARCH_CHUNK_ROWS: usize = 2000;
+
+/// How far a `SearchSession` has got
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SearchProgress {
+    /// `searched` of `total` rows looked at so far, newest first
+    Searching { searched: usize, total: usize },
+    /// Every row has been searched
+    Done,
+}
+
+/// A search over a large buffer run a chunk at a time, newest rows first, so
+/// the UI can stay responsive and show the nearest matches early. Give it a
+/// new query to cancel the current search; the line texts it has built for
+/// scrollback are kept, since history doesn't change once written. Output
+/// arriving after the search started is not searched.
+pub struct SearchSession {
+    regex: Regex,
+    // Rows from 0 up to here are still to search; a line boundary
+    end: usize,
+    total: usize,
+    // Newest first, in absolute rows
+    matches: Vec<SearchMatch>,
+    // `ScrollbackBuffer::evicted` when rows were last valid
+    evicted: usize,
+    size: (usize, usize),
+    // Text of logical lines wholly in scrollback, by first row
+    lines: VecDeque<Option<Box<str>>>,
+}
+
+impl SearchSession {
+    /// Search `grid` for `query`; nothing is searched until `poll`
+    pub fn new(query: &SearchQuery, grid: &Grid) -> Result<Self, regex::Error> {
+        let mut session = SearchSession {
+            regex: query.compile()?,
+            end: 0,
+            total: 0,
+            matches: Vec::new(),
+            evicted: grid.scrollback.evicted(),
+            size: (grid.cols, grid.rows),
+            lines: VecDeque::new(),
+        };
+        session.restart(grid);
+        Ok(session)
+    }
+
+    /// Drop the current search and start over with `query`. On an invalid
+    /// query the current search carries on.
+    pub fn set_query(&mut self, query: &SearchQuery, grid: &Grid) -> Result<(), regex::Error> {
+        self.regex = query.compile()?;
+        self.restart(grid);
+        Ok(())
+    }
+
+    /// Matches found so far, newest first, in absolute rows
+    pub fn matches(&self) -> &[SearchMatch] {
+        &self.matches
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.end == 0
+    }
+
+    fn restart(&mut self, grid: &Grid) {
+        self.follow(grid);
+        self.total = grid.scrollable_lines();
+        self.end = self.total;
+        self.matches.clear();
+    }
+
+    // Keep rows in step with evictions; a resize moves text between rows, so
+    // cached lines go and the search starts over
+    fn follow(&mut self, grid: &Grid) {
+        let evicted = grid.scrollback.evicted();
+        let shift = evicted.saturating_sub(self.evicted);
+        self.evicted = evicted;
+        if (grid.cols, grid.rows) != self.size {
+            self.size = (grid.cols, grid.rows);
+            self.lines.clear();
+            self.total = grid.scrollable_lines();
+            self.end = self.total;
+            self.matches.clear();
+            return;
+        }
+        if shift == 0 {
+            return;
+        }
+        self.lines.drain(..shift.min(self.lines.len()));
+        self.end = self.end.saturating_sub(shift);
+        self.total = self.total.saturating_sub(shift);
+        self.matches.retain(|m| m.start.1 >= shift);
+        for m in &mut self.matches {
+            m.start.1 -= shift;
+            m.end.1 -= shift;
+        }
+    }
+
+    /// Search the next `SEARCH_CHUNK_ROWS` or so rows, going up
+    pub fn poll(&mut self, grid: &Grid) -> SearchProgress {
+        self.follow(grid);
+        let history = grid.history_len();
+        let mut text = String::new();
+        let mut cells = Vec::new();
+        let mut budget = SEARCH_CHUNK_ROWS;
+        while self.end > 0 && budget > 0 {
+            let mut first = self.end - 1;
+            while first > 0 && grid.abs_row(first - 1).is_some_and(|(_, wrapped)| wrapped) {
+                first -= 1;
+            }
+            let rows = first..self.end;
+            budget = budget.saturating_sub(rows.len());
+            self.end = first;
+
+            // Scrollback lines are built once; screen lines can still change
+            text.clear();
+            let line: &str = if rows.end <= history {
+                if self.lines.len() <= first {
+                    self.lines.resize(first + 1, None);
+                }
+                self.lines[first].get_or_insert_with(|| {
+                    line_text(grid, rows.clone(), &mut text, None);
+                    text.as_str().into()
+                })
+            } else {
+                line_text(grid, rows.clone(), &mut text, None);
+                &text
+            };
+
+            let found = self.regex.find_iter(line).filter(|m| !m.is_empty()).map(|m| m.range()).collect::<Vec<_>>();
+            if found.is_empty() {
+                continue;
+            }
+            let mut line = String::new();
+            cells.clear();
+            line_text(grid, rows, &mut line, Some(&mut cells));
+            for m in found.into_iter().rev() {
+                self.matches.push(SearchMatch { start: cells[m.start], end: cells[m.end - 1] });
+            }
+        }
+        if self.end == 0 {
+            SearchProgress::Done
+        } else {
+            SearchProgress::Searching { searched: self.total - self.end, total: self.total }
+        }
+    }
+}
+
+// Text of the logical line on absolute `rows`, optionally with the cell of each byte
+fn line_text(grid: &Grid, rows: Range<usize>, text: &mut String, mut cells: Option<&mut Vec<(usize, usize)>>) {
+    for row in rows {
+        if let Some((line, _)) = grid.abs_row(row) {
+            push_row(line, row, text, cells.as_deref_mut());
+        }
+    }
+}
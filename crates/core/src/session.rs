@@ -0,0 +1,92 @@
+//! Terminal state dumps: the grid and its scrollback saved to a file and
+//! loaded back, for session restore and benchmark fixtures.
+//!
+//! Cell rows are stored as runs of identical cells, so the blank tail of
+//! most lines costs one run instead of a cell each.
+
+use std::collections::VecDeque;
+use std::path::Path;
+
+use anyhow::{bail, Result};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::grid::{Cell, Grid};
+
+// Bumped whenever the dump layout changes; older dumps are refused
+const FORMAT_VERSION: u32 = 2;
+
+// A row as (count, cell) runs
+struct Runs<'a>(&'a [Cell]);
+
+impl Serialize for Runs<'_> {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        let mut runs: Vec<(u32, Cell)> = Vec::new();
+        for &cell in self.0 {
+            match runs.last_mut() {
+                Some((n, last)) if *last == cell => *n += 1,
+                _ => runs.push((1, cell)),
+            }
+        }
+        runs.serialize(s)
+    }
+}
+
+struct OwnedRuns(Vec<Cell>);
+
+impl<'de> Deserialize<'de> for OwnedRuns {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        let runs = Vec::<(u32, Cell)>::deserialize(d)?;
+        let mut cells = Vec::new();
+        for (n, cell) in runs {
+            cells.resize(cells.len() + n as usize, cell);
+        }
+        Ok(OwnedRuns(cells))
+    }
+}
+
+/// `#[serde(with)]` for a run-length encoded `Vec<Cell>`
+pub(crate) mod cell_runs {
+    use super::*;
+    
+    pub fn serialize<S: Serializer>(cells: &[Cell], s: S) -> Result<S::Ok, S::Error> {
+        Runs(cells).serialize(s)
+    }
+    
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Vec<Cell>, D::Error> {
+        Ok(OwnedRuns::deserialize(d)?.0)
+    }
+}
+
+/// `#[serde(with)]` for scrollback lines, each run-length encoded
+pub(crate) mod line_runs {
+    use super::*;
+    
+    pub fn serialize<S: Serializer>(lines: &VecDeque<Vec<Cell>>, s: S) -> Result<S::Ok, S::Error> {
+        s.collect_seq(lines.iter().map(|line| Runs(line)))
+    }
+    
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<VecDeque<Vec<Cell>>, D::Error> {
+        let lines = Vec::<OwnedRuns>::deserialize(d)?;
+        Ok(lines.into_iter().map(|runs| runs.0).collect())
+    }
+}
+
+impl Grid {
+    /// Write the screen, scrollback, cursor and marks to `path`. Theme,
+    /// config-driven settings and application modes aren't part of the dump
+    pub fn save_to(&self, path: impl AsRef<Path>) -> Result<()> {
+        let bytes = bincode::serialize(&(FORMAT_VERSION, self))?;
+        std::fs::write(path, bytes)?;
+        Ok(())
+    }
+    
+    /// Read a grid written by `save_to`; every row starts dirty
+    pub fn load_from(path: impl AsRef<Path>) -> Result<Grid> {
+        let bytes = std::fs::read(path)?;
+        let (version, grid): (u32, Grid) = bincode::deserialize(&bytes)?;
+        if version != FORMAT_VERSION {
+            bail!("Unsupported session dump version {}", version);
+        }
+        Ok(grid)
+    }
+}
@@ -0,0 +1,113 @@
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use crate::grid::Grid;
+use crate::scrollback::DEFAULT_SCROLLBACK_LINES;
+use crate::vt::{C1Controls, Osc4Capture, Performer, VtEvent};
+
+/// Pasted text without control characters other than tabs and line breaks,
+/// and without any bracketed paste end marker, which would let the rest of
+/// the payload run as typed commands. `\r` is kept for `normalize_paste`.
+pub fn sanitize_paste(text: &str) -> String {
+    text.replace("\x1b[201~", "")
+        .chars()
+        .filter(|&c| !c.is_ascii_control() || matches!(c, '\t' | '\n' | '\r'))
+        .collect()
+}
+
+/// Pasted text with `\r\n` and lone `\r` line breaks turned into `\n`.
+/// Without bracketed paste every line break runs what came before it, so
+/// trailing ones are dropped and the last line waits for Enter.
+pub fn normalize_paste(text: &str, bracketed: bool) -> String {
+    let mut s = text.replace("\r\n", "\n").replace('\r', "\n");
+    if !bracketed {
+        let end = s.trim_end_matches('\n').len();
+        s.truncate(end);
+    }
+    s
+}
+
+/// Bytes a paste of `text` sends to the application, sanitized and wrapped
+/// in `CSI 200 ~` / `CSI 201 ~` when it enabled bracketed paste
+pub fn encode_paste(text: &str, bracketed: bool) -> Vec<u8> {
+    let text = normalize_paste(&sanitize_paste(text), bracketed);
+    if bracketed {
+        [b"\x1b[200~", text.as_bytes(), b"\x1b[201~"].concat()
+    } else {
+        text.into_bytes()
+    }
+}
+
+/// A grid together with the VT parser that feeds it.
+/// Every terminal owns its parser, so a half-received escape sequence
+/// can never leak into another terminal's grid.
+pub struct Terminal {
+    pub grid: Grid,
+    parser: vte::Parser,
+    // Updated by the parser when it sees CSI ? 2004 h/l
+    bracketed_paste: Arc<AtomicBool>,
+    // Query responses waiting to be written back to the PTY
+    replies: Vec<u8>,
+    // Keeps batched palette queries whole past vte's parameter limit
+    osc_4: Osc4Capture,
+    c1: C1Controls,
+}
+
+impl Terminal {
+    /// Terminal with the default scrollback length
+    pub fn new(cols: usize, rows: usize) -> Self {
+        Self::with_grid(Grid::new(cols, rows, DEFAULT_SCROLLBACK_LINES))
+    }
+
+    pub fn with_grid(grid: Grid) -> Self {
+        Self {
+            grid,
+            parser: vte::Parser::new(),
+            bracketed_paste: Arc::new(AtomicBool::new(false)),
+            replies: Vec::new(),
+            osc_4: Osc4Capture::default(),
+            c1: C1Controls::default(),
+        }
+    }
+
+    /// Honor 8-bit C1 controls (`general.c1_controls`); see `C1Controls`
+    pub fn set_c1_controls(&mut self, enabled: bool) {
+        self.c1.enabled = enabled;
+    }
+
+    /// Shared bracketed paste flag, readable without locking the terminal
+    pub fn bracketed_paste(&self) -> Arc<AtomicBool> {
+        self.bracketed_paste.clone()
+    }
+
+    /// Feed PTY output through the parser into the grid
+    pub fn advance(&mut self, bytes: &[u8]) -> Vec<VtEvent> {
+        let mut p = Performer {
+            g: &mut self.grid,
+            bracketed_paste: Some(&self.bracketed_paste),
+            events: Vec::new(),
+            replies: std::mem::take(&mut self.replies),
+            osc_4_body: None,
+        };
+        for &b in bytes {
+            let esc_form;
+            let seq = match self.c1.advance(b) {
+                Some(fin) => {
+                    esc_form = [0x1b, fin];
+                    &esc_form[..]
+                }
+                None => std::slice::from_ref(&b),
+            };
+            for &b in seq {
+                p.osc_4_body = self.osc_4.advance(b);
+                self.parser.advance(&mut p, b);
+            }
+        }
+        self.replies = p.replies;
+        p.events
+    }
+    
+    /// Responses (e.g. DECRQM reports) the embedder must write to the PTY
+    pub fn take_replies(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.replies)
+    }
+}
@@ -1,11 +1,140 @@
 use vte::{Params, Perform};
-use crate::grid::{Grid, Color};
-use std::sync::Arc;
+use crate::grid::{AttrFlags, CellAttrs, CellColor, Color, Grid};
+use crate::marks::MarkKind;
 use std::sync::atomic::{AtomicBool, Ordering};
 
+/// Side effects produced while parsing that the embedder has to act on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VtEvent {
+    /// BEL (0x07) was received
+    Bell,
+}
+
+/// DECRPM mode status values (the `v` in `CSI ? n ; v $ y`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModeStatus {
+    NotRecognized = 0,
+    Set = 1,
+    Reset = 2,
+    PermanentlySet = 3,
+    PermanentlyReset = 4,
+}
+
+impl ModeStatus {
+    fn from_flag(on: bool) -> Self {
+        if on { ModeStatus::Set } else { ModeStatus::Reset }
+    }
+}
+
 pub struct Performer<'a> { 
     pub g: &'a mut Grid,
-    pub bracketed_paste: Option<Arc<AtomicBool>>,
+    pub bracketed_paste: Option<&'a AtomicBool>,
+    pub events: Vec<VtEvent>,
+    /// Bytes to write back to the PTY (query responses)
+    pub replies: Vec<u8>,
+    /// Untruncated body of the OSC 4 being dispatched, from `Osc4Capture`
+    pub osc_4_body: Option<Vec<u8>>,
+}
+
+// Longest OSC 4 body kept whole; longer ones fall back to vte's parameters
+const MAX_OSC_4_BODY: usize = 4096;
+
+#[derive(Default, Clone, Copy)]
+enum CaptureState {
+    #[default]
+    Idle,
+    Esc,
+    OscStart,
+    OscFour,
+    Body,
+}
+
+/// vte splits an OSC string into at most 16 parameters, which cuts a
+/// batched palette query (`OSC 4 ; i ; ? ; j ; ? ...`) off after seven
+/// entries. This watches the raw bytes for OSC 4 and keeps the whole body.
+#[derive(Default)]
+pub struct Osc4Capture {
+    state: CaptureState,
+    body: Vec<u8>,
+}
+
+impl Osc4Capture {
+    /// Feed one byte ahead of the parser. Returns the body after `4;` on the
+    /// byte that makes vte dispatch the OSC (BEL, or the ESC of ST).
+    pub fn advance(&mut self, b: u8) -> Option<Vec<u8>> {
+        use CaptureState::*;
+        self.state = match (self.state, b) {
+            (Body, 0x07) => {
+                self.state = Idle;
+                return Some(std::mem::take(&mut self.body));
+            }
+            (Body, 0x1b) => {
+                self.state = Esc;
+                return Some(std::mem::take(&mut self.body));
+            }
+            // CAN/SUB abort the string
+            (Body, 0x18 | 0x1a) => {
+                self.body.clear();
+                Idle
+            }
+            (Body, _) if self.body.len() < MAX_OSC_4_BODY => {
+                self.body.push(b);
+                Body
+            }
+            (Body, _) => {
+                self.body.clear();
+                Idle
+            }
+            (_, 0x1b) => Esc,
+            (Esc, b']') => OscStart,
+            (OscStart, b'4') => OscFour,
+            (OscFour, b';') => Body,
+            _ => Idle,
+        };
+        None
+    }
+}
+
+/// 8-bit C1 controls (0x80-0x9F, e.g. 0x9B for CSI, 0x9D for OSC, 0x9C
+/// for ST). The stream is UTF-8, so vte drops these bytes as invalid; when
+/// enabled this turns each into its 7-bit `ESC` form instead. A byte in
+/// that range that continues a multi-byte character is never a control.
+#[derive(Default)]
+pub struct C1Controls {
+    pub enabled: bool,
+    // Continuation bytes still due for the current UTF-8 character
+    pending: u8,
+}
+
+impl C1Controls {
+    /// Feed one byte ahead of the parser. Returns the byte that follows
+    /// `ESC` in the 7-bit form when `b` is a C1 control to honor.
+    pub fn advance(&mut self, b: u8) -> Option<u8> {
+        if !self.enabled {
+            return None;
+        }
+        let (pending, control) = match b {
+            0x80..=0xBF if self.pending > 0 => (self.pending - 1, None),
+            0x80..=0x9F => (0, Some(b - 0x40)),
+            0xC2..=0xDF => (1, None),
+            0xE0..=0xEF => (2, None),
+            0xF0..=0xF4 => (3, None),
+            _ => (0, None),
+        };
+        self.pending = pending;
+        control
+    }
+}
+
+// Largest single OSC reply; a full 256-entry palette query fits
+const MAX_OSC_REPLY: usize = 8192;
+
+// Crate version as a single DA2 number: major * 10000 + minor * 100 + patch
+fn firmware_version() -> u32 {
+    let part = |v: &str| v.parse::<u32>().unwrap_or(0);
+    part(env!("CARGO_PKG_VERSION_MAJOR")) * 10000
+        + part(env!("CARGO_PKG_VERSION_MINOR")) * 100
+        + part(env!("CARGO_PKG_VERSION_PATCH"))
 }
 
 impl<'a> Perform for Performer<'a> {
@@ -19,19 +148,19 @@ impl<'a> Perform for Performer<'a> {
         match byte {
             b'\n' => self.g.lf(),
             b'\r' => self.g.cr(),
+            0x07 => {
+                self.g.bells += 1;
+                self.events.push(VtEvent::Bell);
+            }
             b'\t' => {
-                // Tab: move to next tab stop (every 8 columns)
-                let tab_stop = ((self.g.x / 8) + 1) * 8;
-                let tab_stop = tab_stop.min(self.g.cols - 1);
-                while self.g.x < tab_stop {
-                    self.g.put(' ');
-                }
+                // Tab: move to the next tab stop, leaving the cells it passes alone
+                self.g.cursor.x = self.g.next_tab_stop();
+                self.g.cursor.pending_wrap = false;
             }
-            0x08 => { 
-                // Backspace
-                if self.g.x > 0 { 
-                    self.g.x -= 1; 
-                } 
+            // Backspace: from a pending wrap it steps back off the last column
+            0x08 => {
+                self.g.cursor.pending_wrap = false;
+                self.g.cursor.x = self.g.cursor.x.saturating_sub(1);
             }
             _ => {}
         }
@@ -39,16 +168,86 @@ impl<'a> Perform for Performer<'a> {
 
     // CSI sequences (ESC [ ... )
     fn csi_dispatch(&mut self, params: &Params, inter: &[u8], _ignore: bool, c: char) {
+        // Kitty keyboard protocol
+        //   CSI > flags u  -> push enhancement flags
+        //   CSI < n u      -> pop n entries
+        //   CSI = flags ; mode u -> modify the current entry
+        //   CSI ? u        -> report current flags (CSI ? flags u)
+        if c == 'u' && !inter.is_empty() {
+            let mut it = params.iter();
+            let first = it.next().and_then(|p| p.first()).copied();
+            match inter {
+                b">" => self.g.modes.keyboard.push(first.unwrap_or(0) as u8),
+                b"<" => self.g.modes.keyboard.pop(first.unwrap_or(1).max(1) as usize),
+                b"=" => {
+                    let mode = it.next().and_then(|p| p.first()).copied().unwrap_or(1);
+                    self.g.modes.keyboard.set(first.unwrap_or(0) as u8, mode);
+                }
+                b"?" => {
+                    let reply = format!("\x1b[?{}u", self.g.modes.keyboard.flags());
+                    self.replies.extend_from_slice(reply.as_bytes());
+                }
+                _ => {}
+            }
+            return;
+        }
+        
+        // Cursor movement cancels a pending wrap instead of performing it
+        if inter.is_empty() && matches!(c, 'H' | 'f' | 'A' | 'B' | 'e' | 'C' | 'a' | 'D' | 'E' | 'F' | 'G' | '`' | 'd') {
+            self.g.cursor.pending_wrap = false;
+        }
+        
+        // DECRQM – request mode (CSI ? n $ p for DEC modes, CSI n $ p for ANSI modes)
+        if c == 'p' && inter.last() == Some(&b'$') {
+            let private = inter.first() == Some(&b'?');
+            let mode = params.iter().next().and_then(|p| p.first()).copied().unwrap_or(0);
+            let status = if private { self.dec_mode_status(mode) } else { self.ansi_mode_status(mode) };
+            let marker = if private { "?" } else { "" };
+            self.replies.extend_from_slice(format!("\x1b[{}{};{}$y", marker, mode, status as u8).as_bytes());
+            return;
+        }
+        
+        // DA – device attributes
+        //   CSI c  (DA1) -> VT220 (62) with ANSI color (22); nothing else is claimed
+        //   CSI > c (DA2) -> terminal type 1 (VT220), our version, ROM cartridge 0
+        if c == 'c' {
+            let n = params.iter().next().and_then(|p| p.first()).copied().unwrap_or(0);
+            if n == 0 {
+                match inter {
+                    b"" => self.replies.extend_from_slice(b"\x1b[?62;22c"),
+                    b">" => {
+                        let reply = format!("\x1b[>1;{};0c", firmware_version());
+                        self.replies.extend_from_slice(reply.as_bytes());
+                    }
+                    _ => {}
+                }
+            }
+            return;
+        }
+        
         // Handle DEC private mode set/reset (CSI ? ... h/l)
         if inter == b"?" {
             let is_set = c == 'h';
             for param in params.iter() {
                 for n in param {
-                    if *n == 2004 {
-                        // Bracketed paste mode
-                        if let Some(ref bp) = self.bracketed_paste {
-                            bp.store(is_set, Ordering::Relaxed);
+                    match *n {
+                        1 => self.g.modes.app_cursor_keys = is_set,
+                        2004 => {
+                            // Bracketed paste mode
+                            if let Some(bp) = self.bracketed_paste {
+                                bp.store(is_set, Ordering::Relaxed);
+                            }
+                        }
+                        // Alternate screen; 1049 also saves/restores the cursor,
+                        // which entering/exiting the alt screen always does here
+                        47 | 1047 | 1049 => {
+                            if is_set {
+                                self.g.enter_alt_screen();
+                            } else {
+                                self.g.exit_alt_screen();
+                            }
                         }
+                        _ => {}
                     }
                     // TODO: handle ?25h/?25l for cursor visible later
                 }
@@ -57,6 +256,14 @@ impl<'a> Perform for Performer<'a> {
         }
         
         match c {
+            // SM / RM – ANSI modes; only IRM (4, insert mode) is supported
+            'h' | 'l' => {
+                for param in params.iter() {
+                    if param.first() == Some(&4) {
+                        self.g.modes.insert = c == 'h';
+                    }
+                }
+            }
             // ED (Erase in Display) 0/1/2
             //   CSI 0 J  -> clear from cursor to end of screen
             //   CSI 1 J  -> clear from start of screen to cursor
@@ -68,22 +275,23 @@ impl<'a> Perform for Performer<'a> {
                         // clear current line from cursor to end
                         self.g.clear_eol_from_cursor();
                         // clear all lines below
-                        for row in (self.g.y + 1)..self.g.rows {
+                        for row in (self.g.cursor.y + 1)..self.g.rows {
                             self.g.clear_line(row);
                         }
                     }
                     1 => { // clear from start to cursor (inclusive)
                         // clear all lines above
-                        for row in 0..self.g.y {
+                        for row in 0..self.g.cursor.y {
                             self.g.clear_line(row);
                         }
                         // clear beginning of current line up to cursor
                         self.g.clear_bol_to_cursor();
                     }
                     2 => { // clear entire screen and home cursor (typical terminal behavior)
-                        self.g.clear_all();
-                        self.g.x = 0;
-                        self.g.y = 0;
+                        if self.g.clear_pushes_to_scrollback {
+                            self.g.push_screen_to_scrollback();
+                        }
+                        self.g.clear_screen();
                     }
                     _ => {}
                 }
@@ -97,7 +305,7 @@ impl<'a> Perform for Performer<'a> {
                 match n {
                     0 => self.g.clear_eol_from_cursor(),
                     1 => self.g.clear_bol_to_cursor(),
-                    2 => self.g.clear_line(self.g.y),
+                    2 => self.g.clear_line(self.g.cursor.y),
                     _ => {}
                 }
             }
@@ -106,122 +314,140 @@ impl<'a> Perform for Performer<'a> {
                 let mut it = params.iter();
                 let row = it.next().and_then(|p| p.first()).copied().unwrap_or(1) as usize;
                 let col = it.next().and_then(|p| p.first()).copied().unwrap_or(1) as usize;
-                self.g.y = row.saturating_sub(1).min(self.g.rows.saturating_sub(1));
-                self.g.x = col.saturating_sub(1).min(self.g.cols.saturating_sub(1));
+                self.g.cursor.y = row.saturating_sub(1).min(self.g.rows.saturating_sub(1));
+                self.g.cursor.x = col.saturating_sub(1).min(self.g.cols.saturating_sub(1));
             }
             // Cursor movement
             'A' => {
                 // Cursor up
-                let n = params.iter().next().and_then(|p| p.first()).copied().unwrap_or(1) as usize;
-                self.g.y = self.g.y.saturating_sub(n);
+                let n = params.iter().next().and_then(|p| p.first()).copied().unwrap_or(1).max(1) as usize;
+                self.g.cursor.y = self.g.cursor.y.saturating_sub(n);
             }
-            'B' => {
-                // Cursor down
-                let n = params.iter().next().and_then(|p| p.first()).copied().unwrap_or(1) as usize;
-                self.g.y = (self.g.y + n).min(self.g.rows - 1);
+            'B' | 'e' => {
+                // Cursor down (CUD) / vertical position relative (VPR)
+                let n = params.iter().next().and_then(|p| p.first()).copied().unwrap_or(1).max(1) as usize;
+                self.g.cursor.y = (self.g.cursor.y + n).min(self.g.rows.saturating_sub(1));
             }
-            'C' => {
-                // Cursor forward
-                let n = params.iter().next().and_then(|p| p.first()).copied().unwrap_or(1) as usize;
-                self.g.x = (self.g.x + n).min(self.g.cols - 1);
+            'C' | 'a' => {
+                // Cursor forward (CUF) / horizontal position relative (HPR)
+                let n = params.iter().next().and_then(|p| p.first()).copied().unwrap_or(1).max(1) as usize;
+                self.g.cursor.x = (self.g.cursor.x + n).min(self.g.cols.saturating_sub(1));
             }
             'D' => {
                 // Cursor backward
+                let n = params.iter().next().and_then(|p| p.first()).copied().unwrap_or(1).max(1) as usize;
+                self.g.cursor.x = self.g.cursor.x.saturating_sub(n);
+            }
+            'E' => {
+                // Cursor next line (CNL): down n rows, to column 0
+                let n = params.iter().next().and_then(|p| p.first()).copied().unwrap_or(1).max(1) as usize;
+                self.g.cursor.y = (self.g.cursor.y + n).min(self.g.rows.saturating_sub(1));
+                self.g.cursor.x = 0;
+            }
+            'F' => {
+                // Cursor previous line (CPL): up n rows, to column 0
+                let n = params.iter().next().and_then(|p| p.first()).copied().unwrap_or(1).max(1) as usize;
+                self.g.cursor.y = self.g.cursor.y.saturating_sub(n);
+                self.g.cursor.x = 0;
+            }
+            'G' | '`' => {
+                // Cursor horizontal absolute (CHA) / horizontal position absolute (HPA), 1-based
                 let n = params.iter().next().and_then(|p| p.first()).copied().unwrap_or(1) as usize;
-                self.g.x = self.g.x.saturating_sub(n);
+                self.g.cursor.x = n.saturating_sub(1).min(self.g.cols.saturating_sub(1));
+            }
+            'd' => {
+                // Vertical position absolute (VPA), 1-based
+                let n = params.iter().next().and_then(|p| p.first()).copied().unwrap_or(1) as usize;
+                self.g.cursor.y = n.saturating_sub(1).min(self.g.rows.saturating_sub(1));
+            }
+            // TBC – tab clear
+            //   CSI 0 g -> clear the stop at the cursor column
+            //   CSI 3 g -> clear all stops
+            'g' => {
+                let n = params.iter().next().and_then(|p| p.first()).copied().unwrap_or(0);
+                match n {
+                    0 => self.g.clear_tab_stop(),
+                    3 => self.g.clear_all_tab_stops(),
+                    _ => {}
+                }
+            }
+            // DSR – device status report
+            //   CSI 5 n -> "OK" (CSI 0 n)
+            //   CSI 6 n -> cursor position report, 1-based (CSI row ; col R).
+            //   Origin mode isn't supported, so scroll margins don't shift it.
+            'n' => {
+                let n = params.iter().next().and_then(|p| p.first()).copied().unwrap_or(0);
+                match n {
+                    5 => self.replies.extend_from_slice(b"\x1b[0n"),
+                    6 => {
+                        let reply = format!("\x1b[{};{}R", self.g.cursor.y + 1, self.g.cursor.x + 1);
+                        self.replies.extend_from_slice(reply.as_bytes());
+                    }
+                    _ => {}
+                }
+            }
+            // DECSTBM – set the scroll region, 1-based and inclusive; no
+            // parameters (or 0) mean the whole screen
+            'r' if inter.is_empty() => {
+                let mut it = params.iter();
+                let top = it.next().and_then(|p| p.first()).copied().filter(|&n| n > 0).unwrap_or(1) as usize;
+                let bottom = it.next().and_then(|p| p.first()).copied().filter(|&n| n > 0).map_or(self.g.rows, |n| n as usize);
+                self.g.set_scroll_margins(top - 1, bottom - 1);
+            }
+            // XTWINOPS – only the text area size report is answered
+            //   CSI 18 t -> CSI 8 ; rows ; cols t
+            't' => {
+                let n = params.iter().next().and_then(|p| p.first()).copied().unwrap_or(0);
+                if n == 18 {
+                    let reply = format!("\x1b[8;{};{}t", self.g.rows, self.g.cols);
+                    self.replies.extend_from_slice(reply.as_bytes());
+                }
             }
             // SGR – Select Graphic Rendition (colors and text attributes)
             'm' => {
                 let mut params_iter = params.iter();
                 while let Some(param) = params_iter.next() {
-                    for n in param {
-                        match *n {
-                            0 => {
-                                // Reset all attributes
-                                self.g.current_fg = Color::default();
-                                self.g.current_bg = Color::BLACK;
-                                self.g.current_bold = false;
-                                self.g.current_italic = false;
-                                self.g.current_underline = false;
+                    // Colon subparameters (e.g. 38:2::r:g:b) arrive in the same group
+                    let (&n, sub) = match param.split_first() {
+                        Some(split) => split,
+                        None => continue,
+                    };
+                    match n {
+                        // Reset all attributes
+                        0 => self.g.cursor.attrs = CellAttrs::default(),
+                        1 => self.g.cursor.attrs.flags.set(AttrFlags::BOLD, true),
+                        3 => self.g.cursor.attrs.flags.set(AttrFlags::ITALIC, true),
+                        4 => self.g.cursor.attrs.flags.set(AttrFlags::UNDERLINE, true),
+                        22 => self.g.cursor.attrs.flags.set(AttrFlags::BOLD, false),
+                        23 => self.g.cursor.attrs.flags.set(AttrFlags::ITALIC, false),
+                        24 => self.g.cursor.attrs.flags.set(AttrFlags::UNDERLINE, false),
+                        
+                        // Foreground colors
+                        30..=37 => self.g.cursor.attrs.fg = CellColor::Indexed((n - 30) as u8),
+                        38 => {
+                            // Extended foreground color
+                            if let Some(color) = extended_color(sub, &mut params_iter) {
+                                self.g.cursor.attrs.fg = color;
                             }
-                            1 => self.g.current_bold = true,
-                            3 => self.g.current_italic = true,
-                            4 => self.g.current_underline = true,
-                            22 => self.g.current_bold = false,
-                            23 => self.g.current_italic = false,
-                            24 => self.g.current_underline = false,
-                            
-                            // Foreground colors
-                            30..=37 => self.g.current_fg = Color::from_ansi((*n - 30) as u8),
-                            38 => {
-                                // Extended foreground color
-                                if let Some(next_param) = params_iter.next() {
-                                    if let Some(&2) = next_param.first() {
-                                        // RGB color (38;2;r;g;b)
-                                        let r = params_iter.next()
-                                            .and_then(|p| p.first())
-                                            .copied()
-                                            .unwrap_or(0) as u8;
-                                        let g = params_iter.next()
-                                            .and_then(|p| p.first())
-                                            .copied()
-                                            .unwrap_or(0) as u8;
-                                        let b = params_iter.next()
-                                            .and_then(|p| p.first())
-                                            .copied()
-                                            .unwrap_or(0) as u8;
-                                        self.g.current_fg = Color { r, g, b };
-                                    } else if let Some(&5) = next_param.first() {
-                                        // 256 color (38;5;n)
-                                        if let Some(color_param) = params_iter.next() {
-                                            if let Some(&color) = color_param.first() {
-                                                self.g.current_fg = Color::from_ansi(color as u8);
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                            39 => self.g.current_fg = Color::default(), // Default foreground
-                            
-                            // Background colors
-                            40..=47 => self.g.current_bg = Color::from_ansi((*n - 40) as u8),
-                            48 => {
-                                // Extended background color
-                                if let Some(next_param) = params_iter.next() {
-                                    if let Some(&2) = next_param.first() {
-                                        // RGB color (48;2;r;g;b)
-                                        let r = params_iter.next()
-                                            .and_then(|p| p.first())
-                                            .copied()
-                                            .unwrap_or(0) as u8;
-                                        let g = params_iter.next()
-                                            .and_then(|p| p.first())
-                                            .copied()
-                                            .unwrap_or(0) as u8;
-                                        let b = params_iter.next()
-                                            .and_then(|p| p.first())
-                                            .copied()
-                                            .unwrap_or(0) as u8;
-                                        self.g.current_bg = Color { r, g, b };
-                                    } else if let Some(&5) = next_param.first() {
-                                        // 256 color (48;5;n)
-                                        if let Some(color_param) = params_iter.next() {
-                                            if let Some(&color) = color_param.first() {
-                                                self.g.current_bg = Color::from_ansi(color as u8);
-                                            }
-                                        }
-                                    }
-                                }
+                        }
+                        39 => self.g.cursor.attrs.fg = CellColor::Default, // Default foreground
+                        
+                        // Background colors
+                        40..=47 => self.g.cursor.attrs.bg = CellColor::Indexed((n - 40) as u8),
+                        48 => {
+                            // Extended background color
+                            if let Some(color) = extended_color(sub, &mut params_iter) {
+                                self.g.cursor.attrs.bg = color;
                             }
-                            49 => self.g.current_bg = Color::BLACK, // Default background
-                            
-                            // Bright foreground colors
-                            90..=97 => self.g.current_fg = Color::from_ansi(((*n - 90) + 8) as u8),
-                            // Bright background colors
-                            100..=107 => self.g.current_bg = Color::from_ansi(((*n - 100) + 8) as u8),
-                            
-                            _ => {} // Ignore other SGR codes for now
                         }
+                        49 => self.g.cursor.attrs.bg = CellColor::Default, // Default background
+                        
+                        // Bright foreground colors
+                        90..=97 => self.g.cursor.attrs.fg = CellColor::Indexed(((n - 90) + 8) as u8),
+                        // Bright background colors
+                        100..=107 => self.g.cursor.attrs.bg = CellColor::Indexed(((n - 100) + 8) as u8),
+                        
+                        _ => {} // Ignore other SGR codes for now
                     }
                 }
             }
@@ -229,11 +455,46 @@ impl<'a> Perform for Performer<'a> {
         }
     }
 
-    // ESC single-char sequences; ignore for now
-    fn esc_dispatch(&mut self, _inter: &[u8], _ignore: bool, _byte: u8) {}
+    // ESC sequences
+    fn esc_dispatch(&mut self, inter: &[u8], _ignore: bool, byte: u8) {
+        match (inter, byte) {
+            // DECALN – screen alignment test: fill with 'E', cursor home
+            (b"#", b'8') => {
+                self.g.fill_with('E');
+                self.g.cursor.x = 0;
+                self.g.cursor.y = 0;
+                self.g.cursor.pending_wrap = false;
+            }
+            // HTS – set a tab stop at the cursor column
+            (b"", b'H') => self.g.set_tab_stop(),
+            // IND – index: down a row, scrolling at the bottom
+            (b"", b'D') => self.g.lf(),
+            // NEL – next line: carriage return + index
+            (b"", b'E') => {
+                self.g.cr();
+                self.g.lf();
+            }
+            // RI – reverse index: up a row, scrolling down at the top
+            (b"", b'M') => self.g.reverse_lf(),
+            _ => {}
+        }
+    }
     
-    // OSC (ESC ] ... BEL) – vte will swallow; ignore payload
-    fn osc_dispatch(&mut self, _params: &[&[u8]], _bell_terminated: bool) {}
+    // OSC (ESC ] ... BEL)
+    fn osc_dispatch(&mut self, params: &[&[u8]], bell_terminated: bool) {
+        match params {
+            [b"4", rest @ ..] => match self.osc_4_body.take() {
+                Some(body) => {
+                    let full: Vec<&[u8]> = body.split(|&b| b == b';').collect();
+                    self.osc_4(&full, bell_terminated);
+                }
+                None => self.osc_4(rest, bell_terminated),
+            },
+            [b"133", rest @ ..] => self.osc_133(rest),
+            [b"1337", rest @ ..] => self.osc_1337(rest),
+            _ => {}
+        }
+    }
     
     // Hooks for device control strings
     fn hook(&mut self, _params: &Params, _intermediates: &[u8], _ignore: bool, _action: char) {}
@@ -241,15 +502,109 @@ impl<'a> Perform for Performer<'a> {
     fn unhook(&mut self) {}
 }
 
-pub fn advance_bytes(g: &mut Grid, bytes: &[u8]) {
-    advance_bytes_with_bracketed(g, bytes, None);
+impl<'a> Performer<'a> {
+    // Current state of a DEC private mode, as reported by DECRQM
+    fn dec_mode_status(&self, mode: u16) -> ModeStatus {
+        match mode {
+            1 => ModeStatus::from_flag(self.g.modes.app_cursor_keys),
+            // Autowrap is always on
+            7 => ModeStatus::PermanentlySet,
+            // The cursor is always shown; ?25l is ignored
+            25 => ModeStatus::PermanentlySet,
+            47 | 1047 | 1049 => ModeStatus::from_flag(self.g.is_alt_screen()),
+            2004 => match self.bracketed_paste {
+                Some(bp) => ModeStatus::from_flag(bp.load(Ordering::Relaxed)),
+                None => ModeStatus::NotRecognized,
+            },
+            _ => ModeStatus::NotRecognized,
+        }
+    }
+    
+    // Current state of an ANSI mode, as reported by DECRQM
+    fn ansi_mode_status(&self, mode: u16) -> ModeStatus {
+        match mode {
+            4 => ModeStatus::from_flag(self.g.modes.insert),
+            _ => ModeStatus::NotRecognized,
+        }
+    }
+    
+    // OSC 4 ; index ; ? [; index ; ? ...] – palette query. Any number of
+    // entries is answered in a single OSC 4 reply, in request order, using
+    // the query's terminator, from the theme palette. Setting colors isn't supported.
+    fn osc_4(&mut self, params: &[&[u8]], bell_terminated: bool) {
+        let mut reply = String::from("\x1b]4");
+        let mut answered = 0;
+        for pair in params.chunks_exact(2) {
+            if pair[1] != b"?" {
+                continue;
+            }
+            let Some(index) = std::str::from_utf8(pair[0]).ok().and_then(|s| s.parse::<u8>().ok()) else {
+                continue;
+            };
+            let entry = format!(";{};{}", index, self.g.palette.ansi[index as usize].to_xterm_rgb());
+            if reply.len() + entry.len() > MAX_OSC_REPLY {
+                tracing::debug!("OSC 4 reply capped after {} entries", answered);
+                break;
+            }
+            reply.push_str(&entry);
+            answered += 1;
+        }
+        if answered == 0 {
+            return;
+        }
+        reply.push_str(if bell_terminated { "\x07" } else { "\x1b\\" });
+        self.replies.extend_from_slice(reply.as_bytes());
+    }
+    
+    // FinalTerm shell integration: A prompt start, B command start, C output
+    // start, D command finished (an exit code may follow; it isn't kept)
+    fn osc_133(&mut self, params: &[&[u8]]) {
+        match params.first() {
+            Some(&b"A") => self.g.add_mark(MarkKind::Prompt),
+            // The command line follows the prompt on its row, which A already marked
+            Some(&b"B") => {}
+            Some(&b"C") => self.g.start_command_output(),
+            Some(&b"D") => self.g.end_command_output(),
+            _ => {}
+        }
+    }
+    
+    // iTerm2 proprietary OSC 1337; only SetMark is honored
+    fn osc_1337(&mut self, params: &[&[u8]]) {
+        match params.first() {
+            Some(&b"SetMark") => self.g.add_mark(MarkKind::SetMark),
+            _ => {
+                self.g.unknown_osc_1337 += 1;
+                tracing::debug!("Ignoring OSC 1337 {:?}", params.first().map(|p| String::from_utf8_lossy(p)));
+            }
+        }
+    }
 }
 
-pub fn advance_bytes_with_bracketed(g: &mut Grid, bytes: &[u8], bracketed_paste: Option<Arc<AtomicBool>>) {
-    static PARSER: std::sync::OnceLock<std::sync::Mutex<vte::Parser>> = std::sync::OnceLock::new();
-    let mut parser = PARSER.get_or_init(|| std::sync::Mutex::new(vte::Parser::new())).lock().unwrap();
-    let mut p = Performer { g, bracketed_paste };
-    for &b in bytes { 
-        parser.advance(&mut p, b); 
+// Color for SGR 38/48. `sub` holds colon subparameters (ITU T.416):
+//   38:5:n, 38:2:r:g:b, or 38:2:cs:r:g:b with a (possibly empty) color-space id.
+// Without subparameters the legacy 38;5;n / 38;2;r;g;b form is read from `rest`.
+fn extended_color<'a>(sub: &[u16], rest: &mut impl Iterator<Item = &'a [u16]>) -> Option<CellColor> {
+    let rgb = |r: u16, g: u16, b: u16| CellColor::Rgb(Color { r: r as u8, g: g as u8, b: b as u8 });
+    
+    if !sub.is_empty() {
+        return match *sub {
+            [5, n, ..] => Some(CellColor::Indexed(n as u8)),
+            [2, _cs, r, g, b, ..] => Some(rgb(r, g, b)),
+            [2, r, g, b] => Some(rgb(r, g, b)),
+            _ => None,
+        };
     }
-}
\ No newline at end of file
+    
+    let mut next = || rest.next().and_then(|p| p.first()).copied();
+    match next()? {
+        5 => next().map(|n| CellColor::Indexed(n as u8)),
+        2 => {
+            let r = next().unwrap_or(0);
+            let g = next().unwrap_or(0);
+            let b = next().unwrap_or(0);
+            Some(rgb(r, g, b))
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,39 @@
+//! BEL reaches the embedder as an event and a count on the grid; OSC terminators don't.
+
+use the_dev_terminal_core::grid::Grid;
+use the_dev_terminal_core::vt::VtEvent;
+use the_dev_terminal_core::Terminal;
+
+#[test]
+fn bel_yields_a_bell_event() {
+    let mut term = Terminal::with_grid(Grid::new(20, 4, 100));
+    assert_eq!(term.advance(b"\x07"), [VtEvent::Bell]);
+    assert!(term.advance(b"no bell here").is_empty());
+}
+
+#[test]
+fn every_bel_in_a_chunk_is_reported() {
+    let mut term = Terminal::with_grid(Grid::new(20, 4, 100));
+    let events = term.advance(b"a\x07b\x07\x07c");
+    assert_eq!(events, [VtEvent::Bell; 3]);
+    // The bells print nothing and don't move the cursor
+    assert_eq!(term.grid.row_text(0).trim_end(), "abc");
+    assert_eq!(term.grid.x(), 3);
+}
+
+#[test]
+fn bel_ending_an_osc_is_not_a_bell() {
+    let mut term = Terminal::with_grid(Grid::new(20, 4, 100));
+    assert!(term.advance(b"\x1b]0;title\x07").is_empty());
+    assert_eq!(term.advance(b"\x1b]0;title\x07\x07"), [VtEvent::Bell]);
+}
+
+#[test]
+fn bel_counts_on_the_grid_until_taken() {
+    let mut term = Terminal::with_grid(Grid::new(20, 4, 100));
+    term.advance(b"\x07");
+    assert_eq!(term.grid.bells, 1);
+    term.advance(b"a\x07\x1b]0;title\x07\x07");
+    assert_eq!(term.grid.take_bells(), 3);
+    assert_eq!(term.grid.take_bells(), 0);
+}
@@ -0,0 +1,49 @@
+//! Bidi policy: logical order on the grid, optional reversed RTL runs for display.
+
+use the_dev_terminal_core::bidi::isolate_runs;
+use the_dev_terminal_core::grid::Grid;
+use the_dev_terminal_core::Terminal;
+
+fn printed(text: &str) -> Terminal {
+    let mut term = Terminal::with_grid(Grid::new(20, 2, 10));
+    term.advance(text.as_bytes());
+    term
+}
+
+#[test]
+fn rtl_override_spoof_keeps_logical_order() {
+    // "invoice<RLO>fdp.exe" would display as "invoiceexe.pdf"
+    let term = printed("invoice\u{202E}fdp.exe");
+    assert_eq!(term.grid.row_text(0).trim_end(), "invoicefdp.exe");
+    assert_eq!(term.grid.x(), 14);
+    assert_eq!(isolate_runs(term.grid.row_cells(0)), None);
+
+    // Isolates, embeddings and marks are dropped the same way
+    let term = printed("a\u{2067}b\u{2069}c\u{200F}d\u{202B}e\u{202C}");
+    assert_eq!(term.grid.row_text(0).trim_end(), "abcde");
+}
+
+#[test]
+fn isolate_runs_reverse_each_rtl_run_in_place() {
+    // Columns 3-12 hold "שלום, עולם" between Latin text
+    let term = printed("ab \u{5e9}\u{5dc}\u{5d5}\u{5dd}, \u{5e2}\u{5d5}\u{5dc}\u{5dd} cd");
+    let map = isolate_runs(term.grid.row_cells(0)).unwrap();
+    assert_eq!(map.len(), 20);
+    let mut want: Vec<usize> = (0..20).collect();
+    want[3..=12].reverse();
+    assert_eq!(map, want);
+    // The punctuation and space inside the run move with it; the space after it doesn't
+    assert_eq!((map[7], map[8], map[13]), (8, 7, 13));
+}
+
+#[test]
+fn ltr_text_and_wide_characters_split_runs() {
+    // Single RTL letters between LTR cells have nothing to swap with
+    let term = printed("\u{5d0}x\u{5d1}\u{4e16}\u{5d2}");
+    assert_eq!(isolate_runs(term.grid.row_cells(0)), None);
+
+    // Two runs either side of a word reverse separately
+    let term = printed("\u{5d0}\u{5d1} x \u{5d2}\u{5d3}");
+    let map = isolate_runs(term.grid.row_cells(0)).unwrap();
+    assert_eq!(&map[..7], [1, 0, 2, 3, 4, 6, 5]);
+}
@@ -0,0 +1,62 @@
+//! Clearing the screen, by an app's `CSI 2 J` or the clear-screen shortcuts.
+
+use the_dev_terminal_core::grid::Grid;
+use the_dev_terminal_core::Terminal;
+
+// Two lines of history, then three screen rows of text
+fn full_screen(clear_pushes_to_scrollback: bool) -> Terminal {
+    let mut term = Terminal::with_grid(Grid::new(10, 3, 100));
+    term.grid.clear_pushes_to_scrollback = clear_pushes_to_scrollback;
+    term.advance(b"h0\r\nh1\r\ns0\r\ns1\r\ns2");
+    assert_eq!(term.grid.scrollback.len(), 2);
+    term
+}
+
+#[test]
+fn ed2_discards_the_screen_by_default() {
+    let mut term = full_screen(false);
+    term.advance(b"\x1b[2J");
+    assert_eq!(term.grid.scrollback.len(), 2);
+    assert!(term.grid.to_string_lines().trim().is_empty());
+}
+
+#[test]
+fn ed2_moves_the_screen_into_scrollback_when_asked() {
+    let mut term = full_screen(true);
+    term.advance(b"\x1b[2J");
+    assert_eq!(term.grid.scrollback.len(), 5);
+    assert_eq!(term.grid.get_text_absolute((0, 2), (9, 4)), "s0\ns1\ns2");
+    assert!(term.grid.to_string_lines().trim().is_empty());
+    assert_eq!((term.grid.x(), term.grid.y()), (0, 0));
+
+    // Blank rows below the last text aren't pushed
+    term.advance(b"x\x1b[2J");
+    assert_eq!(term.grid.scrollback.len(), 6);
+}
+
+#[test]
+fn ed2_on_the_alt_screen_never_feeds_scrollback() {
+    let mut term = full_screen(true);
+    term.advance(b"\x1b[?1049hvim\x1b[2J");
+    assert_eq!(term.grid.scrollback.len(), 2);
+}
+
+#[test]
+fn clear_screen_leaves_scrollback_alone() {
+    for keep in [false, true] {
+        let mut term = full_screen(keep);
+        term.grid.clear_screen();
+        assert_eq!(term.grid.scrollback.len(), 2);
+        assert!(term.grid.to_string_lines().trim().is_empty());
+    }
+}
+
+#[test]
+fn clearing_scrollback_and_screen_drops_everything() {
+    // What ⌘K does, whatever clear_pushes_to_scrollback says
+    let mut term = full_screen(true);
+    term.grid.scrollback.clear();
+    term.grid.clear_screen();
+    assert_eq!(term.grid.scrollback.len(), 0);
+    assert_eq!(term.grid.scrollable_lines(), 3);
+}
@@ -0,0 +1,46 @@
+//! Device attribute replies, which tools like vim and tmux use to probe the terminal.
+
+use the_dev_terminal_core::grid::Grid;
+use the_dev_terminal_core::Terminal;
+
+fn reply_to(query: &[u8]) -> Vec<u8> {
+    let mut term = Terminal::with_grid(Grid::new(80, 24, 100));
+    term.advance(query);
+    term.take_replies()
+}
+
+#[test]
+fn primary_da_claims_a_vt220_with_color() {
+    assert_eq!(reply_to(b"\x1b[c"), b"\x1b[?62;22c");
+    assert_eq!(reply_to(b"\x1b[0c"), b"\x1b[?62;22c");
+}
+
+#[test]
+fn secondary_da_reports_the_crate_version() {
+    let part = |v: &str| v.parse::<u32>().unwrap();
+    let version = part(env!("CARGO_PKG_VERSION_MAJOR")) * 10000
+        + part(env!("CARGO_PKG_VERSION_MINOR")) * 100
+        + part(env!("CARGO_PKG_VERSION_PATCH"));
+    let want = format!("\x1b[>1;{version};0c");
+    assert_eq!(reply_to(b"\x1b[>c"), want.as_bytes());
+    assert_eq!(reply_to(b"\x1b[>0c"), want.as_bytes());
+}
+
+#[test]
+fn other_da_requests_get_no_reply() {
+    assert!(reply_to(b"\x1b[1c").is_empty());
+    assert!(reply_to(b"\x1b[=c").is_empty());
+    assert!(reply_to(b"\x1b[>1c").is_empty());
+}
+
+#[test]
+fn cursor_key_mode_is_tracked_and_reported() {
+    let mut term = Terminal::with_grid(Grid::new(80, 24, 100));
+    assert!(!term.grid.modes.app_cursor_keys);
+    term.advance(b"\x1b[?1h\x1b[?1$p");
+    assert!(term.grid.modes.app_cursor_keys);
+    assert_eq!(term.take_replies(), b"\x1b[?1;1$y");
+    term.advance(b"\x1b[?1l\x1b[?1$p");
+    assert!(!term.grid.modes.app_cursor_keys);
+    assert_eq!(term.take_replies(), b"\x1b[?1;2$y");
+}
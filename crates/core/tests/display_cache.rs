@@ -0,0 +1,74 @@
+//! Display content is cached until the grid changes or scrolls.
+
+use std::sync::Arc;
+use the_dev_terminal_core::grid::Grid;
+use the_dev_terminal_core::Terminal;
+
+#[test]
+fn unchanged_grid_returns_the_cached_content() {
+    let mut term = Terminal::with_grid(Grid::new(10, 3, 100));
+    term.advance(b"hello");
+    let (cells, text) = (term.grid.get_cells_for_display(), term.grid.get_display_content());
+    assert!(Arc::ptr_eq(&cells, &term.grid.get_cells_for_display()));
+    assert!(Arc::ptr_eq(&text, &term.grid.get_display_content()));
+    assert_eq!(text.lines().next(), Some("hello     "));
+    
+    // Moving the cursor leaves the cells alone
+    term.advance(b"\x1b[2;3H");
+    assert!(Arc::ptr_eq(&text, &term.grid.get_display_content()));
+}
+
+#[test]
+fn mutation_invalidates_the_cache() {
+    let mut term = Terminal::with_grid(Grid::new(10, 3, 100));
+    term.advance(b"hello");
+    let text = term.grid.get_display_content();
+    let generation = term.grid.generation();
+    
+    term.advance(b"!");
+    assert!(term.grid.generation() > generation);
+    assert_eq!(term.grid.get_display_content().lines().next(), Some("hello!    "));
+    assert_eq!(text.lines().next(), Some("hello     "));
+    
+    term.grid.reverse_lf();
+    assert_eq!(term.grid.get_display_content().lines().nth(1), Some("hello!    "));
+}
+
+#[test]
+fn scrolling_the_view_invalidates_the_cache() {
+    let mut term = Terminal::with_grid(Grid::new(10, 2, 100));
+    term.advance(b"one\r\ntwo\r\nthree");
+    assert_eq!(&*term.grid.get_display_content(), "two       \nthree     \n");
+    term.grid.scroll_up(1);
+    assert_eq!(&*term.grid.get_display_content(), "one       \ntwo       \n");
+}
+
+#[test]
+fn scrolled_viewport_borrows_history_and_composes_fast() {
+    use the_dev_terminal_core::perf::PerfTimer;
+    let mut term = Terminal::with_grid(Grid::new(200, 60, 10_000));
+    for i in 0..10_100 {
+        term.advance(format!("\x1b[3{}mline {} {}\r\n", i % 8, i, "x".repeat(i % 150)).as_bytes());
+    }
+    let g = &mut term.grid;
+    assert_eq!(g.scrollback.len(), 10_000);
+    g.scrollback.scroll_up(5_000);
+    let top = g.view_top();
+    
+    // History rows are the stored lines themselves, not copies
+    let rows: Vec<_> = g.visible_rows(top, g.rows).collect();
+    let stored: Vec<_> = g.scrollback.get_visible_lines(g.rows).collect();
+    assert_eq!(rows.len(), stored.len());
+    assert!(rows.iter().zip(&stored).all(|(a, b)| std::ptr::eq(*a, *b)));
+    
+    // Best of a few runs, so a busy machine doesn't fail the test
+    let best = (0..20)
+        .map(|_| {
+            let timer = PerfTimer::new("compose viewport");
+            let cells = g.viewport_cells(top, g.rows);
+            assert_eq!(cells.len(), 200 * 60);
+            timer.elapsed_ms()
+        })
+        .fold(f32::MAX, f32::min);
+    assert!(best < 1.0, "{best} ms");
+}
@@ -0,0 +1,81 @@
+//! Streaming export of scrollback and screen, as written by "save scrollback".
+
+use std::io::{self, Write};
+use std::time::{Duration, SystemTime};
+use the_dev_terminal_core::grid::Grid;
+use the_dev_terminal_core::paths;
+use the_dev_terminal_core::Terminal;
+
+// A writer that takes `left` bytes and fails after that
+struct Limited {
+    written: Vec<u8>,
+    left: usize,
+}
+
+impl Write for Limited {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.left == 0 {
+            return Err(io::Error::other("disk full"));
+        }
+        let n = buf.len().min(self.left);
+        self.written.extend_from_slice(&buf[..n]);
+        self.left -= n;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn streamed_text_matches_export() {
+    let mut term = Terminal::with_grid(Grid::new(6, 4, 100));
+    term.advance(b"one   \r\nwrapped line here\r\n\r\n\r\n\x1b[1;32mgreen\x1b[m\r\nlast\r\n\r\n");
+    assert!(term.grid.history_len() > 0);
+
+    let mut text = Vec::new();
+    term.grid.write_all_text(&mut text).unwrap();
+    let text = String::from_utf8(text).unwrap();
+    // Wraps joined, trailing spaces trimmed, inner blank lines kept, trailing ones dropped
+    assert_eq!(text, "one\nwrapped line here\n\n\ngreen\nlast\n");
+    assert_eq!(text, term.grid.export_text());
+
+    let mut ansi = Vec::new();
+    term.grid.write_all_ansi(&mut ansi).unwrap();
+    let ansi = String::from_utf8(ansi).unwrap();
+    assert_eq!(ansi, term.grid.export_ansi());
+    assert!(ansi.contains("\x1b[1;32mgreen\x1b[0m\n"), "{ansi:?}");
+}
+
+#[test]
+fn empty_grid_writes_one_newline() {
+    let grid = Grid::new(10, 3, 100);
+    let mut text = Vec::new();
+    grid.write_all_text(&mut text).unwrap();
+    assert_eq!(text, b"\n");
+    assert_eq!(grid.export_text(), "\n");
+}
+
+#[test]
+fn write_errors_stop_the_export() {
+    let mut term = Terminal::with_grid(Grid::new(20, 5, 1000));
+    for i in 0..500 {
+        term.advance(format!("line {}\r\n", i).as_bytes());
+    }
+    let mut out = Limited { written: Vec::new(), left: 100 };
+    let err = term.grid.write_all_text(&mut out).unwrap_err();
+    assert_eq!(err.to_string(), "disk full");
+    assert_eq!(out.written.len(), 100);
+    assert!(term.grid.export_text().as_bytes().starts_with(&out.written));
+}
+
+#[test]
+fn dump_names_are_utc_timestamps() {
+    assert_eq!(paths::dump_file_name(SystemTime::UNIX_EPOCH), "terminal-1970-01-01-000000.txt");
+    // 2024-02-29 23:59:58 UTC
+    let leap = SystemTime::UNIX_EPOCH + Duration::from_secs(1_709_251_198);
+    assert_eq!(paths::dump_file_name(leap), "terminal-2024-02-29-235958.txt");
+    let later = SystemTime::UNIX_EPOCH + Duration::from_secs(1_735_689_600);
+    assert_eq!(paths::dump_file_name(later), "terminal-2025-01-01-000000.txt");
+}
@@ -0,0 +1,47 @@
+//! Redraw pacing: deadlines from the frame rate caps in `[performance]`.
+
+use std::time::{Duration, Instant};
+use the_dev_terminal_core::frame_pacing::{next_frame_deadline, FramePacer};
+
+const MS: Duration = Duration::from_millis(1);
+
+#[test]
+fn deadline_is_one_interval_after_the_last_frame() {
+    let last = Instant::now();
+    assert_eq!(next_frame_deadline(last + 2 * MS, last, 100), last + 10 * MS);
+    assert_eq!(next_frame_deadline(last + 2 * MS, last, 30), last + Duration::from_secs(1) / 30);
+}
+
+#[test]
+fn overdue_frame_is_due_now() {
+    let last = Instant::now();
+    let now = last + 50 * MS;
+    assert_eq!(next_frame_deadline(now, last, 120), now);
+}
+
+#[test]
+fn zero_fps_is_uncapped() {
+    let last = Instant::now();
+    let now = last + MS;
+    assert_eq!(next_frame_deadline(now, last, 0), now);
+}
+
+#[test]
+fn pacer_uses_max_fps_while_active_and_idle_fps_otherwise() {
+    let t0 = Instant::now();
+    let mut pacer = FramePacer::new(100, 10);
+    
+    // Nothing drawn yet: the first frame may go right away
+    pacer.request_frame(t0, false);
+    assert!(pacer.take_due(t0));
+    pacer.frame_drawn(t0);
+    
+    pacer.request_frame(t0 + MS, false);
+    assert_eq!(pacer.deadline(), Some(t0 + 100 * MS));
+    // Activity pulls the pending frame in
+    pacer.request_frame(t0 + MS, true);
+    assert_eq!(pacer.deadline(), Some(t0 + 10 * MS));
+    assert!(!pacer.take_due(t0 + 5 * MS));
+    assert!(pacer.take_due(t0 + 10 * MS));
+    assert_eq!(pacer.deadline(), None);
+}
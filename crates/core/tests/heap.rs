@@ -0,0 +1,26 @@
+use the_dev_terminal_core::grid::{Cell, Grid};
+use the_dev_terminal_core::perf::PerfMonitor;
+use the_dev_terminal_core::Terminal;
+
+#[test]
+fn heap_usage_counts_screen_cells_and_scrollback() {
+    let mut term = Terminal::with_grid(Grid::new(10, 4, 100));
+    let cell = std::mem::size_of::<Cell>();
+    let usage = term.grid.heap_usage();
+    assert_eq!((usage.cells, usage.scrollback), (40 * cell, 0));
+    
+    term.advance(b"1\r\n2\r\n3\r\n4\r\n5\r\n6");
+    let usage = term.grid.heap_usage();
+    assert_eq!(usage.cells, 40 * cell);
+    assert_eq!(usage.scrollback, term.grid.scrollback.bytes());
+    assert!(usage.scrollback >= 2 * 10 * cell);
+    
+    // The primary screen is kept while the alternate one is up
+    term.advance(b"\x1b[?1049h");
+    assert_eq!(term.grid.heap_usage().cells, 80 * cell);
+    
+    let mut perf = PerfMonitor::new();
+    perf.set_enabled(true);
+    perf.record_heap(term.grid.heap_usage());
+    assert_eq!(perf.get_stats().heap.cells, 80 * cell);
+}
@@ -0,0 +1,84 @@
+//! Crash-loop detection across launches, and the safe mode it leads to.
+
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use the_dev_terminal_core::config::Config;
+use the_dev_terminal_core::paths::{LaunchSentinel, SAFE_MODE_AFTER_FAILURES};
+
+// Far above any real pid_max, so no such process exists
+const DEAD_PID: u32 = 0x3fff_fff0;
+
+fn state_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("tdt-launch-{}-{}", std::process::id(), name));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+fn own_sentinel(dir: &Path) -> PathBuf {
+    dir.join(format!("launch-{}.starting", std::process::id()))
+}
+
+// What a launch that died before its first frame leaves behind; old enough
+// to be stale where the process can't be checked
+fn plant_crashed_launch(dir: &Path, pid: u32) {
+    let path = dir.join(format!("launch-{pid}.starting"));
+    let file = File::create(path).unwrap();
+    file.set_modified(SystemTime::now() - Duration::from_secs(3600)).unwrap();
+}
+
+#[test]
+fn clean_start_has_no_failures() {
+    let dir = state_dir("clean");
+    let (sentinel, history) = LaunchSentinel::begin(&dir).unwrap();
+    assert_eq!(history.failures, 0);
+    assert!(!history.should_engage_safe_mode());
+    assert!(own_sentinel(&dir).exists());
+
+    sentinel.mark_started().unwrap();
+    assert!(!own_sentinel(&dir).exists());
+    let (_, history) = LaunchSentinel::begin(&dir).unwrap();
+    assert_eq!(history.failures, 0);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn one_crash_warns_but_two_engage_safe_mode() {
+    let dir = state_dir("crashes");
+    std::fs::create_dir_all(&dir).unwrap();
+
+    plant_crashed_launch(&dir, DEAD_PID);
+    let (_, history) = LaunchSentinel::begin(&dir).unwrap();
+    assert_eq!(history.failures, 1);
+    assert!(!history.should_engage_safe_mode());
+    // The dead launch is counted once and its sentinel removed
+    assert!(!dir.join(format!("launch-{DEAD_PID}.starting")).exists());
+
+    plant_crashed_launch(&dir, DEAD_PID + 1);
+    let (sentinel, history) = LaunchSentinel::begin(&dir).unwrap();
+    assert_eq!(history.failures, SAFE_MODE_AFTER_FAILURES);
+    assert!(history.should_engage_safe_mode());
+
+    // Coming up once resets the count
+    sentinel.mark_started().unwrap();
+    let (_, history) = LaunchSentinel::begin(&dir).unwrap();
+    assert_eq!(history.failures, 0);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn safe_mode_bypasses_a_broken_config() {
+    let home = state_dir("home");
+    let config_dir = home.join(".config/the-dev-terminal");
+    std::fs::create_dir_all(&config_dir).unwrap();
+    std::fs::write(config_dir.join("config.toml"), "[general\nnot toml").unwrap();
+    // The only test in this binary that reads HOME
+    std::env::set_var("HOME", &home);
+
+    assert!(Config::resolve(false).is_err());
+    let config = Config::resolve(true).unwrap();
+    assert_eq!(config.general.word_chars, Config::default().general.word_chars);
+    // The broken file is left for the user to fix
+    assert_eq!(std::fs::read_to_string(config_dir.join("config.toml")).unwrap(), "[general\nnot toml");
+    std::fs::remove_dir_all(&home).unwrap();
+}
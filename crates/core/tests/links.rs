@@ -0,0 +1,66 @@
+use the_dev_terminal_core::grid::Grid;
+use the_dev_terminal_core::links::{find_urls, LinkRef};
+use the_dev_terminal_core::Terminal;
+
+fn uris(text: &str) -> Vec<String> {
+    find_urls(text).into_iter().map(|(_, _, uri)| uri).collect()
+}
+
+#[test]
+fn detects_each_scheme_and_bare_hosts() {
+    assert_eq!(
+        uris("see https://a.io/x http://b.io ftp://c.io file:///tmp/d mailto:me@e.io www.f.io"),
+        [
+            "https://a.io/x",
+            "http://b.io",
+            "ftp://c.io",
+            "file:///tmp/d",
+            "mailto:me@e.io",
+            "https://www.f.io",
+        ]
+    );
+    // Only at the start of a word, and not a bare prefix
+    assert!(uris("xhttps://a.io https:// www.").is_empty());
+}
+
+#[test]
+fn trailing_punctuation_and_unbalanced_brackets_are_left_out() {
+    assert_eq!(uris("(see https://a.io/x)."), ["https://a.io/x"]);
+    assert_eq!(uris("https://en.wikipedia.org/wiki/Rust_(language), ok"), ["https://en.wikipedia.org/wiki/Rust_(language)"]);
+    assert_eq!(uris("<https://a.io/?q=1>"), ["https://a.io/?q=1"]);
+    // Balanced parentheses and brackets stay, even nested or at the very end
+    assert_eq!(uris("https://en.wikipedia.org/wiki/Rust_(programming_language)"), ["https://en.wikipedia.org/wiki/Rust_(programming_language)"]);
+    assert_eq!(uris("see https://a.io/f(x_(y))."), ["https://a.io/f(x_(y))"]);
+    assert_eq!(uris("[link](https://a.io/x[1])"), ["https://a.io/x[1]"]);
+    // Sentence punctuation after a URL is not part of it
+    assert_eq!(uris("Docs are at https://a.io/docs."), ["https://a.io/docs"]);
+    assert_eq!(uris("https://a.io, https://b.io; https://c.io: www.d.io."), ["https://a.io", "https://b.io", "https://c.io", "https://www.d.io"]);
+    // ... but not inside it
+    assert_eq!(uris("https://a.io/v1.2/x:y"), ["https://a.io/v1.2/x:y"]);
+    // Positions are in characters
+    assert_eq!(find_urls("\u{4e16} www.a.io")[0].0, 2);
+}
+
+#[test]
+fn link_at_joins_wrapped_rows() {
+    let mut term = Terminal::with_grid(Grid::new(10, 4, 10));
+    term.advance(b"go https://ab.io/cd now");
+    let link = LinkRef { uri: "https://ab.io/cd".to_string(), start: (3, 0), end: (8, 1) };
+    assert_eq!(term.grid.link_at(3, 0), Some(link.clone()));
+    assert_eq!(term.grid.link_at(5, 1), Some(link));
+    assert_eq!(term.grid.link_at(2, 0), None);
+    assert_eq!(term.grid.link_at(0, 2), None);
+}
+
+#[test]
+fn link_at_sees_new_output() {
+    let mut term = Terminal::with_grid(Grid::new(20, 3, 10));
+    term.advance(b"plain text");
+    assert_eq!(term.grid.link_at(2, 0), None);
+    term.advance(b"\x1b[2K\rwww.a.io");
+    assert_eq!(term.grid.link_at(2, 0).map(|l| l.uri), Some("https://www.a.io".to_string()));
+    
+    // Lines in scrollback keep their links
+    term.advance(b"\r\n\r\n\r\n\r\n");
+    assert_eq!(term.grid.link_at(0, 0).map(|l| l.end), Some((7, 0)));
+}
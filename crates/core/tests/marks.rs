@@ -0,0 +1,94 @@
+//! Shell-integration marks (OSC 133 ; A and OSC 1337 ; SetMark) and
+//! navigating between them.
+
+use the_dev_terminal_core::grid::Grid;
+use the_dev_terminal_core::marks::{MarkKind, MarkList};
+use the_dev_terminal_core::Terminal;
+
+#[test]
+fn prompt_markers_record_their_absolute_rows() {
+    let mut term = Terminal::with_grid(Grid::new(20, 3, 100));
+    // Prompts on rows 0 and 3; by the end row 0 has scrolled into history
+    term.advance(b"\x1b]133;A\x07$ \x1b]133;B\x07ls\r\n\x1b]133;C\x07a\r\nb\r\n\x1b]133;D;0\x07");
+    term.advance(b"\x1b]133;A\x07$ \x1b]133;B\x07");
+    assert_eq!(term.grid.history_len(), 1);
+    let marks: Vec<(usize, MarkKind)> = term.grid.marks.iter().map(|m| (m.row, m.kind)).collect();
+    assert_eq!(marks, [(0, MarkKind::Prompt), (3, MarkKind::Prompt)]);
+    assert_eq!(term.grid.row_text(3).trim_end(), "$");
+}
+
+#[test]
+fn prompts_are_found_above_and_below_a_row() {
+    let mut term = Terminal::with_grid(Grid::new(20, 3, 100));
+    for cmd in ["one", "two", "three"] {
+        term.advance(format!("\x1b]133;A\x07$ {cmd}\r\nout\r\n").as_bytes());
+    }
+    // Prompts at rows 0, 2 and 4
+    let g = &term.grid;
+    assert_eq!(g.prev_mark(4), Some(2));
+    assert_eq!(g.prev_mark(2), Some(0));
+    assert_eq!(g.prev_mark(0), None);
+    assert_eq!(g.next_mark(0), Some(2));
+    assert_eq!(g.next_mark(3), Some(4));
+    assert_eq!(g.next_mark(4), None);
+}
+
+#[test]
+fn output_block_spans_from_output_start_to_command_end() {
+    let mut term = Terminal::with_grid(Grid::new(20, 10, 100));
+    // Rows: 0 prompt, 1-2 output of ls, 3 prompt, 4 output of seq (ended by the next prompt), 5 prompt
+    term.advance(b"\x1b]133;A\x07$ ls\r\n\x1b]133;C\x07a.txt\r\nb.txt\r\n\x1b]133;D;0\x07");
+    term.advance(b"\x1b]133;A\x07$ seq 1\r\n\x1b]133;C\x071\r\n");
+    term.advance(b"\x1b]133;A\x07$ true\r\n\x1b]133;C\x07\x1b]133;D;0\x07\x1b]133;A\x07$ ");
+    let g = &term.grid;
+    assert_eq!(g.output_block_for_row(1), Some((1, 2)));
+    assert_eq!(g.output_block_for_row(2), Some((1, 2)));
+    assert_eq!(g.output_block_for_row(4), Some((4, 4)));
+    // Prompt rows and a command that printed nothing have no output block
+    assert_eq!(g.output_block_for_row(0), None);
+    assert_eq!(g.output_block_for_row(3), None);
+    assert_eq!(g.output_block_for_row(6), None);
+}
+
+#[test]
+fn osc_133_and_1337_marks_share_one_list() {
+    let mut term = Terminal::with_grid(Grid::new(20, 6, 100));
+    term.advance(b"\x1b]133;A\x07$ \x1b]133;B\x07make\r\n\x1b]133;C\x07");
+    term.advance(b"\x1b]1337;SetMark\x07building\r\n\x1b]133;D;0\x07");
+    // Both kinds on one row: the prompt is kept, whichever came first
+    term.advance(b"\x1b]1337;SetMark\x07\x1b]133;A\x07$ \r\n");
+    term.advance(b"\x1b]133;A\x07\x1b]1337;SetMark\x07$ ");
+    let g = &term.grid;
+    let marks: Vec<(usize, MarkKind)> = g.marks.iter().map(|m| (m.row, m.kind)).collect();
+    assert_eq!(
+        marks,
+        [(0, MarkKind::Prompt), (1, MarkKind::SetMark), (2, MarkKind::Prompt), (3, MarkKind::Prompt)]
+    );
+    // Navigation treats them alike
+    assert_eq!(g.next_mark(0), Some(1));
+    assert_eq!(g.prev_mark(2), Some(1));
+    assert_eq!(g.next_mark(3), None);
+    assert_eq!(g.unknown_osc_1337, 0);
+}
+
+#[test]
+fn unknown_osc_1337_subcommands_are_counted() {
+    let mut term = Terminal::with_grid(Grid::new(20, 3, 100));
+    term.advance(b"\x1b]1337;CurrentDir=/tmp\x07\x1b]1337;SetMark\x07");
+    assert_eq!(term.grid.unknown_osc_1337, 1);
+    assert_eq!(term.grid.marks.len(), 1);
+}
+
+#[test]
+fn marks_collapsing_onto_one_row_keep_the_prompt() {
+    for (first, second) in [(MarkKind::SetMark, MarkKind::Prompt), (MarkKind::Prompt, MarkKind::SetMark)] {
+        let mut marks = MarkList::new();
+        marks.add(4, first);
+        marks.add(5, second);
+        marks.add(8, MarkKind::SetMark);
+        // Rows 4 and 5 both land on row 2
+        marks.remap(|(col, row)| Some((col, row / 2)));
+        let got: Vec<(usize, MarkKind)> = marks.iter().map(|m| (m.row, m.kind)).collect();
+        assert_eq!(got, [(2, MarkKind::Prompt), (4, MarkKind::SetMark)], "{first:?} then {second:?}");
+    }
+}
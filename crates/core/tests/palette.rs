@@ -0,0 +1,114 @@
+//! Cell colors resolved against the theme palette at draw time.
+
+use the_dev_terminal_core::config::ThemeConfig;
+use the_dev_terminal_core::grid::{Cell, CellColor, Color, Grid};
+use the_dev_terminal_core::palette::Palette;
+use the_dev_terminal_core::Terminal;
+
+#[test]
+fn theme_fills_defaults_and_the_sixteen_ansi_slots() {
+    let theme = ThemeConfig { red: "#102030".to_string(), background: "#0f0f10".to_string(), ..ThemeConfig::default() };
+    let palette = Palette::from_theme(&theme);
+    assert_eq!(palette.background, Color { r: 15, g: 15, b: 16 });
+    assert_eq!(palette.fg(CellColor::Indexed(1)), Color { r: 0x10, g: 0x20, b: 0x30 });
+    assert_eq!(palette.bg(CellColor::Default), palette.background);
+    assert_eq!(palette.fg(CellColor::Default), palette.foreground);
+    // The cube and grays aren't themed
+    assert_eq!(palette.ansi[196], Color::from_ansi(196));
+    assert_eq!(palette.fg(CellColor::Rgb(Color { r: 1, g: 2, b: 3 })), Color { r: 1, g: 2, b: 3 });
+}
+
+#[test]
+fn sgr_indexes_1_and_9_resolve_to_the_theme() {
+    let theme = ThemeConfig {
+        red: "#aa0000".to_string(),
+        bright_red: "#ff5555".to_string(),
+        ..ThemeConfig::default()
+    };
+    let themed = Palette::from_theme(&theme);
+    let builtin = Palette::default();
+    
+    // Standard, bright and 256-color forms of both indexes
+    let mut term = Terminal::with_grid(Grid::new(10, 2, 10));
+    term.advance(b"\x1b[31ma\x1b[91mb\x1b[38;5;1mc\x1b[38;5;9md\x1b[38;5;196me");
+    let fg: Vec<CellColor> = term.grid.cells[..5].iter().map(|c| c.fg).collect();
+    assert_eq!(fg[..4], [CellColor::Indexed(1), CellColor::Indexed(9), CellColor::Indexed(1), CellColor::Indexed(9)]);
+    
+    let red = Color { r: 0xaa, g: 0, b: 0 };
+    let bright_red = Color { r: 0xff, g: 0x55, b: 0x55 };
+    let drawn = |p: &Palette| fg.iter().map(|&c| p.fg(c)).collect::<Vec<_>>();
+    assert_eq!(drawn(&themed), [red, bright_red, red, bright_red, Color::from_ansi(196)]);
+    assert_eq!(drawn(&builtin), [Color::RED, Color::BRIGHT_RED, Color::RED, Color::BRIGHT_RED, Color::from_ansi(196)]);
+}
+
+#[test]
+fn bad_theme_colors_keep_the_builtin_ones() {
+    let theme = ThemeConfig { green: "not a color".to_string(), ..ThemeConfig::default() };
+    assert_eq!(Palette::from_theme(&theme).ansi[2], Color::GREEN);
+}
+
+#[test]
+fn switching_palette_recolors_existing_cells() {
+    let mut term = Terminal::with_grid(Grid::new(10, 2, 10));
+    term.advance(b"\x1b[31;44mx\x1b[my");
+    let x = term.grid.cells[0].attrs();
+    assert_eq!((x.fg, x.bg), (CellColor::Indexed(1), CellColor::Indexed(4)));
+    assert_eq!(term.grid.cells[1].fg, CellColor::Default);
+    
+    term.grid.take_dirty_rows();
+    let mut light = Palette::default();
+    light.ansi[1] = Color { r: 200, g: 0, b: 0 };
+    light.background = Color::BRIGHT_WHITE;
+    term.grid.set_palette(light);
+    assert_eq!(term.grid.take_dirty_rows(), [0, 1]);
+    assert_eq!(term.grid.palette.fg(term.grid.cells[0].fg), Color { r: 200, g: 0, b: 0 });
+    assert_eq!(term.grid.palette.bg(term.grid.cells[1].bg), Color::BRIGHT_WHITE);
+}
+
+#[test]
+fn palette_queries_answer_with_the_theme() {
+    let mut term = Terminal::with_grid(Grid::new(10, 2, 10));
+    let mut palette = Palette::default();
+    palette.ansi[1] = Color { r: 0x12, g: 0x34, b: 0x56 };
+    term.grid.set_palette(palette);
+    term.advance(b"\x1b]4;1;?\x07");
+    assert_eq!(term.take_replies(), b"\x1b]4;1;rgb:1212/3434/5656\x07");
+}
+
+#[test]
+fn bold_ansi_colors_draw_bright_unless_disabled() {
+    let mut term = Terminal::with_grid(Grid::new(10, 2, 10));
+    term.advance(b"\x1b[1;31ma\x1b[22mb\x1b[1;91mc\x1b[38;5;1md\x1b[39me");
+    let attrs: Vec<_> = term.grid.cells[..5].iter().map(Cell::attrs).collect();
+    
+    let mut palette = Palette::default();
+    assert!(palette.bold_as_bright);
+    let drawn = |p: &Palette| attrs.iter().map(|a| p.text_fg(a)).collect::<Vec<_>>();
+    assert_eq!(drawn(&palette), [Color::BRIGHT_RED, Color::RED, Color::BRIGHT_RED, Color::BRIGHT_RED, palette.foreground]);
+    
+    palette.bold_as_bright = false;
+    assert_eq!(drawn(&palette), [Color::RED, Color::RED, Color::BRIGHT_RED, Color::RED, palette.foreground]);
+}
+
+#[test]
+fn search_colors_default_from_the_theme_and_stand_apart_from_selection() {
+    let theme = ThemeConfig::default();
+    let palette = Palette::from_theme(&theme);
+    assert_eq!(Color::from_hex(&theme.search_match), Some(palette.search_match));
+    assert_eq!(Color::from_hex(&theme.search_current_match), Some(palette.search_current_match));
+    assert_eq!(palette.search_match, Palette::default().search_match);
+    assert_ne!(palette.search_match, palette.search_current_match);
+    // The selection color is #rrggbbaa; compare its color part
+    let selection = Color::from_hex(&theme.selection[..7]).unwrap();
+    assert_ne!(palette.search_match, selection);
+    assert_ne!(palette.search_current_match, selection);
+}
+
+#[test]
+fn themes_without_search_colors_get_the_defaults() {
+    let theme: ThemeConfig = toml::from_str("name = \"Mine\"\nred = \"#102030\"").unwrap();
+    assert_eq!(theme.search_match, ThemeConfig::default().search_match);
+    let palette = Palette::from_theme(&ThemeConfig { search_match: "#010203".to_string(), ..theme });
+    assert_eq!(palette.search_match, Color { r: 1, g: 2, b: 3 });
+    assert_eq!(palette.search_current_match, Palette::default().search_current_match);
+}
@@ -0,0 +1,50 @@
+//! Line break handling and sanitizing of pasted text.
+
+use the_dev_terminal_core::terminal::{encode_paste, normalize_paste, sanitize_paste};
+
+#[test]
+fn crlf_and_lone_cr_become_newlines() {
+    assert_eq!(normalize_paste("a\r\nb\rc\nd", true), "a\nb\nc\nd");
+    assert_eq!(normalize_paste("a\r\nb\rc\nd", false), "a\nb\nc\nd");
+    // \r\n is one break, not two
+    assert_eq!(normalize_paste("a\r\n\r\nb", false), "a\n\nb");
+}
+
+#[test]
+fn trailing_newlines_are_dropped_only_without_bracketed_paste() {
+    assert_eq!(normalize_paste("make\n", false), "make");
+    assert_eq!(normalize_paste("make\r\n\r\n", false), "make");
+    assert_eq!(normalize_paste("make\n", true), "make\n");
+    assert_eq!(normalize_paste("\n", false), "");
+    // Leading and inner blank lines are kept
+    assert_eq!(normalize_paste("\nls\n\npwd\n", false), "\nls\n\npwd");
+}
+
+#[test]
+fn single_line_pastes_are_unchanged() {
+    assert_eq!(normalize_paste("echo hi", false), "echo hi");
+    assert_eq!(encode_paste("echo hi", false), b"echo hi");
+    assert_eq!(encode_paste("echo hi\r\n", true), b"\x1b[200~echo hi\n\x1b[201~");
+}
+
+#[test]
+fn embedded_end_marker_is_removed() {
+    assert_eq!(sanitize_paste("ls\x1b[201~rm -rf ~\n"), "lsrm -rf ~\n");
+    // The payload can't close the bracket early
+    assert_eq!(encode_paste("a\x1b[201~b", true), b"\x1b[200~ab\x1b[201~");
+}
+
+#[test]
+fn control_characters_are_stripped() {
+    assert_eq!(sanitize_paste("ding\x07 dong"), "ding dong");
+    assert_eq!(sanitize_paste("\x1b[31mred\x1b[m\x00"), "[31mred[m");
+    assert_eq!(encode_paste("a\x08\x7fb", false), b"ab");
+}
+
+#[test]
+fn tabs_and_line_breaks_are_kept() {
+    assert_eq!(sanitize_paste("a\tb\nc\r\nd"), "a\tb\nc\r\nd");
+    assert_eq!(encode_paste("a\tb\r\nc\n", false), b"a\tb\nc");
+    // Non-ASCII text is untouched
+    assert_eq!(sanitize_paste("caf\u{e9} \u{4e16}\u{754c}"), "caf\u{e9} \u{4e16}\u{754c}");
+}
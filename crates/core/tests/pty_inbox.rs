@@ -0,0 +1,28 @@
+//! PTY output batching: chunks queued while the UI is busy are parsed together.
+
+use the_dev_terminal_core::grid::Grid;
+use the_dev_terminal_core::pty::PtyInbox;
+use the_dev_terminal_core::Terminal;
+
+#[test]
+fn queued_chunks_coalesce_into_one_parse() {
+    let inbox = PtyInbox::new();
+    let reader = inbox.clone();
+    
+    // Only the first chunk of a batch has to wake the event loop
+    assert!(reader.push(b"\x1b[31mhel"));
+    assert!(!reader.push(b"lo\r"));
+    assert!(!reader.push(b"\nworld"));
+    
+    let batch = inbox.take();
+    assert_eq!(batch, b"\x1b[31mhello\r\nworld");
+    assert!(inbox.take().is_empty());
+    
+    // A sequence split across chunks parses as one
+    let mut term = Terminal::with_grid(Grid::new(20, 4, 100));
+    term.advance(&batch);
+    assert_eq!(term.grid.get_text_absolute((0, 0), (19, 1)), "hello\nworld");
+    
+    // Once taken, the next chunk starts a new batch
+    assert!(reader.push(b"!"));
+}
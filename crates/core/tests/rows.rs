@@ -0,0 +1,75 @@
+//! Row access by absolute row, the same for scrollback and screen rows.
+
+use the_dev_terminal_core::grid::{Cell, Grid};
+use the_dev_terminal_core::Terminal;
+
+fn text(cells: &[Cell]) -> String {
+    cells.iter().filter(|c| !c.is_wide_trailer()).map(|c| match c.ch() { '\0' => ' ', ch => ch }).collect()
+}
+
+// 6x2 terminal with "zero" .. "four" printed; three rows scrolled off
+fn scrolled() -> Grid {
+    let mut term = Terminal::with_grid(Grid::new(6, 2, 100));
+    term.advance(b"zero\r\none\r\ntwo\r\nthree\r\nfour");
+    assert_eq!(term.grid.history_len(), 3);
+    term.grid
+}
+
+#[test]
+fn rows_entirely_in_scrollback() {
+    let g = scrolled();
+    assert_eq!(g.row_text(0), "zero  ");
+    assert_eq!(g.row_text(2), "two   ");
+    assert_eq!(text(g.row_cells(1)), "one   ");
+    assert_eq!(g.row_cells(1)[0].ch(), 'o');
+}
+
+#[test]
+fn screen_rows_follow_scrollback() {
+    let g = scrolled();
+    assert_eq!(g.row_text(3), "three ");
+    assert_eq!(g.row_text(4), "four  ");
+    assert!(g.row_cells(5).is_empty());
+    assert_eq!(g.row_text(5), "");
+}
+
+#[test]
+fn visible_rows_cross_from_scrollback_to_screen() {
+    let g = scrolled();
+    let rows: Vec<String> = g.visible_rows(2, 3).map(text).collect();
+    assert_eq!(rows, ["two   ", "three ", "four  "]);
+    // Past the bottom the rows are empty
+    let rows: Vec<usize> = g.visible_rows(4, 3).map(<[Cell]>::len).collect();
+    assert_eq!(rows, [6, 0, 0]);
+}
+
+#[test]
+fn region_text_reads_scrollback_under_a_scrolled_viewport() {
+    let g = scrolled();
+    // Viewport scrolled up to absolute row 1: "one" then "two", both history
+    assert_eq!(g.get_text_in_absolute_region(1, 0, 0, 2, 0), "one");
+    assert_eq!(g.get_text_in_absolute_region(1, 0, 1, 2, 1), "two");
+    // The screen's own row 0 holds something else
+    assert_eq!(g.get_text_in_region(0, 0, 2, 0), "thr");
+    // A region straddling history and screen
+    assert_eq!(g.get_text_in_absolute_region(2, 1, 0, 4, 1), "wo  \nhree");
+}
+
+#[test]
+fn row_text_counts_wide_characters_once() {
+    let mut term = Terminal::with_grid(Grid::new(8, 2, 100));
+    term.advance("\u{4e16}a\x1b[1Cb\r\n\r\n".as_bytes());
+    // The trailer adds nothing and the skipped cell reads as a space
+    assert_eq!(term.grid.row_text(0), "\u{4e16}a b   ");
+    assert_eq!(term.grid.row_cells(0)[2].ch(), 'a');
+    assert!(term.grid.row_cells(0)[1].is_wide_trailer());
+}
+
+#[test]
+fn alternate_screen_has_no_scrollback_rows() {
+    let mut g = scrolled();
+    g.enter_alt_screen();
+    assert_eq!(g.history_len(), 0);
+    assert_eq!(g.row_text(0), "      ");
+    assert!(g.row_cells(2).is_empty());
+}
@@ -0,0 +1,343 @@
+//! Scripted scenarios replayed headlessly against the core `Terminal`.
+//!
+//! Each `tests/scenarios/<name>.scn` is a script of one command per line; the
+//! test harness plays the application on the other side of the PTY: `feed`
+//! is what the application prints, and everything the terminal sends back
+//! (query replies, pastes) collects until an `expect-sent` checks it.
+//!
+//! No PTY, child process, clock or window is involved: timing, rendering and
+//! the app's event loop (keybindings, scroll policy, resize handling) are out
+//! of reach here and are tested in their own modules. Scenarios with a real
+//! shell or a fake application on a PTY are in `apps/terminal/tests/scenarios.rs`.
+//!
+//!     # comment
+//!     size 80 24                  start a fresh terminal (default 80x24)
+//!     feed "text\e[31m"           application output
+//!     feed-file ls_color.bin      recorded output from tests/fixtures/vt
+//!     repeat 300 "line {i}\r\n"   feed a template with {i} = 0..n
+//!     resize 100 30               resize like a window resize does
+//!     c1-controls true            honor 8-bit C1 controls like general.c1_controls
+//!     scroll-up 5                 scroll the view back into scrollback
+//!     paste "a\nb"                user paste, bracketed if the app enabled it
+//!     expect-line 0 "text"        screen row text, trailing blanks trimmed
+//!     expect-view 0 "text"        row of the scrolled view, checked against its cells too
+//!     expect-view-cursor 2 3      cursor column and row in the scrolled view;
+//!                                 with no argument, scrolled out of view
+//!     expect-contains "text"      some screen row contains the text
+//!     expect-copy 0 0 79 1 "text" text of a selection from col,row to col,row
+//!     expect-copy-abs 0 2 9 4 "text"  the same with absolute rows (scrollback first)
+//!     expect-cursor 4 0           cursor column and row
+//!     expect-alt true             alternate screen shown or not
+//!     expect-style 0 28 "fg=#0dbc79 bold"   attributes of one cell
+//!     expect-sent "\e[0n"         bytes sent since the last expect-sent
+//!     expect-search "line 1" 111  scrollback search match count
+//!     expect-dirty-rows 0 2       screen rows changed since the last check
+//!     expect-scrollback 5         lines held in scrollback
+//!     expect-export "a\nb\n"       the whole history exported as text
+//!     expect-export-ansi "\e[1mb\e[0m\n"   the same with SGR sequences
+//!     expect-last-output "text"   output of the last finished command (OSC 133);
+//!                                 with no argument, that none has finished
+//!
+//! Strings take `\e \n \r \t \\ \" \xNN \u{N}` escapes. Adding coverage for a
+//! simple case is just a new `.scn` file.
+
+use std::path::{Path, PathBuf};
+use the_dev_terminal_core::grid::{Cell, CellColor, Grid};
+use the_dev_terminal_core::terminal::{encode_paste, Terminal};
+use the_dev_terminal_core::search::SearchQuery;
+use std::sync::atomic::Ordering;
+
+fn tests_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests")
+}
+
+fn unescape(s: &str) -> Result<Vec<u8>, String> {
+    let mut out = Vec::new();
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            let mut buf = [0; 4];
+            out.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+            continue;
+        }
+        match chars.next() {
+            Some('e') => out.push(0x1b),
+            Some('n') => out.push(b'\n'),
+            Some('r') => out.push(b'\r'),
+            Some('t') => out.push(b'\t'),
+            Some('\\') => out.push(b'\\'),
+            Some('"') => out.push(b'"'),
+            Some('x') => {
+                let hex: String = chars.by_ref().take(2).collect();
+                out.push(u8::from_str_radix(&hex, 16).map_err(|_| format!("bad \\x{hex}"))?);
+            }
+            Some('u') => {
+                let rest: String = chars.by_ref().take_while(|&c| c != '}').collect();
+                let hex = rest.strip_prefix('{').ok_or("expected \\u{...}")?;
+                let ch = u32::from_str_radix(hex, 16)
+                    .ok()
+                    .and_then(char::from_u32)
+                    .ok_or_else(|| format!("bad \\u{{{hex}}}"))?;
+                let mut buf = [0; 4];
+                out.extend_from_slice(ch.encode_utf8(&mut buf).as_bytes());
+            }
+            other => return Err(format!("unknown escape \\{}", other.map(String::from).unwrap_or_default())),
+        }
+    }
+    Ok(out)
+}
+
+// Split a line into words and "quoted strings" (quotes removed, escapes kept)
+fn tokenize(line: &str) -> Result<Vec<String>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = line.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '"' {
+            chars.next();
+            let mut s = String::new();
+            loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some('\\') => {
+                        s.push('\\');
+                        s.extend(chars.next());
+                    }
+                    Some(c) => s.push(c),
+                    None => return Err("unterminated string".into()),
+                }
+            }
+            tokens.push(s);
+        } else {
+            let mut s = String::new();
+            while let Some(&c) = chars.peek() {
+                if c.is_whitespace() {
+                    break;
+                }
+                s.push(c);
+                chars.next();
+            }
+            tokens.push(s);
+        }
+    }
+    Ok(tokens)
+}
+
+// Same labels as the VT snapshots, e.g. "fg=#cd3131 bold"
+fn attr_label(grid: &Grid, cell: &Cell) -> String {
+    let mut parts = Vec::new();
+    if cell.fg != CellColor::Default {
+        parts.push(format!("fg={}", grid.palette.fg(cell.fg).to_hex()));
+    }
+    if cell.bg != CellColor::Default {
+        parts.push(format!("bg={}", grid.palette.bg(cell.bg).to_hex()));
+    }
+    for (on, name) in [(cell.attrs().bold(), "bold"), (cell.attrs().italic(), "italic"), (cell.attrs().underline(), "underline")] {
+        if on {
+            parts.push(name.to_string());
+        }
+    }
+    parts.join(" ")
+}
+
+fn row_text(grid: &Grid, row: usize) -> String {
+    grid.get_text_in_region(0, row, grid.cols - 1, row).trim_end().to_string()
+}
+
+struct Scenario {
+    term: Terminal,
+    // Everything the terminal sent to the application since the last expect-sent
+    sent: Vec<u8>,
+}
+
+impl Scenario {
+    fn new(cols: usize, rows: usize) -> Self {
+        Self { term: Terminal::new(cols, rows), sent: Vec::new() }
+    }
+
+    fn feed(&mut self, bytes: &[u8]) {
+        self.term.advance(bytes);
+        self.sent.extend(self.term.take_replies());
+    }
+
+    fn run(&mut self, args: &[String]) -> Result<(), String> {
+        let arg = |i: usize| args.get(i).ok_or_else(|| format!("missing argument {i}"));
+        let num = |i: usize| -> Result<usize, String> {
+            arg(i)?.parse().map_err(|_| format!("not a number: {}", args[i]))
+        };
+        let grid = &self.term.grid;
+        match args[0].as_str() {
+            "size" => *self = Scenario::new(num(1)?, num(2)?),
+            "feed" => self.feed(&unescape(arg(1)?)?),
+            "feed-file" => {
+                let path = tests_dir().join("fixtures/vt").join(arg(1)?);
+                let bytes = std::fs::read(&path).map_err(|e| format!("{}: {e}", path.display()))?;
+                self.feed(&bytes);
+            }
+            "repeat" => {
+                let template = arg(2)?;
+                for i in 0..num(1)? {
+                    self.feed(&unescape(&template.replace("{i}", &i.to_string()))?);
+                }
+            }
+            "resize" => {
+                self.term.grid.resize_preserve(num(1)?, num(2)?);
+            }
+            "c1-controls" => self.term.set_c1_controls(arg(1)? == "true"),
+            "scroll-up" => self.term.grid.scroll_up(num(1)?),
+            "paste" => {
+                let text = String::from_utf8(unescape(arg(1)?)?).map_err(|e| e.to_string())?;
+                let bracketed = self.term.bracketed_paste().load(Ordering::Relaxed);
+                self.sent.extend(encode_paste(&text, bracketed));
+            }
+            "expect-line" => {
+                let row = num(1)?;
+                if row >= grid.rows {
+                    return Err(format!("row {row} is off the {}-row screen", grid.rows));
+                }
+                let want = String::from_utf8(unescape(arg(2)?)?).map_err(|e| e.to_string())?;
+                let got = row_text(grid, row);
+                if got != want {
+                    return Err(format!("row {row} is {got:?}, expected {want:?}"));
+                }
+            }
+            "expect-view" => {
+                let row = num(1)?;
+                let want = String::from_utf8(unescape(arg(2)?)?).map_err(|e| e.to_string())?;
+                let text = grid.get_display_content();
+                let from_cells = grid.snapshot_viewport(grid.view_top(), grid.rows).text();
+                if *text != *from_cells {
+                    return Err(format!("view text and cells disagree:\n{text}---\n{from_cells}"));
+                }
+                let got = text.lines().nth(row).ok_or("row off the view")?.trim_end();
+                if got != want {
+                    return Err(format!("view row {row} is {got:?}, expected {want:?}"));
+                }
+            }
+            "expect-view-cursor" => {
+                let want = match args.get(1) {
+                    Some(_) => Some((num(1)?, num(2)?)),
+                    None => None,
+                };
+                let got = grid.snapshot_viewport(grid.view_top(), grid.rows).cursor;
+                if got != want {
+                    return Err(format!("view cursor at {got:?}, expected {want:?}"));
+                }
+            }
+            "expect-contains" => {
+                let want = String::from_utf8(unescape(arg(1)?)?).map_err(|e| e.to_string())?;
+                if !(0..grid.rows).any(|r| row_text(grid, r).contains(&want)) {
+                    return Err(format!("no row contains {want:?}:\n{}", grid.to_string_lines()));
+                }
+            }
+            "expect-copy" | "expect-copy-abs" => {
+                let (start, end) = ((num(1)?, num(2)?), (num(3)?, num(4)?));
+                let got = if args[0] == "expect-copy" {
+                    grid.get_text_between(start, end)
+                } else {
+                    grid.get_text_absolute(start, end)
+                };
+                let want = String::from_utf8(unescape(arg(5)?)?).map_err(|e| e.to_string())?;
+                if got != want {
+                    return Err(format!("selection is {got:?}, expected {want:?}"));
+                }
+            }
+            "expect-cursor" => {
+                let want = (num(1)?, num(2)?);
+                if (grid.x(), grid.y()) != want {
+                    return Err(format!("cursor at {:?}, expected {want:?}", (grid.x(), grid.y())));
+                }
+            }
+            "expect-alt" => {
+                let want = arg(1)? == "true";
+                if grid.is_alt_screen() != want {
+                    return Err(format!("alt screen is {}, expected {want}", grid.is_alt_screen()));
+                }
+            }
+            "expect-style" => {
+                let (row, col) = (num(1)?, num(2)?);
+                let cell = grid.cells.get(row * grid.cols + col).ok_or("cell off screen")?;
+                let got = attr_label(grid, cell);
+                if got != *arg(3)? {
+                    return Err(format!("cell {row},{col} is {got:?}, expected {:?}", arg(3)?));
+                }
+            }
+            "expect-sent" => {
+                let want = unescape(arg(1)?)?;
+                let got = std::mem::take(&mut self.sent);
+                if got != want {
+                    return Err(format!("sent {:?}, expected {:?}", got.escape_ascii().to_string(), want.escape_ascii().to_string()));
+                }
+            }
+            "expect-search" => {
+                let query = SearchQuery::Plain { text: arg(1)?.to_string(), case_sensitive: true };
+                let got = grid.scrollback.search(&query).map_err(|e| e.to_string())?.len();
+                if got != num(2)? {
+                    return Err(format!("{got} scrollback matches for {:?}, expected {}", arg(1)?, args[2]));
+                }
+            }
+            "expect-dirty-rows" => {
+                let want = (1..args.len()).map(num).collect::<Result<Vec<_>, _>>()?;
+                let got = self.term.grid.take_dirty_rows();
+                if got != want {
+                    return Err(format!("dirty rows {got:?}, expected {want:?}"));
+                }
+            }
+            "expect-scrollback" => {
+                if grid.scrollback.len() != num(1)? {
+                    return Err(format!("{} scrollback lines, expected {}", grid.scrollback.len(), args[1]));
+                }
+            }
+            "expect-export" | "expect-export-ansi" => {
+                let want = String::from_utf8(unescape(arg(1)?)?).map_err(|e| e.to_string())?;
+                let got = if args[0] == "expect-export" { grid.export_text() } else { grid.export_ansi() };
+                if got != want {
+                    return Err(format!("exported {got:?}, expected {want:?}"));
+                }
+            }
+            "expect-last-output" => {
+                // No argument: no command has finished yet
+                let want = match args.get(1) {
+                    Some(a) => Some(String::from_utf8(unescape(a)?).map_err(|e| e.to_string())?),
+                    None => None,
+                };
+                let got = grid.last_command_output();
+                if got != want {
+                    return Err(format!("last command output {got:?}, expected {want:?}"));
+                }
+            }
+            other => return Err(format!("unknown command {other:?}")),
+        }
+        Ok(())
+    }
+}
+
+fn run_file(path: &Path) -> Result<(), String> {
+    let script = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let mut scenario = Scenario::new(80, 24);
+    for (n, line) in script.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        tokenize(line)
+            .and_then(|args| scenario.run(&args))
+            .map_err(|e| format!("{}:{}: {e}", path.display(), n + 1))?;
+    }
+    Ok(())
+}
+
+#[test]
+fn scenarios() {
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(tests_dir().join("scenarios"))
+        .expect("tests/scenarios")
+        .map(|e| e.expect("dir entry").path())
+        .filter(|p| p.extension().is_some_and(|e| e == "scn"))
+        .collect();
+    paths.sort();
+    assert!(!paths.is_empty(), "no scenarios found");
+
+    let failures: Vec<String> = paths.iter().filter_map(|p| run_file(p).err()).collect();
+    assert!(failures.is_empty(), "{} scenario(s) failed:\n{}", failures.len(), failures.join("\n"));
+}
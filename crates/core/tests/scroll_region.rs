@@ -0,0 +1,167 @@
+//! Scroll-region primitives: shifting a band of screen rows up or down.
+
+use the_dev_terminal_core::grid::{CellColor, Color, Grid};
+use the_dev_terminal_core::Terminal;
+
+const BLUE: CellColor = CellColor::Rgb(Color { r: 0, g: 0, b: 255 });
+
+// A 4x5 screen whose rows read "r0" through "r4"
+fn numbered() -> Grid {
+    let mut term = Terminal::with_grid(Grid::new(4, 5, 100));
+    term.advance(b"r0\r\nr1\r\nr2\r\nr3\r\nr4");
+    term.grid.take_dirty_rows();
+    term.grid
+}
+
+fn rows(g: &Grid) -> Vec<String> {
+    g.get_display_content().lines().map(|l| l.trim_end().to_string()).collect()
+}
+
+#[test]
+fn up_shifts_the_region_and_blanks_its_bottom() {
+    let mut g = numbered();
+    g.scroll_region_up(1, 3, 1, BLUE, false);
+    assert_eq!(rows(&g), ["r0", "r2", "r3", "", "r4"]);
+    assert_eq!(g.cells[3 * 4].bg, BLUE);
+    assert_ne!(g.cells[4 * 4 + 2].bg, BLUE);
+    assert_eq!(g.take_dirty_rows(), [1, 2, 3]);
+    assert_eq!(g.history_len(), 0);
+}
+
+#[test]
+fn down_shifts_the_region_and_blanks_its_top() {
+    let mut g = numbered();
+    g.scroll_region_down(1, 3, 2, BLUE);
+    assert_eq!(rows(&g), ["r0", "", "", "r1", "r4"]);
+    assert_eq!(g.cells[2 * 4].bg, BLUE);
+    assert_eq!(g.take_dirty_rows(), [1, 2, 3]);
+}
+
+#[test]
+fn count_saturates_at_the_region_height() {
+    let mut g = numbered();
+    g.scroll_region_up(1, 2, 99, BLUE, false);
+    assert_eq!(rows(&g), ["r0", "", "", "r3", "r4"]);
+    
+    let mut g = numbered();
+    g.scroll_region_down(2, 3, usize::MAX, BLUE);
+    assert_eq!(rows(&g), ["r0", "r1", "", "", "r4"]);
+}
+
+#[test]
+fn one_row_region_only_blanks_that_row() {
+    let mut g = numbered();
+    g.scroll_region_up(2, 2, 1, BLUE, false);
+    assert_eq!(rows(&g), ["r0", "r1", "", "r3", "r4"]);
+    
+    let mut g = numbered();
+    g.scroll_region_down(4, 4, 3, BLUE);
+    assert_eq!(rows(&g), ["r0", "r1", "r2", "r3", ""]);
+}
+
+#[test]
+fn zero_count_and_empty_region_are_no_ops() {
+    let mut g = numbered();
+    g.scroll_region_up(3, 1, 1, BLUE, true);
+    g.scroll_region_down(3, 1, 1, BLUE);
+    assert_eq!(rows(&g), ["r0", "r1", "r2", "r3", "r4"]);
+    assert!(!g.has_dirty_rows());
+    
+    g.scroll_region_up(0, 4, 0, BLUE, true);
+    assert_eq!(rows(&g), ["r0", "r1", "r2", "r3", "r4"]);
+    assert_eq!(g.history_len(), 0);
+}
+
+#[test]
+fn bottom_past_the_screen_is_clamped() {
+    let mut g = numbered();
+    g.scroll_region_down(3, 40, 1, BLUE);
+    assert_eq!(rows(&g), ["r0", "r1", "r2", "", "r3"]);
+}
+
+#[test]
+fn full_screen_region_feeds_scrollback_when_asked() {
+    let mut g = numbered();
+    g.scroll_region_up(0, 4, 2, BLUE, true);
+    assert_eq!(rows(&g), ["r2", "r3", "r4", "", ""]);
+    assert_eq!(g.history_len(), 2);
+    assert_eq!(g.get_text_absolute((0, 0), (3, 1)), "r0\nr1");
+    
+    let mut g = numbered();
+    g.scroll_region_up(0, 4, 2, BLUE, false);
+    assert_eq!(rows(&g), ["r2", "r3", "r4", "", ""]);
+    assert_eq!(g.history_len(), 0);
+}
+
+#[test]
+fn wrapped_flags_move_with_their_rows() {
+    let mut term = Terminal::with_grid(Grid::new(4, 4, 100));
+    // "abcdef" soft-wraps from row 0 into row 1
+    term.advance(b"abcdef");
+    term.grid.scroll_region_down(0, 3, 1, BLUE);
+    term.grid.scroll_region_up(0, 3, 1, BLUE, true);
+    assert_eq!(term.grid.history_len(), 1);
+    // The wrapped pair still joins into one logical line across scrollback and screen
+    term.grid.scroll_region_up(0, 3, 1, BLUE, true);
+    assert_eq!(term.grid.history_len(), 2);
+    assert_eq!(term.grid.get_text_absolute((0, 1), (3, 2)), "abcdef");
+}
+
+#[test]
+fn cnl_and_cpl_go_to_column_zero_and_clamp() {
+    let mut term = Terminal::with_grid(numbered());
+    term.advance(b"\x1b[1;3H\x1b[2E");
+    assert_eq!((term.grid.x(), term.grid.y()), (0, 2));
+    term.advance(b"\x1b[3G\x1b[9E");
+    assert_eq!((term.grid.x(), term.grid.y()), (0, 4));
+    term.advance(b"\x1b[3G\x1b[F");
+    assert_eq!((term.grid.x(), term.grid.y()), (0, 3));
+    term.advance(b"\x1b[3G\x1b[9F");
+    assert_eq!((term.grid.x(), term.grid.y()), (0, 0));
+    assert_eq!(rows(&term.grid), ["r0", "r1", "r2", "r3", "r4"]);
+}
+
+#[test]
+fn ri_moves_up_and_scrolls_at_the_top() {
+    let mut term = Terminal::with_grid(numbered());
+    term.advance(b"\x1b[3;2H\x1bM");
+    assert_eq!((term.grid.x(), term.grid.y()), (1, 1));
+    term.advance(b"\x1bM\x1bM");
+    assert_eq!(term.grid.y(), 0);
+    assert_eq!(rows(&term.grid), ["", "r0", "r1", "r2", "r3"]);
+}
+
+#[test]
+fn ri_and_ind_scroll_inside_decstbm_margins() {
+    let mut term = Terminal::with_grid(numbered());
+    // Rows 2-4 (1-based) scroll; DECSTBM homes the cursor
+    term.advance(b"\x1b[2;4r");
+    assert_eq!((term.grid.x(), term.grid.y()), (0, 0));
+    term.advance(b"\x1b[2;1H\x1bM");
+    assert_eq!(term.grid.y(), 1);
+    assert_eq!(rows(&term.grid), ["r0", "", "r1", "r2", "r4"]);
+
+    term.advance(b"\x1b[4;1H\x1bD\x1bD");
+    assert_eq!(term.grid.y(), 3);
+    assert_eq!(rows(&term.grid), ["r0", "r2", "", "", "r4"]);
+    // Only a full-screen scroll feeds the history
+    assert_eq!(term.grid.history_len(), 0);
+
+    // Below the region a line feed still moves down to the last row
+    term.advance(b"\x1b[5;1H\n");
+    assert_eq!(term.grid.y(), 4);
+    assert_eq!(rows(&term.grid), ["r0", "r2", "", "", "r4"]);
+
+    // CSI r with no parameters restores the full screen
+    term.advance(b"\x1b[r\x1b[5;1H\n");
+    assert_eq!(rows(&term.grid), ["r2", "", "", "r4", ""]);
+    assert_eq!(term.grid.history_len(), 1);
+}
+
+#[test]
+fn invalid_margins_are_ignored() {
+    let mut term = Terminal::with_grid(numbered());
+    term.advance(b"\x1b[4;2r\x1b[1;1H\x1bM");
+    assert_eq!(rows(&term.grid), ["", "r0", "r1", "r2", "r3"]);
+    assert_eq!(term.grid.scroll_margins(), (0, 4));
+}
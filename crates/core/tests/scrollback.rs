@@ -0,0 +1,224 @@
+//! Scrollback addressed by absolute line: 0 is the oldest line kept.
+
+use the_dev_terminal_core::grid::Cell;
+use the_dev_terminal_core::scrollback::ScrollbackBuffer;
+
+fn text(line: &[Cell]) -> String {
+    let mut s = String::new();
+    for cell in line {
+        cell.push_text(&mut s);
+    }
+    s
+}
+
+// Lines "0" .. "99"
+fn hundred() -> ScrollbackBuffer {
+    let mut sb = ScrollbackBuffer::new(1000);
+    for i in 0..100 {
+        let line = i.to_string().chars().map(|ch| Cell::new(ch, Default::default())).collect();
+        sb.push_line(line, false);
+    }
+    sb
+}
+
+fn visible(sb: &ScrollbackBuffer) -> Vec<String> {
+    sb.get_visible_lines(10).map(text).collect()
+}
+
+fn numbers(range: std::ops::Range<usize>) -> Vec<String> {
+    range.map(|i| i.to_string()).collect()
+}
+
+#[test]
+fn line_and_range_use_absolute_indexes() {
+    let sb = hundred();
+    assert_eq!(sb.line(0).map(text).as_deref(), Some("0"));
+    assert_eq!(sb.line(99).map(text).as_deref(), Some("99"));
+    assert!(sb.line(100).is_none());
+    assert_eq!(sb.range(45..48).map(text).collect::<Vec<_>>(), numbers(45..48));
+    // Past the newest line is left out
+    assert_eq!(sb.range(98..120).map(text).collect::<Vec<_>>(), numbers(98..100));
+    assert_eq!(sb.range(150..160).count(), 0);
+}
+
+#[test]
+fn viewport_at_bottom_shows_no_history() {
+    let mut sb = hundred();
+    assert!(visible(&sb).is_empty());
+    // One line up, the newest history line tops the window
+    sb.scroll_up(1);
+    assert_eq!(visible(&sb), numbers(99..100));
+    sb.page_up(10);
+    assert_eq!(visible(&sb), numbers(89..99));
+}
+
+#[test]
+fn viewport_in_middle_starts_scroll_offset_above_newest() {
+    let mut sb = hundred();
+    sb.scroll_up(55);
+    assert_eq!(visible(&sb), numbers(45..55));
+}
+
+#[test]
+fn viewport_at_top_reaches_first_lines() {
+    let mut sb = hundred();
+    sb.scroll_to_top();
+    assert_eq!(visible(&sb), numbers(0..10));
+    // Every step down shows a different window
+    sb.scroll_down(1);
+    assert_eq!(visible(&sb), numbers(1..11));
+    sb.page_down(10);
+    assert_eq!(visible(&sb), numbers(11..21));
+}
+
+#[test]
+fn viewport_matches_grid_view_top() {
+    use the_dev_terminal_core::grid::Grid;
+    use the_dev_terminal_core::Terminal;
+    let mut term = Terminal::with_grid(Grid::new(4, 10, 1000));
+    for i in 0..110 {
+        term.advance(format!("{}\r\n", i).as_bytes());
+    }
+    let g = &mut term.grid;
+    g.scrollback.scroll_up(30);
+    let top = g.view_top();
+    let history: Vec<&[Cell]> = g.scrollback.get_visible_lines(g.rows).collect();
+    assert_eq!(history.len(), 10);
+    for (i, line) in history.into_iter().enumerate() {
+        assert_eq!(text(line), text(g.row_cells(top + i)));
+    }
+}
+
+#[test]
+fn evictions_are_counted_so_absolute_rows_can_follow_their_text() {
+    use the_dev_terminal_core::grid::Grid;
+    use the_dev_terminal_core::Terminal;
+    let mut term = Terminal::with_grid(Grid::new(8, 3, 5));
+    for i in 0..8 {
+        term.advance(format!("line {}\r\n", i).as_bytes());
+    }
+    assert_eq!(term.grid.scrollback.evicted(), 1);
+    // A selection anchored on "line 4" while output keeps arriving
+    let row = (0..term.grid.scrollable_lines()).find(|&r| term.grid.row_text(r).starts_with("line 4")).unwrap();
+    let before = term.grid.scrollback.evicted();
+    term.advance(b"more\r\nand more\r\n");
+    let shifted = term.grid.scrollback.evicted() - before;
+    assert_eq!(shifted, 2);
+    assert!(term.grid.row_text(row - shifted).starts_with("line 4"));
+    
+    // Clearing drops every line at once
+    let len = term.grid.scrollback.len();
+    let before = term.grid.scrollback.evicted();
+    term.grid.scrollback.clear();
+    assert_eq!(term.grid.scrollback.evicted() - before, len);
+}
+
+fn blank_line(cols: usize) -> Vec<Cell> {
+    vec![Cell::new('x', Default::default()); cols]
+}
+
+#[test]
+fn byte_cap_evicts_wide_lines_sooner_than_narrow_ones() {
+    let per_cell = std::mem::size_of::<Cell>();
+    let header = std::mem::size_of::<Vec<Cell>>();
+    // Room for ten 80-column lines
+    let cap = 10 * (80 * per_cell + header);
+    let mut sb = ScrollbackBuffer::new(1000);
+    sb.set_max_bytes(cap);
+    for _ in 0..10 {
+        assert_eq!(sb.push_line(blank_line(80), false), 0);
+    }
+    assert_eq!(sb.bytes(), cap);
+    
+    // One 400-column line takes the room of five narrow ones
+    assert_eq!(sb.push_line(blank_line(400), false), 5);
+    assert_eq!(sb.len(), 6);
+    assert!(sb.bytes() <= cap);
+    // A mix of widths never goes over either cap
+    for cols in [10, 400, 80, 200, 1, 400, 33] {
+        sb.push_line(blank_line(cols), false);
+        assert!(sb.bytes() <= cap);
+        assert_eq!(sb.bytes(), sb.iter_lines().map(|l| std::mem::size_of_val(l) + header).sum::<usize>());
+    }
+}
+
+#[test]
+fn line_cap_holds_while_the_byte_cap_has_room() {
+    let mut sb = ScrollbackBuffer::new(4);
+    sb.set_max_bytes(1 << 20);
+    for cols in [1, 2, 3, 4, 5, 6] {
+        sb.push_line(blank_line(cols), false);
+    }
+    assert_eq!(sb.len(), 4);
+    assert_eq!(sb.iter_lines().map(<[Cell]>::len).collect::<Vec<_>>(), [3, 4, 5, 6]);
+    
+    // Lowering the byte cap trims what is already stored
+    let newest = std::mem::size_of_val(sb.line(3).unwrap()) + std::mem::size_of::<Vec<Cell>>();
+    sb.set_max_bytes(newest);
+    assert_eq!(sb.len(), 1);
+    assert_eq!(sb.bytes(), newest);
+}
+
+#[test]
+fn heap_usage_reports_scrollback_bytes() {
+    use the_dev_terminal_core::grid::Grid;
+    use the_dev_terminal_core::perf::PerfMonitor;
+    use the_dev_terminal_core::Terminal;
+    let mut term = Terminal::with_grid(Grid::new(400, 2, 100));
+    term.grid.scrollback.set_max_bytes(10_000);
+    for _ in 0..20 {
+        term.advance(b"wide\r\n");
+    }
+    let bytes = term.grid.scrollback.bytes();
+    assert!(bytes > 0 && bytes <= 10_000);
+    let mut perf = PerfMonitor::new();
+    perf.set_enabled(true);
+    perf.record_heap(term.grid.heap_usage());
+    assert_eq!(perf.get_stats().heap.scrollback, bytes);
+}
+
+#[test]
+fn zero_lines_keeps_no_history() {
+    let mut sb = ScrollbackBuffer::new(0);
+    assert_eq!(sb.push_line(blank_line(10), false), 1);
+    assert_eq!(sb.push_line(blank_line(10), true), 1);
+    assert!(sb.is_empty());
+    assert_eq!((sb.bytes(), sb.evicted()), (0, 2));
+    
+    // A grid without scrollback still scrolls its screen
+    use the_dev_terminal_core::grid::Grid;
+    use the_dev_terminal_core::Terminal;
+    let mut term = Terminal::with_grid(Grid::new(8, 2, 0));
+    term.advance(b"one\r\ntwo\r\nthree");
+    assert_eq!(term.grid.history_len(), 0);
+    assert_eq!(term.grid.export_text(), "two\nthree\n");
+}
+
+#[test]
+fn huge_line_caps_leave_the_byte_cap_in_charge() {
+    let mut sb = ScrollbackBuffer::new(usize::MAX);
+    for _ in 0..5000 {
+        assert_eq!(sb.push_line(blank_line(4), false), 0);
+    }
+    assert_eq!(sb.len(), 5000);
+    sb.set_max_bytes(sb.bytes() / 2);
+    assert_eq!(sb.len(), 2500);
+}
+
+#[test]
+fn lowering_the_line_cap_trims_the_oldest() {
+    let mut sb = hundred();
+    sb.set_max_lines(30);
+    assert_eq!(sb.len(), 30);
+    assert_eq!(sb.evicted(), 70);
+    assert_eq!(text(sb.line(0).unwrap()), "70");
+    sb.push_line(vec![Cell::new('x', Default::default())], false);
+    assert_eq!((sb.len(), text(sb.line(0).unwrap())), (30, "71".to_string()));
+    
+    // Raising it keeps what is there and lets history grow again
+    sb.set_max_lines(1000);
+    sb.push_line(Vec::new(), false);
+    assert_eq!(sb.len(), 31);
+    sb.set_max_lines(0);
+    assert!(sb.is_empty());
+}
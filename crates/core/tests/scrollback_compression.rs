@@ -0,0 +1,91 @@
+//! Lines older than the hot window are stored without their blank tail.
+
+use the_dev_terminal_core::grid::{Cell, Grid};
+use the_dev_terminal_core::scrollback::{ScrollbackBuffer, HOT_LINES};
+use the_dev_terminal_core::search::SearchQuery;
+use the_dev_terminal_core::Terminal;
+
+// Rows with colors, a colored blank tail, wide characters and nothing at all
+fn styled_rows() -> Vec<Vec<Cell>> {
+    let mut term = Terminal::with_grid(Grid::new(20, 6, 100));
+    term.advance("\x1b[1;31mred\x1b[m plain\r\n".as_bytes());
+    term.advance("\x1b[44mblue    \x1b[m\r\n".as_bytes());
+    term.advance("\u{4e16}\u{754c} wide\r\n".as_bytes());
+    term.advance("e\u{301} and \u{1f600}\r\n".as_bytes());
+    term.advance(b"\r\n");
+    (0..5).map(|r| term.grid.row_cells(r).to_vec()).collect()
+}
+
+#[test]
+fn compressed_lines_round_trip() {
+    let rows = styled_rows();
+    let mut sb = ScrollbackBuffer::new(10_000);
+    for row in &rows {
+        sb.push_line(row.clone(), false);
+    }
+    for _ in 0..HOT_LINES {
+        sb.push_line(vec![Cell::default(); 20], false);
+    }
+
+    for (i, row) in rows.iter().enumerate() {
+        let stored = sb.line(i).unwrap();
+        // Only default cells were dropped, from the end
+        assert!(stored.len() < row.len());
+        assert!(row[..stored.len()] == *stored);
+        assert!(row[stored.len()..].iter().all(|c| *c == Cell::default()));
+    }
+    // Blanks with a color are kept and the empty line keeps nothing
+    assert_eq!(sb.line(1).unwrap().len(), 8);
+    assert!(sb.line(4).unwrap().is_empty());
+    assert!(sb.bytes() < sb.uncompressed_bytes());
+
+    // Taken back out, lines are as wide as they were pushed
+    let popped = sb.pop_lines(sb.len());
+    assert_eq!(popped.len(), rows.len() + HOT_LINES);
+    for (row, (line, _)) in rows.iter().zip(&popped) {
+        assert!(row == line);
+    }
+    assert_eq!(sb.bytes(), 0);
+}
+
+#[test]
+fn hot_lines_are_kept_whole() {
+    let mut sb = ScrollbackBuffer::new(10_000);
+    for _ in 0..HOT_LINES + 10 {
+        sb.push_line(vec![Cell::default(); 80], false);
+    }
+    assert!((0..10).all(|i| sb.line(i).unwrap().is_empty()));
+    assert!((10..HOT_LINES + 10).all(|i| sb.line(i).unwrap().len() == 80));
+}
+
+#[test]
+fn compressed_history_still_reads_searches_and_selects() {
+    let mut term = Terminal::with_grid(Grid::new(40, 5, 10_000));
+    for i in 0..HOT_LINES + 100 {
+        term.advance(format!("line {}\r\n", i).as_bytes());
+        if i == 3 {
+            term.advance(b"\r\n");
+        }
+    }
+    let g = &term.grid;
+    assert_eq!(g.row_text(0), "line 0");
+    assert!(g.get_text_absolute((0, 3), (39, 5)).starts_with("line 3\n\nline 4"));
+    let found = g.search(&SearchQuery::Plain { text: "line 2".into(), case_sensitive: true }).unwrap();
+    assert!(found.iter().any(|m| m.start == (0, 2)));
+    assert!(g.export_text().starts_with("line 0\nline 1\nline 2\nline 3\n\nline 4\n"));
+}
+
+#[test]
+fn cargo_build_log_savings() {
+    let log = include_str!("fixtures/cargo_build.log").replace('\n', "\r\n");
+    let mut term = Terminal::with_grid(Grid::new(120, 40, 10_000));
+    // Twenty builds' worth, so most of the history is past the hot window
+    for _ in 0..20 {
+        term.advance(log.as_bytes());
+    }
+    let sb = &term.grid.scrollback;
+    assert!(sb.len() > HOT_LINES * 8);
+    let (bytes, whole) = (sb.bytes(), sb.uncompressed_bytes());
+    assert!(bytes * 2 < whole, "{bytes} of {whole}");
+    assert!(term.grid.row_text(0).contains("Compiling"));
+}
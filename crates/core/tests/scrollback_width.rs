@@ -0,0 +1,79 @@
+//! Scrollback lines keep the width they were pushed with; readers get
+//! them at the current width.
+
+use the_dev_terminal_core::grid::{Cell, Grid};
+use the_dev_terminal_core::scrollback::ScrollbackBuffer;
+use the_dev_terminal_core::Terminal;
+
+fn line(text: &str, cols: usize) -> Vec<Cell> {
+    let mut cells: Vec<Cell> = text.chars().map(|ch| Cell::new(ch, Default::default())).collect();
+    cells.resize(cols, Cell::default());
+    cells
+}
+
+fn rows_text(rows: &[(Vec<Cell>, bool)]) -> Vec<(String, bool)> {
+    rows.iter()
+        .map(|(cells, wrapped)| {
+            let mut s = String::new();
+            for cell in cells {
+                cell.push_text(&mut s);
+            }
+            (s, *wrapped)
+        })
+        .collect()
+}
+
+fn history() -> ScrollbackBuffer {
+    let mut sb = ScrollbackBuffer::new(100);
+    sb.push_line(line("abcdef", 6), true);
+    sb.push_line(line("gh", 6), false);
+    sb.push_line(line("xy", 6), false);
+    sb
+}
+
+#[test]
+fn narrower_width_splits_logical_lines() {
+    let rows = history().lines_rewrapped(4);
+    assert!(rows.iter().all(|(cells, _)| cells.len() == 4));
+    assert_eq!(rows_text(&rows), [
+        ("abcd".to_string(), true),
+        ("efgh".to_string(), false),
+        ("xy  ".to_string(), false),
+    ]);
+}
+
+#[test]
+fn wider_width_joins_and_pads() {
+    let rows = history().lines_rewrapped(10);
+    assert_eq!(rows_text(&rows), [
+        ("abcdefgh  ".to_string(), false),
+        ("xy        ".to_string(), false),
+    ]);
+}
+
+#[test]
+fn wide_characters_are_not_split() {
+    let mut sb = ScrollbackBuffer::new(10);
+    let wide = Cell::new('漢', Default::default());
+    let mut cells = vec![Cell::new('a', Default::default()), wide, wide.trailer()];
+    cells.resize(4, Cell::default());
+    sb.push_line(cells, false);
+    let rows = sb.lines_rewrapped(2);
+    assert_eq!(rows_text(&rows), [("a ".to_string(), true), ("漢".to_string(), false)]);
+    assert!(rows[1].0[1].is_wide_trailer());
+}
+
+#[test]
+fn old_lines_stay_readable_after_a_resize_without_reflow() {
+    let mut term = Terminal::with_grid(Grid::new(8, 2, 100));
+    term.advance(b"12345678\r\nshort\r\nlast\r\nnow");
+    let g = &mut term.grid;
+    g.resize_preserve(4, 2);
+    g.resize_preserve(12, 3);
+    // Scrollback now holds lines narrower than the 12-wide screen
+    assert!(g.scrollback.iter_lines().all(|line| line.len() < 12));
+    assert_eq!(g.viewport_cells(0, 3).len(), 12 * 3);
+    assert_eq!(g.get_text_absolute((0, 0), (11, 0)), "12345678");
+    assert_eq!(g.get_text_in_absolute_region(0, 2, 0, 11, 0), "345678");
+    assert!(g.scrollback.lines_rewrapped(g.cols).iter().all(|(cells, _)| cells.len() == 12));
+}
@@ -0,0 +1,156 @@
+//! Searching the screen and scrollback by substring or regex.
+
+use the_dev_terminal_core::grid::{Cell, Grid};
+use the_dev_terminal_core::search::{SearchMatch, SearchProgress, SearchQuery, SearchSession, SEARCH_CHUNK_ROWS};
+use the_dev_terminal_core::Terminal;
+
+fn term(cols: usize, rows: usize, text: &str) -> Terminal {
+    let mut term = Terminal::with_grid(Grid::new(cols, rows, 100));
+    term.advance(text.as_bytes());
+    term
+}
+
+fn plain(text: &str, case_sensitive: bool) -> SearchQuery {
+    SearchQuery::Plain { text: text.to_string(), case_sensitive }
+}
+
+fn regex(pattern: &str) -> SearchQuery {
+    SearchQuery::Regex(pattern.to_string())
+}
+
+fn span(start: (usize, usize), end: (usize, usize)) -> SearchMatch {
+    SearchMatch { start, end }
+}
+
+#[test]
+fn plain_search_is_literal_and_optionally_case_insensitive() {
+    let t = term(20, 3, "Error: a.b\r\nerror: axb");
+    assert_eq!(t.grid.search(&plain("error", true)).unwrap(), [span((0, 1), (4, 1))]);
+    assert_eq!(t.grid.search(&plain("ERROR", false)).unwrap().len(), 2);
+    // Regex syntax in plain text is just text
+    assert_eq!(t.grid.search(&plain("a.b", true)).unwrap(), [span((7, 0), (9, 0))]);
+}
+
+#[test]
+fn regex_search_finds_timestamps_and_error_codes() {
+    let t = term(30, 3, "12:03:44 build E0308\r\nok\r\n09:15:00 E0425 again");
+    let times = t.grid.search(&regex(r"\d\d:\d\d:\d\d")).unwrap();
+    assert_eq!(times, [span((0, 0), (7, 0)), span((0, 2), (7, 2))]);
+    let codes = t.grid.search(&regex(r"E\d{4}")).unwrap();
+    assert_eq!(codes, [span((15, 0), (19, 0)), span((9, 2), (13, 2))]);
+}
+
+#[test]
+fn invalid_regex_is_an_error_not_a_panic() {
+    let t = term(10, 2, "x");
+    let err = t.grid.search(&regex("(unclosed")).unwrap_err();
+    assert!(!err.to_string().is_empty());
+    assert!(regex("[z-a]").compile().is_err());
+}
+
+#[test]
+fn matches_run_across_wrapped_rows() {
+    // "hello world" wraps after "hello " on a 6-column grid
+    let t = term(6, 3, "hello world");
+    assert_eq!(t.grid.search(&plain("lo wo", true)).unwrap(), [span((3, 0), (1, 1))]);
+}
+
+#[test]
+fn wide_and_combining_characters_map_to_their_cells() {
+    // 漢 takes columns 1-2; e + U+0301 is one cell at column 4
+    let t = term(12, 2, "a漢be\u{301}x");
+    assert_eq!(t.grid.search(&plain("b", true)).unwrap(), [span((3, 0), (3, 0))]);
+    assert_eq!(t.grid.search(&plain("漢b", true)).unwrap(), [span((1, 0), (3, 0))]);
+    assert_eq!(t.grid.search(&regex("e\u{301}x")).unwrap(), [span((4, 0), (5, 0))]);
+    // A match inside a combined cell covers that cell
+    assert_eq!(t.grid.search(&regex("\u{301}")).unwrap(), [span((4, 0), (4, 0))]);
+}
+
+#[test]
+fn rows_are_absolute_with_scrollback_first() {
+    let t = term(10, 2, "needle 0\r\nhay\r\nneedle 2\r\nneedle 3");
+    assert_eq!(t.grid.history_len(), 2);
+    let rows: Vec<usize> = t.grid.search(&plain("needle", true)).unwrap().iter().map(|m| m.start.1).collect();
+    assert_eq!(rows, [0, 2, 3]);
+    // The scrollback on its own
+    assert_eq!(t.grid.scrollback.search(&plain("needle", true)).unwrap(), [span((0, 0), (5, 0))]);
+}
+
+// Grid with `lines` lines of history, "line N" plus a marker on every 1000th
+fn big_history(lines: usize) -> Grid {
+    let mut grid = Grid::new(40, 24, lines);
+    for i in 0..lines {
+        let text = if i % 1000 == 0 { format!("line {i} MARK") } else { format!("line {i}") };
+        let mut cells: Vec<Cell> = text.chars().map(|ch| Cell::new(ch, Default::default())).collect();
+        cells.resize(40, Cell::default());
+        grid.scrollback.push_line(cells, false);
+    }
+    grid
+}
+
+// Poll to the end; returns how many polls it took
+fn run(session: &mut SearchSession, grid: &Grid) -> usize {
+    let mut polls = 1;
+    while let SearchProgress::Searching { searched, total } = session.poll(grid) {
+        assert!(searched <= polls * SEARCH_CHUNK_ROWS && searched < total);
+        polls += 1;
+    }
+    polls
+}
+
+#[test]
+fn incremental_search_over_200k_lines_works_in_chunks() {
+    let grid = big_history(200_000);
+    let total = grid.scrollable_lines();
+    let mut session = SearchSession::new(&plain("mark", false), &grid).unwrap();
+    let polls = run(&mut session, &grid);
+    assert_eq!(polls, total.div_ceil(SEARCH_CHUNK_ROWS));
+    assert_eq!(session.matches().len(), 200);
+    // Newest first
+    assert_eq!(session.matches()[0], span((12, 199_000), (15, 199_000)));
+    assert_eq!(session.matches()[199].start, (7, 0));
+    
+    // A new query cancels the search and reuses the cached scrollback text
+    session.set_query(&regex(r"line 1999\d\d\b"), &grid).unwrap();
+    assert!(session.matches().is_empty() && !session.is_done());
+    run(&mut session, &grid);
+    assert_eq!(session.matches().len(), 100);
+    assert!(session.set_query(&regex("("), &grid).is_err());
+    assert!(session.is_done());
+}
+
+#[test]
+fn first_poll_finds_the_newest_matches() {
+    let grid = big_history(10_000);
+    let mut session = SearchSession::new(&plain("MARK", true), &grid).unwrap();
+    let progress = session.poll(&grid);
+    assert!(matches!(progress, SearchProgress::Searching { total, .. } if total == 10_024));
+    let rows: Vec<usize> = session.matches().iter().map(|m| m.start.1).collect();
+    // The first chunk is the newest 2000 rows, 8024 and up
+    assert_eq!(rows, [9000]);
+}
+
+#[test]
+fn matches_follow_evicted_scrollback() {
+    let mut term = Terminal::with_grid(Grid::new(20, 2, 5));
+    term.advance(b"a hit\r\nb\r\nc hit\r\nd\r\ne");
+    let mut session = SearchSession::new(&plain("hit", true), &term.grid).unwrap();
+    run(&mut session, &term.grid);
+    assert_eq!(session.matches().iter().map(|m| m.start.1).collect::<Vec<_>>(), [2, 0]);
+    // Four more lines push the two oldest out of a full history
+    term.advance(b"\r\nf\r\ng\r\nh\r\ni");
+    assert_eq!(session.poll(&term.grid), SearchProgress::Done);
+    assert_eq!(session.matches().iter().map(|m| m.start.1).collect::<Vec<_>>(), [0]);
+    assert!(term.grid.row_text(0).starts_with("c hit"));
+}
+
+#[test]
+fn incremental_and_whole_searches_agree() {
+    let t = term(6, 3, "one hit\r\n漢hit\r\nhit hit hit\r\nlast");
+    let query = plain("hit", true);
+    let mut session = SearchSession::new(&query, &t.grid).unwrap();
+    run(&mut session, &t.grid);
+    let mut newest_first = t.grid.search(&query).unwrap();
+    newest_first.reverse();
+    assert_eq!(session.matches(), &newest_first[..]);
+}
@@ -0,0 +1,88 @@
+//! Session dumps: a grid saved to a file and loaded back is the same grid.
+
+use std::path::PathBuf;
+use the_dev_terminal_core::grid::{CellColor, Color, Grid};
+use the_dev_terminal_core::Terminal;
+
+// A dump file unique to this test
+fn dump_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("tdt-session-{}-{}.bin", std::process::id(), name))
+}
+
+fn round_trip(grid: &Grid, name: &str) -> (Grid, u64) {
+    let path = dump_path(name);
+    grid.save_to(&path).unwrap();
+    let size = std::fs::metadata(&path).unwrap().len();
+    let loaded = Grid::load_from(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    (loaded, size)
+}
+
+#[test]
+fn round_trip_keeps_text_colors_and_cursor() {
+    let mut term = Terminal::with_grid(Grid::new(12, 4, 100));
+    term.advance(b"\x1b]133;A\x07$ ls\r\n");
+    term.advance("\x1b[1;31mred\x1b[m \u{4e16}\u{754c} e\u{301}\r\n".as_bytes());
+    term.advance(b"\x1b[38;2;1;2;3;48;5;200mrgb\x1b[m abcdefghijkl");
+    term.advance(b"\r\nmore\r\nlines");
+    assert!(term.grid.history_len() > 0);
+    
+    let (mut loaded, _) = round_trip(&term.grid, "text");
+    let g = &term.grid;
+    assert_eq!((loaded.cols, loaded.rows), (g.cols, g.rows));
+    assert_eq!(loaded.history_len(), g.history_len());
+    assert_eq!(loaded.export_text(), g.export_text());
+    assert_eq!(loaded.export_ansi(), g.export_ansi());
+    assert_eq!((loaded.x(), loaded.y()), (g.x(), g.y()));
+    assert_eq!(loaded.marks.iter().collect::<Vec<_>>(), g.marks.iter().collect::<Vec<_>>());
+    assert_eq!(loaded.marks.iter().count(), 1);
+    assert!(loaded.cells == g.cells);
+    
+    // Wide characters keep their trailers and combining marks their text
+    let text = loaded.export_text();
+    assert!(text.contains("\u{4e16}\u{754c} e\u{301}"), "{text:?}");
+    let red = loaded.abs_row(1).unwrap().0[0];
+    assert_eq!((red.ch(), red.fg, red.attrs().bold()), ('r', CellColor::Indexed(1), true));
+    let rgb = loaded.abs_row(2).unwrap().0[0].attrs();
+    assert_eq!((rgb.fg, rgb.bg), (CellColor::Rgb(Color { r: 1, g: 2, b: 3 }), CellColor::Indexed(200)));
+    
+    // A loaded grid is drawn from scratch and keeps working
+    assert_eq!(loaded.take_dirty_rows().len(), loaded.rows);
+    let mut term = Terminal::with_grid(loaded);
+    term.advance(b"!");
+    assert!(term.grid.export_text().ends_with("lines!\n"));
+}
+
+#[test]
+fn soft_wraps_and_alternate_screen_survive() {
+    let mut term = Terminal::with_grid(Grid::new(4, 3, 100));
+    term.advance(b"abcdefgh\r\nx\r\ny\r\nz");
+    term.advance(b"\x1b[?1049hFULL");
+    
+    let (loaded, _) = round_trip(&term.grid, "alt");
+    assert!(loaded.is_alt_screen());
+    let mut term = Terminal::with_grid(loaded);
+    term.advance(b"\x1b[?1049l");
+    assert_eq!(term.grid.export_text(), "abcdefgh\nx\ny\nz\n");
+}
+
+#[test]
+fn blank_scrollback_stays_small() {
+    let mut term = Terminal::with_grid(Grid::new(200, 50, 10_000));
+    for i in 0..10_049 {
+        term.advance(format!("{}\r\n", i).as_bytes());
+    }
+    assert_eq!(term.grid.history_len(), 10_000);
+    let (loaded, size) = round_trip(&term.grid, "big");
+    assert_eq!(loaded.export_text(), term.grid.export_text());
+    // Two million cells, but each line is a few runs
+    assert!(size < 2_000_000, "dump is {} bytes", size);
+}
+
+#[test]
+fn loading_garbage_fails() {
+    let path = dump_path("garbage");
+    std::fs::write(&path, b"not a dump").unwrap();
+    assert!(Grid::load_from(&path).is_err());
+    std::fs::remove_file(&path).unwrap();
+}
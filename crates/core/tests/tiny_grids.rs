@@ -0,0 +1,131 @@
+//! Every public entry point on the smallest grids a window can produce.
+
+use the_dev_terminal_core::grid::{CellColor, Grid};
+use the_dev_terminal_core::marks::MarkKind;
+use the_dev_terminal_core::Terminal;
+
+// Output touching cursor movement, erasing, editing, tabs, scrolling,
+// wide and combining characters, the alternate screen and shell marks
+const STREAM: &[&str] = &[
+    "hello world\r\n",
+    "\u{4e16}\u{754c}e\u{301}\u{1F1FA}\u{1F1F8}\t|\ttab\r\n",
+    "\x1b[5;5H\x1b[10A\x1b[10B\x1b[10C\x1b[10D\x1b[3E\x1b[3F\x1b[9G\x1b[9d",
+    "\x1b[0J\x1b[1J\x1b[2J\x1b[0K\x1b[1K\x1b[2K",
+    "\x1b[4h\u{4e16}x\x1b[4l\x1b[0g\x1b[3g\x1bH\t\t",
+    "\x1b#8\x1bD\x1bE\x1bM\x1bM\x1bM",
+    "\x1b[6n\x1b[18t\x1b[1;31;48;5;200;38;2;1;2;3mX\x1b[m",
+    "\x1b[?1049h\u{4e16}alt\r\n\x1b[?1049l",
+    "\x1b]133;A\x07$ \x1b]133;B\x07ls\r\n\x1b]133;C\x07out\r\n\x1b]133;D;0\x07",
+    "\u{4e16}\u{4e16}\u{4e16}\r\n\x08\x08\x08\u{4e16}",
+];
+
+fn exercise(cols: usize, rows: usize) {
+    let mut term = Terminal::with_grid(Grid::new(cols, rows, 5));
+    for chunk in STREAM {
+        term.advance(chunk.as_bytes());
+        let g = &mut term.grid;
+        assert!(g.x() < g.cols && g.y() < g.rows, "cursor {:?} off a {}x{} grid", (g.x(), g.y()), cols, rows);
+    }
+    
+    let g = &mut term.grid;
+    let end = g.history_len() + g.rows;
+    for abs in 0..end + 2 {
+        let _ = (g.abs_row(abs), g.row_cells(abs), g.row_text(abs), g.link_at(0, abs), g.link_at(cols + 3, abs));
+        let _ = (g.prev_mark(abs), g.next_mark(abs));
+    }
+    let _ = g.visible_rows(0, rows + 2).count();
+    let _ = (g.get_text_in_region(0, 0, cols + 3, rows + 3), g.get_text_between((0, 0), (cols + 3, rows + 3)));
+    let _ = g.get_text_absolute((cols + 3, 0), (cols + 3, end + 3));
+    let _ = (g.export_text(), g.export_ansi(), g.to_string_lines(), g.theme_json(), g.last_command_output());
+    let _ = (g.viewport_text(0, rows + 2), g.snapshot_viewport(end, rows), g.viewport_cursor(end + 3, rows));
+    let _ = (g.get_cells_for_display(), g.get_display_content(), g.heap_usage(), g.selection_bounds((3, 3), (0, 0)));
+    let _ = (g.next_tab_stop(), g.scrollable_lines(), g.cursor_abs_row(), g.take_dirty_rows());
+    
+    g.scroll_up(100);
+    g.page_down();
+    g.page_up();
+    g.scroll_down(100);
+    g.scroll_to_bottom();
+    g.set_tab_stop();
+    g.clear_tab_stop();
+    g.clear_all_tab_stops();
+    let _ = g.next_tab_stop();
+    g.scroll_region_up(0, rows + 3, rows + 3, CellColor::Indexed(1), true);
+    g.scroll_region_down(0, rows + 3, rows + 3, CellColor::Default);
+    g.scroll_region_up(rows + 3, 0, 1, CellColor::Default, false);
+    g.add_mark(MarkKind::Prompt);
+    g.start_command_output();
+    g.end_command_output();
+    g.push_screen_to_scrollback();
+    g.clear_line(rows + 3);
+    g.clear_eol();
+    g.clear_eol_from_cursor();
+    g.clear_bol_to_cursor();
+    g.fill_with('\u{4e16}');
+    g.put('\u{4e16}');
+    g.wrap();
+    g.reverse_lf();
+    g.lf();
+    g.cr();
+    g.enter_alt_screen();
+    g.put('a');
+    g.exit_alt_screen();
+    g.clear_screen();
+    g.clear_all();
+    
+    // Shrinking to one cell and back
+    let resize = g.resize_preserve(1, 1);
+    let _ = (resize.abs_position((cols + 3, 0)), resize.abs_position((0, end + 3)));
+    let reflow = g.reflow(1, 1);
+    let _ = (reflow.position((cols + 3, rows + 3)), reflow.abs_position((0, end + 3)));
+    g.resize(1, 1);
+    term.advance("\u{4e16}ab\tc\x1b[2;2H\x1b[1Pdef\r\n".as_bytes());
+    let g = &mut term.grid;
+    g.reflow(cols + 1, rows + 1);
+    g.resize_preserve(cols, rows);
+    g.resize(cols, rows);
+    let _ = (g.export_text(), g.get_display_content());
+}
+
+#[test]
+fn one_by_one_grid_survives_everything() {
+    exercise(1, 1);
+}
+
+#[test]
+fn two_by_two_grid_survives_everything() {
+    exercise(2, 2);
+}
+
+#[test]
+fn zero_sizes_become_one_cell() {
+    let mut g = Grid::new(1, 1, 0);
+    g.resize(0, 0);
+    assert_eq!((g.cols, g.rows), (1, 1));
+    g.put('x');
+    let _ = g.reflow(0, 0).position((0, 0));
+    assert_eq!((g.cols, g.rows), (1, 1));
+}
+
+#[test]
+fn resize_survives_a_cell_buffer_of_the_wrong_size() {
+    // Too short: the missing cells come back blank
+    let mut term = Terminal::with_grid(Grid::new(4, 3, 10));
+    term.advance(b"abcd\r\nef");
+    let g = &mut term.grid;
+    g.cells.truncate(5);
+    g.resize_preserve(6, 4);
+    assert_eq!(g.cells.len(), 6 * 4);
+    assert_eq!(g.row_text(0).trim_end(), "abcd");
+    assert_eq!(g.row_text(1).trim_end(), "e");
+
+    // Too long: the extra cells are dropped
+    let mut term = Terminal::with_grid(Grid::new(4, 3, 10));
+    term.advance(b"abcd\r\nef");
+    let g = &mut term.grid;
+    let extra = vec![g.cells[0]; 7];
+    g.cells.extend(extra);
+    g.resize_preserve(2, 2);
+    assert_eq!(g.cells.len(), 2 * 2);
+    let _ = (g.export_text(), g.get_display_content());
+}
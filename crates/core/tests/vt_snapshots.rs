@@ -0,0 +1,127 @@
+//! VT regression snapshots.
+//!
+//! Each `tests/fixtures/vt/<name>.bin` is raw PTY output recorded from a real
+//! program on an 80x24 terminal. It is fed through `Terminal::advance` and the
+//! resulting screen text, cell attributes, cursor and query replies are compared
+//! with `<name>.snap` next to it.
+//!
+//! After an intentional behavior change, regenerate the snapshots with
+//!
+//!     UPDATE_SNAPSHOTS=1 cargo test -p the-dev-terminal-core --test vt_snapshots
+//!
+//! and review the diff before committing.
+
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use the_dev_terminal_core::grid::{Cell, CellColor, Grid};
+use the_dev_terminal_core::terminal::Terminal;
+
+const COLS: usize = 80;
+const ROWS: usize = 24;
+
+fn fixtures_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/vt")
+}
+
+// Attributes that differ from a blank default cell, e.g. "fg=#cd3131 bold"
+fn attr_label(grid: &Grid, cell: &Cell) -> String {
+    let mut parts = Vec::new();
+    if cell.fg != CellColor::Default {
+        parts.push(format!("fg={}", grid.palette.fg(cell.fg).to_hex()));
+    }
+    if cell.bg != CellColor::Default {
+        parts.push(format!("bg={}", grid.palette.bg(cell.bg).to_hex()));
+    }
+    if cell.attrs().bold() {
+        parts.push("bold".to_string());
+    }
+    if cell.attrs().italic() {
+        parts.push("italic".to_string());
+    }
+    if cell.attrs().underline() {
+        parts.push("underline".to_string());
+    }
+    parts.join(" ")
+}
+
+// One line per run of identically styled non-default cells: "row col-col attrs"
+fn attribute_dump(grid: &Grid) -> String {
+    let mut out = String::new();
+    for row in 0..grid.rows {
+        let cells = &grid.cells[row * grid.cols..(row + 1) * grid.cols];
+        let mut col = 0;
+        while col < cells.len() {
+            let label = attr_label(grid, &cells[col]);
+            let start = col;
+            while col < cells.len() && attr_label(grid, &cells[col]) == label {
+                col += 1;
+            }
+            if !label.is_empty() {
+                let _ = writeln!(out, "{:>2} {:>2}-{:<2} {}", row, start, col - 1, label);
+            }
+        }
+    }
+    out
+}
+
+fn snapshot(bytes: &[u8]) -> String {
+    let mut term = Terminal::new(COLS, ROWS);
+    term.advance(bytes);
+    let replies = term.take_replies();
+    let grid = &term.grid;
+
+    let mut out = String::new();
+    let _ = writeln!(out, "cursor: {},{}", grid.x(), grid.y());
+    let _ = writeln!(out, "alt screen: {}", grid.is_alt_screen());
+    let _ = writeln!(out, "replies: {}", replies.escape_ascii());
+    out.push_str("--- text\n");
+    for line in grid.to_string_lines().lines() {
+        let _ = writeln!(out, "|{}", line.trim_end());
+    }
+    out.push_str("--- attributes\n");
+    out.push_str(&attribute_dump(grid));
+    out
+}
+
+fn check(name: &str) {
+    let dir = fixtures_dir();
+    let input = std::fs::read(dir.join(format!("{name}.bin"))).expect("fixture");
+    let actual = snapshot(&input);
+    let snap_path = dir.join(format!("{name}.snap"));
+
+    if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        std::fs::write(&snap_path, &actual).expect("write snapshot");
+        return;
+    }
+    let expected = std::fs::read_to_string(&snap_path)
+        .unwrap_or_else(|_| panic!("missing {}; run with UPDATE_SNAPSHOTS=1", snap_path.display()));
+    assert!(
+        expected == actual,
+        "{name} differs from its snapshot (UPDATE_SNAPSHOTS=1 to accept)\n--- expected\n{expected}\n--- actual\n{actual}"
+    );
+}
+
+#[test]
+fn ls_color() {
+    check("ls_color");
+}
+
+#[test]
+fn git_log_graph() {
+    check("git_log_graph");
+}
+
+#[test]
+fn vim_startup() {
+    check("vim_startup");
+}
+
+#[test]
+fn vim_startup_exit() {
+    check("vim_startup_exit");
+}
+
+#[test]
+fn top_frame() {
+    check("top_frame");
+}
@@ -0,0 +1,44 @@
+//! Double-click word selection and the configurable word characters.
+
+use the_dev_terminal_core::config::GeneralConfig;
+use the_dev_terminal_core::grid::{find_word_boundaries, Cell, Grid};
+use the_dev_terminal_core::Terminal;
+
+fn line(text: &str) -> Vec<Cell> {
+    let mut term = Terminal::with_grid(Grid::new(60, 2, 0));
+    term.advance(text.as_bytes());
+    term.grid.row_cells(0).to_vec()
+}
+
+fn word(line: &[Cell], col: usize, word_chars: &str) -> String {
+    let (start, end) = find_word_boundaries(line, col, word_chars);
+    line[start..=end].iter().map(|c| c.ch()).collect()
+}
+
+#[test]
+fn paths_select_whole_with_separators_as_word_chars() {
+    let l = line("ls -l /usr/local/bin/rustc.exe now");
+    // Double-click on "local"
+    assert_eq!(word(&l, 11, "/."), "/usr/local/bin/rustc.exe");
+    assert_eq!(word(&l, 28, "/."), "/usr/local/bin/rustc.exe");
+}
+
+#[test]
+fn without_them_selection_stops_at_separators() {
+    let l = line("ls -l /usr/local/bin/rustc.exe now");
+    assert_eq!(word(&l, 11, ""), "local");
+    assert_eq!(word(&l, 28, ""), "exe");
+    // Underscores and digits are always part of a word
+    assert_eq!(word(&line("a snake_case_2 b"), 4, ""), "snake_case_2");
+}
+
+#[test]
+fn default_word_chars_keep_locations_and_urls_whole() {
+    let chars = GeneralConfig::default().word_chars;
+    assert_eq!(word(&line("error at src/grid.rs:12:4 here"), 12, &chars), "src/grid.rs:12:4");
+    assert_eq!(word(&line("see https://example.com/a-b~c ok"), 14, &chars), "https://example.com/a-b~c");
+    // Spaces and brackets still end a word; off a word the span is one cell
+    assert_eq!(word(&line("(~/notes.md)"), 3, &chars), "~/notes.md");
+    assert_eq!(find_word_boundaries(&line("a  b"), 1, &chars), (1, 1));
+    assert_eq!(find_word_boundaries(&line("ab"), 50, &chars), (50, 50));
+}
@@ -1,5 +1,8 @@
 use wgpu::util::DeviceExt;
+use cosmic_text::{FontSystem, Metrics, SwashCache};
+use crate::glyph_atlas::{GlyphAtlas, GlyphKey};
 use the_dev_terminal_core::grid::Cell;
+use the_dev_terminal_core::palette::Palette;
 
 #[repr(C)]
 #[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
@@ -9,6 +12,9 @@ struct TextVertex {
     color: [f32; 4],
 }
 
+// Quads the vertex buffer holds; indices are u16
+const MAX_QUADS: usize = 65536 / 4;
+
 pub struct ColoredTextRenderer {
     pipeline: wgpu::RenderPipeline,
     vertex_buffer: wgpu::Buffer,
@@ -16,15 +22,17 @@ pub struct ColoredTextRenderer {
     uniform_buffer: wgpu::Buffer,
     bind_group: wgpu::BindGroup,
     glyph_texture: wgpu::Texture,
-    glyph_view: wgpu::TextureView,
+    _glyph_view: wgpu::TextureView,
+    atlas: GlyphAtlas,
     vertices: Vec<TextVertex>,
     indices: Vec<u16>,
 }
 
 impl ColoredTextRenderer {
-    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue, format: wgpu::TextureFormat) -> Self {
-        // Create a simple glyph texture (we'll generate ASCII glyphs)
-        let glyph_size = 256;
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat, metrics: Metrics) -> Self {
+        // Glyphs are rasterized into the atlas as cells first use them
+        let atlas = GlyphAtlas::new(1024, metrics);
+        let glyph_size = atlas.size();
         let glyph_texture = device.create_texture(&wgpu::TextureDescriptor {
             label: Some("Glyph Texture"),
             size: wgpu::Extent3d {
@@ -40,29 +48,6 @@ impl ColoredTextRenderer {
             view_formats: &[],
         });
         
-        // Generate ASCII glyphs (simplified - just fill with white for now)
-        let glyph_data = vec![255u8; (glyph_size * glyph_size) as usize];
-        
-        queue.write_texture(
-            wgpu::ImageCopyTexture {
-                texture: &glyph_texture,
-                mip_level: 0,
-                origin: wgpu::Origin3d::ZERO,
-                aspect: wgpu::TextureAspect::All,
-            },
-            &glyph_data,
-            wgpu::ImageDataLayout {
-                offset: 0,
-                bytes_per_row: Some(glyph_size),
-                rows_per_image: None,
-            },
-            wgpu::Extent3d {
-                width: glyph_size,
-                height: glyph_size,
-                depth_or_array_layers: 1,
-            },
-        );
-        
         let glyph_view = glyph_texture.create_view(&wgpu::TextureViewDescriptor::default());
         
         let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
@@ -70,8 +55,8 @@ impl ColoredTextRenderer {
             address_mode_u: wgpu::AddressMode::ClampToEdge,
             address_mode_v: wgpu::AddressMode::ClampToEdge,
             address_mode_w: wgpu::AddressMode::ClampToEdge,
-            mag_filter: wgpu::FilterMode::Linear,
-            min_filter: wgpu::FilterMode::Linear,
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Nearest,
             ..Default::default()
         });
         
@@ -211,7 +196,8 @@ impl ColoredTextRenderer {
             uniform_buffer,
             bind_group,
             glyph_texture,
-            glyph_view,
+            _glyph_view: glyph_view,
+            atlas,
             vertices: Vec::new(),
             indices: Vec::new(),
         }
@@ -221,10 +207,19 @@ impl ColoredTextRenderer {
         queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[width, height]));
     }
     
+    /// Rasterize glyphs at `metrics` from now on (font size or DPI changed)
+    pub fn set_metrics(&mut self, metrics: Metrics) {
+        self.atlas.set_metrics(metrics);
+    }
+    
+    #[allow(clippy::too_many_arguments)]
     pub fn prepare_cells(
         &mut self,
         queue: &wgpu::Queue,
+        font_system: &mut FontSystem,
+        swash_cache: &mut SwashCache,
         cells: &[Cell],
+        palette: &Palette,
         cols: usize,
         rows: usize,
         cell_width: f32,
@@ -235,69 +230,81 @@ impl ColoredTextRenderer {
         self.vertices.clear();
         self.indices.clear();
         
-        // For each visible cell, create a colored quad
-        for row in 0..rows {
+        // One textured quad per glyph, tinted with the cell's foreground
+        'rows: for row in 0..rows {
             for col in 0..cols {
                 let idx = row * cols + col;
-                if idx >= cells.len() {
-                    break;
+                if idx >= cells.len() || self.vertices.len() / 4 >= MAX_QUADS {
+                    break 'rows;
                 }
                 
                 let cell = &cells[idx];
-                if cell.ch == '\0' || cell.ch == ' ' {
+                if cell.is_empty() || cell.is_wide_trailer() {
                     continue;
                 }
                 
-                let x = offset_x + col as f32 * cell_width;
-                let y = offset_y + row as f32 * cell_height;
+                let attrs = cell.attrs();
+                let key = GlyphKey { ch: cell.ch(), bold: attrs.bold(), italic: attrs.italic() };
+                let Some(glyph) = self.atlas.glyph(font_system, swash_cache, key) else {
+                    continue;
+                };
                 
+                let x = offset_x + col as f32 * cell_width + glyph.left;
+                let y = offset_y + row as f32 * cell_height + glyph.top;
+                let (x1, y1) = (x + glyph.width, y + glyph.height);
+                let uv = glyph.uv;
+                
+                let fg = palette.text_fg(&attrs);
                 let color = [
-                    cell.fg.r as f32 / 255.0,
-                    cell.fg.g as f32 / 255.0,
-                    cell.fg.b as f32 / 255.0,
+                    fg.r as f32 / 255.0,
+                    fg.g as f32 / 255.0,
+                    fg.b as f32 / 255.0,
                     1.0,
                 ];
                 
-                // Create a simple colored rectangle for each character
-                // In a real implementation, we'd use actual glyph texture coordinates
                 let vertex_base = self.vertices.len() as u16;
-                
-                // Top-left
-                self.vertices.push(TextVertex {
-                    position: [x, y],
-                    tex_coords: [0.0, 0.0],
-                    color,
-                });
-                // Top-right
-                self.vertices.push(TextVertex {
-                    position: [x + cell_width * 0.8, y],
-                    tex_coords: [1.0, 0.0],
-                    color,
-                });
-                // Bottom-right
-                self.vertices.push(TextVertex {
-                    position: [x + cell_width * 0.8, y + cell_height],
-                    tex_coords: [1.0, 1.0],
-                    color,
-                });
-                // Bottom-left
-                self.vertices.push(TextVertex {
-                    position: [x, y + cell_height],
-                    tex_coords: [0.0, 1.0],
-                    color,
-                });
+                self.vertices.extend_from_slice(&[
+                    TextVertex { position: [x, y], tex_coords: [uv.u0, uv.v0], color },
+                    TextVertex { position: [x1, y], tex_coords: [uv.u1, uv.v0], color },
+                    TextVertex { position: [x1, y1], tex_coords: [uv.u1, uv.v1], color },
+                    TextVertex { position: [x, y1], tex_coords: [uv.u0, uv.v1], color },
+                ]);
                 
                 // Two triangles
-                self.indices.push(vertex_base);
-                self.indices.push(vertex_base + 1);
-                self.indices.push(vertex_base + 2);
-                self.indices.push(vertex_base);
-                self.indices.push(vertex_base + 2);
-                self.indices.push(vertex_base + 3);
+                self.indices.extend_from_slice(&[
+                    vertex_base,
+                    vertex_base + 1,
+                    vertex_base + 2,
+                    vertex_base,
+                    vertex_base + 2,
+                    vertex_base + 3,
+                ]);
             }
         }
         
-        // Upload data
+        // Upload data; the atlas only when new glyphs were rasterized
+        if self.atlas.take_dirty() {
+            let size = self.atlas.size();
+            queue.write_texture(
+                wgpu::ImageCopyTexture {
+                    texture: &self.glyph_texture,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d::ZERO,
+                    aspect: wgpu::TextureAspect::All,
+                },
+                self.atlas.pixels(),
+                wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(size),
+                    rows_per_image: None,
+                },
+                wgpu::Extent3d {
+                    width: size,
+                    height: size,
+                    depth_or_array_layers: 1,
+                },
+            );
+        }
         if !self.vertices.is_empty() {
             queue.write_buffer(&self.vertex_buffer, 0, bytemuck::cast_slice(&self.vertices));
             queue.write_buffer(&self.index_buffer, 0, bytemuck::cast_slice(&self.indices));
@@ -35,7 +35,7 @@ var glyph_sampler: sampler;
 
 @fragment
 fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
-    // For now, just return the color directly (colored blocks)
-    // In a real implementation, we'd sample the glyph texture
-    return input.color;
+    // The atlas holds coverage; the cell's color fills the glyph
+    let coverage = textureSample(glyph_texture, glyph_sampler, input.tex_coords).r;
+    return vec4<f32>(input.color.rgb, input.color.a * coverage);
 }
\ No newline at end of file
@@ -0,0 +1,195 @@
+use std::collections::HashMap;
+use cosmic_text::{Attrs, Buffer, FontSystem, Metrics, Shaping, Style, SwashCache, SwashContent, Weight};
+
+// Gap between packed glyphs so sampling one never picks up its neighbour
+const GUTTER: u32 = 1;
+
+/// One rasterization of a character
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct GlyphKey {
+    pub ch: char,
+    pub bold: bool,
+    pub italic: bool,
+}
+
+/// Texture coordinates of a rectangle in the atlas, 0..1 on both axes
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct UvRect {
+    pub u0: f32,
+    pub v0: f32,
+    pub u1: f32,
+    pub v1: f32,
+}
+
+/// A glyph in the atlas, with its size and offset from the cell's top-left in pixels
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AtlasGlyph {
+    pub uv: UvRect,
+    pub left: f32,
+    pub top: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+/// Shelf packer: rectangles fill a row left to right, and a new row starts
+/// below the tallest one when the current row is full
+pub struct AtlasPacker {
+    width: u32,
+    height: u32,
+    x: u32,
+    shelf_y: u32,
+    shelf_height: u32,
+}
+
+impl AtlasPacker {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self { width, height, x: 0, shelf_y: 0, shelf_height: 0 }
+    }
+
+    /// Top-left of a free `w`×`h` rectangle, None once the atlas is full
+    pub fn allocate(&mut self, w: u32, h: u32) -> Option<(u32, u32)> {
+        if w > self.width {
+            return None;
+        }
+        if self.x + w > self.width {
+            self.shelf_y += self.shelf_height + GUTTER;
+            self.x = 0;
+            self.shelf_height = 0;
+        }
+        if self.shelf_y + h > self.height {
+            return None;
+        }
+        let pos = (self.x, self.shelf_y);
+        self.x += w + GUTTER;
+        self.shelf_height = self.shelf_height.max(h);
+        Some(pos)
+    }
+
+    /// Texture coordinates of the `w`×`h` rectangle at (x, y)
+    pub fn uv(&self, x: u32, y: u32, w: u32, h: u32) -> UvRect {
+        let (width, height) = (self.width as f32, self.height as f32);
+        UvRect {
+            u0: x as f32 / width,
+            v0: y as f32 / height,
+            u1: (x + w) as f32 / width,
+            v1: (y + h) as f32 / height,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::new(self.width, self.height);
+    }
+}
+
+/// Glyph coverage for the colored text pipeline: characters are rasterized
+/// on first use into a single-channel atlas, which the renderer uploads
+/// whenever it changed
+pub struct GlyphAtlas {
+    size: u32,
+    packer: AtlasPacker,
+    pixels: Vec<u8>,
+    // None for characters that draw nothing (spaces) or didn't fit
+    glyphs: HashMap<GlyphKey, Option<AtlasGlyph>>,
+    metrics: Metrics,
+    dirty: bool,
+}
+
+impl GlyphAtlas {
+    /// Empty `size`×`size` atlas rasterizing at `metrics`
+    pub fn new(size: u32, metrics: Metrics) -> Self {
+        Self {
+            size,
+            packer: AtlasPacker::new(size, size),
+            pixels: vec![0; (size * size) as usize],
+            glyphs: HashMap::new(),
+            metrics,
+            dirty: true,
+        }
+    }
+
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    /// Coverage, one byte per pixel, row-major
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    /// Whether pixels changed since the last call, so the texture needs uploading
+    pub fn take_dirty(&mut self) -> bool {
+        std::mem::take(&mut self.dirty)
+    }
+
+    /// Rasterize at a new size from now on; every glyph is dropped
+    pub fn set_metrics(&mut self, metrics: Metrics) {
+        if metrics == self.metrics {
+            return;
+        }
+        self.metrics = metrics;
+        self.glyphs.clear();
+        self.packer.clear();
+        self.pixels.fill(0);
+        self.dirty = true;
+    }
+
+    /// The glyph for `key`, rasterized into the atlas the first time it's asked for
+    pub fn glyph(&mut self, font_system: &mut FontSystem, swash_cache: &mut SwashCache, key: GlyphKey) -> Option<AtlasGlyph> {
+        if let Some(glyph) = self.glyphs.get(&key) {
+            return *glyph;
+        }
+        let glyph = self.rasterize(font_system, swash_cache, key);
+        self.glyphs.insert(key, glyph);
+        glyph
+    }
+
+    fn rasterize(&mut self, font_system: &mut FontSystem, swash_cache: &mut SwashCache, key: GlyphKey) -> Option<AtlasGlyph> {
+        let mut attrs = Attrs::new().family(cosmic_text::Family::Monospace);
+        if key.bold {
+            attrs = attrs.weight(Weight::BOLD);
+        }
+        if key.italic {
+            attrs = attrs.style(Style::Italic);
+        }
+        let mut buffer = Buffer::new(font_system, self.metrics);
+        buffer.set_size(font_system, self.metrics.font_size * 4.0, self.metrics.line_height);
+        buffer.set_text(font_system, key.ch.encode_utf8(&mut [0; 4]), attrs, Shaping::Advanced);
+
+        let (physical, baseline) = {
+            let run = buffer.layout_runs().next()?;
+            (run.glyphs.first()?.physical((0.0, 0.0), 1.0), run.line_y)
+        };
+        let image = swash_cache.get_image_uncached(font_system, physical.cache_key)?;
+        let (w, h) = (image.placement.width, image.placement.height);
+        if w == 0 || h == 0 {
+            return None;
+        }
+        let Some((x, y)) = self.packer.allocate(w, h) else {
+            tracing::warn!("Glyph atlas is full; {:?} is not drawn", key.ch);
+            return None;
+        };
+
+        // Color glyphs (emoji) keep only their shape, which the cell's color
+        // fills; subpixel masks are reduced to their green channel
+        let (channels, channel) = match image.content {
+            SwashContent::Mask => (1, 0),
+            SwashContent::SubpixelMask => (4, 1),
+            SwashContent::Color => (4, 3),
+        };
+        for row in 0..h {
+            for col in 0..w {
+                let src = ((row * w + col) * channels + channel) as usize;
+                self.pixels[((y + row) * self.size + x + col) as usize] = image.data[src];
+            }
+        }
+        self.dirty = true;
+
+        Some(AtlasGlyph {
+            uv: self.packer.uv(x, y, w, h),
+            left: (physical.x + image.placement.left) as f32,
+            top: baseline + physical.y as f32 - image.placement.top as f32,
+            width: w as f32,
+            height: h as f32,
+        })
+    }
+}
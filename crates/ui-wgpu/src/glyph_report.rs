@@ -0,0 +1,151 @@
+use std::collections::HashSet;
+
+/// Distinct missing codepoints before we tell the user about their font
+pub const MISSING_GLYPH_THRESHOLD: usize = 8;
+
+/// Codepoint ranges that prompts and TUIs commonly draw from
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum GlyphRange {
+    Powerline,
+    BoxDrawing,
+    Braille,
+    PrivateUse,
+    Other,
+}
+
+impl GlyphRange {
+    pub fn of(ch: char) -> GlyphRange {
+        match ch as u32 {
+            0xE0A0..=0xE0D7 => GlyphRange::Powerline,
+            // Box drawing plus block elements
+            0x2500..=0x259F => GlyphRange::BoxDrawing,
+            0x2800..=0x28FF => GlyphRange::Braille,
+            // Nerd Font icons live in the private use areas
+            0xE000..=0xF8FF | 0xF0000..=0xFFFFD | 0x100000..=0x10FFFD => GlyphRange::PrivateUse,
+            _ => GlyphRange::Other,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            GlyphRange::Powerline => "Powerline symbols (U+E0A0-E0D7)",
+            GlyphRange::BoxDrawing => "Box drawing (U+2500-259F)",
+            GlyphRange::Braille => "Braille (U+2800-28FF)",
+            GlyphRange::PrivateUse => "Private-use icons (Nerd Font)",
+            GlyphRange::Other => "Other",
+        }
+    }
+}
+
+/// Tracks codepoints the font could not shape (notdef / tofu) over a session
+#[derive(Default)]
+pub struct MissingGlyphReport {
+    missing: HashSet<char>,
+    notified: bool,
+}
+
+impl MissingGlyphReport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a codepoint that shaped to the notdef glyph
+    pub fn record_missing(&mut self, ch: char) {
+        if !ch.is_whitespace() && !ch.is_control() {
+            self.missing.insert(ch);
+        }
+    }
+
+    pub fn distinct_missing(&self) -> usize {
+        self.missing.len()
+    }
+
+    /// Missing codepoint counts per range, most affected first
+    pub fn by_range(&self) -> Vec<(GlyphRange, usize)> {
+        let mut counts: Vec<(GlyphRange, usize)> = Vec::new();
+        for &ch in &self.missing {
+            let range = GlyphRange::of(ch);
+            match counts.iter_mut().find(|(r, _)| *r == range) {
+                Some((_, n)) => *n += 1,
+                None => counts.push((range, 1)),
+            }
+        }
+        counts.sort_by_key(|&(_, n)| std::cmp::Reverse(n));
+        counts
+    }
+
+    /// One-time user-facing notice, returned once the threshold is crossed
+    pub fn take_notice(&mut self) -> Option<String> {
+        if self.notified || self.missing.len() < MISSING_GLYPH_THRESHOLD {
+            return None;
+        }
+        self.notified = true;
+        Some(format!(
+            "Your font is missing {} symbols used by your prompt - consider a Nerd Font or adding a fallback",
+            self.missing.len()
+        ))
+    }
+
+    /// Details for the notice: top missing ranges and the config key to fix it
+    pub fn details(&self) -> String {
+        let mut s = String::from("Missing glyphs by range:\n");
+        for (range, n) in self.by_range() {
+            s.push_str(&format!("  {}: {}\n", range.label(), n));
+        }
+        s.push_str("Set appearance.font_family in the config to a font that covers these symbols.");
+        s
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn notice_waits_for_the_threshold_and_comes_once() {
+        let mut report = MissingGlyphReport::new();
+        for ch in ('\u{E0A0}'..).take(MISSING_GLYPH_THRESHOLD - 1) {
+            report.record_missing(ch);
+        }
+        // Repeats, blanks and controls don't count
+        report.record_missing('\u{E0A0}');
+        report.record_missing(' ');
+        report.record_missing('\u{7}');
+        assert_eq!(report.distinct_missing(), MISSING_GLYPH_THRESHOLD - 1);
+        assert_eq!(report.take_notice(), None);
+
+        report.record_missing('\u{2800}');
+        let notice = report.take_notice().unwrap();
+        assert!(notice.contains(&format!("missing {} symbols", MISSING_GLYPH_THRESHOLD)), "{notice}");
+        report.record_missing('\u{2801}');
+        assert_eq!(report.take_notice(), None);
+    }
+
+    #[test]
+    fn codepoints_fall_into_their_ranges() {
+        assert_eq!(GlyphRange::of('\u{E0B0}'), GlyphRange::Powerline);
+        assert_eq!(GlyphRange::of('\u{E0D7}'), GlyphRange::Powerline);
+        assert_eq!(GlyphRange::of('\u{E0D8}'), GlyphRange::PrivateUse);
+        assert_eq!(GlyphRange::of('\u{2500}'), GlyphRange::BoxDrawing);
+        assert_eq!(GlyphRange::of('\u{2588}'), GlyphRange::BoxDrawing);
+        assert_eq!(GlyphRange::of('\u{28FF}'), GlyphRange::Braille);
+        assert_eq!(GlyphRange::of('\u{F101}'), GlyphRange::PrivateUse);
+        assert_eq!(GlyphRange::of('\u{F0001}'), GlyphRange::PrivateUse);
+        assert_eq!(GlyphRange::of('\u{1F600}'), GlyphRange::Other);
+    }
+
+    #[test]
+    fn ranges_are_counted_most_affected_first() {
+        let mut report = MissingGlyphReport::new();
+        for ch in ['\u{F101}', '\u{F102}', '\u{F103}', '\u{E0B0}', '\u{E0B2}', '\u{2500}'] {
+            report.record_missing(ch);
+        }
+        assert_eq!(
+            report.by_range(),
+            [(GlyphRange::PrivateUse, 3), (GlyphRange::Powerline, 2), (GlyphRange::BoxDrawing, 1)]
+        );
+        let details = report.details();
+        assert!(details.contains("  Private-use icons (Nerd Font): 3\n  Powerline symbols (U+E0A0-E0D7): 2\n"), "{details}");
+        assert!(details.ends_with("appearance.font_family in the config to a font that covers these symbols."));
+    }
+}
@@ -1,7 +1,9 @@
 pub mod renderer;
 pub mod text_renderer;
 pub mod colored_text;
+pub mod glyph_atlas;
+pub mod glyph_report;
 
-pub use renderer::Renderer;
+pub use renderer::{drawn_pixels, fg_spans, selection_rows, RenderError, Renderer};
 pub use text_renderer::TextRenderer;
 pub use colored_text::ColoredTextRenderer;
\ No newline at end of file
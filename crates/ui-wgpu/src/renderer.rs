@@ -2,6 +2,7 @@ use anyhow::Result;
 use wgpu::*;
 use wgpu::util::DeviceExt;
 use winit::window::Window;
+use std::ops::Range;
 use std::sync::Arc;
 use cosmic_text::{FontSystem, SwashCache, Buffer as TextBuffer, Metrics, Attrs, Shaping};
 use glyphon::{
@@ -9,7 +10,13 @@ use glyphon::{
     Resolution
 };
 use crate::colored_text::ColoredTextRenderer;
-use the_dev_terminal_core::grid::Cell;
+use crate::glyph_report::MissingGlyphReport;
+use the_dev_terminal_core::bidi::{is_rtl, isolate_runs};
+use the_dev_terminal_core::cluster::{cluster_cells, split_clusters};
+use the_dev_terminal_core::config::BidiMode;
+use the_dev_terminal_core::grid::{cells_text, Cell, CellColor, Color as CellRgb, DisplaySnapshot};
+use the_dev_terminal_core::palette::Palette;
+use the_dev_terminal_core::perf::{PerfTimer, StartupProfile};
 
 #[repr(C)]
 #[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
@@ -24,27 +31,177 @@ struct ScreenUbo {
     size: [f32; 2] 
 }
 
+// One row of shaped text. Runs of plain narrow characters share a buffer;
+// each wide character or multi-codepoint cluster gets its own so it can be
+// centered on its cells instead of wherever the fallback font's advance
+// would put it. Right-to-left characters are standalone too, so the shaper
+// can't reorder them: cells are drawn in logical order unless the bidi mode
+// moves them.
+struct RowText {
+    line: String,
+    // Drawn foreground per cell; a change reshapes the row like new text
+    fg: Vec<CellRgb>,
+    pieces: Vec<TextPiece>,
+}
+
+struct TextPiece {
+    col: usize,
+    // Cells a standalone cluster is centered on; 0 for a run of plain text
+    cells: usize,
+    buffer: TextBuffer,
+}
+
+// Split a row's text into (start column, cells if standalone, cells the
+// text came from) pieces. `map` gives the display column of each cell when
+// RTL runs are reordered.
+fn split_wide(line: &str, map: Option<&[usize]>) -> Vec<(usize, usize, Range<usize>)> {
+    let mut pieces = Vec::new();
+    let (mut run_start, mut run_col, mut col) = (0, 0, 0);
+    let mut offset = 0;
+    for cluster in split_clusters(line) {
+        let cells = cluster_cells(cluster);
+        let end = offset + cluster.len();
+        let display_col = map.and_then(|m| m.get(col)).copied().unwrap_or(col);
+        if cells == 2 || cluster.chars().nth(1).is_some() || cluster.chars().any(is_rtl) || display_col != col {
+            if run_start < offset {
+                pieces.push((run_col, 0, run_col..col));
+            }
+            pieces.push((display_col, cells, col..col + cells));
+            run_start = end;
+            run_col = col + cells;
+        }
+        col += cells;
+        offset = end;
+    }
+    if run_start < line.len() {
+        pieces.push((run_col, 0, run_col..col));
+    }
+    pieces
+}
+
+/// Text of `cells` in runs of one drawn foreground color, as glyphs are
+/// colored: 24-bit colors are kept exact, palette colors resolved
+pub fn fg_spans(cells: &[Cell], palette: &Palette) -> Vec<(String, CellRgb)> {
+    let mut spans: Vec<(String, CellRgb)> = Vec::new();
+    for cell in cells.iter().filter(|c| !c.is_wide_trailer()) {
+        let fg = palette.text_fg(&cell.attrs());
+        match spans.last_mut() {
+            Some((text, color)) if *color == fg => cell.push_text(text),
+            _ => {
+                let mut text = String::new();
+                cell.push_text(&mut text);
+                spans.push((text, fg));
+            }
+        }
+    }
+    spans
+}
+
+/// Where a selection from `a` to `b` ((col, absolute row), either order) is
+/// drawn in a `rows`-row viewport whose first row is absolute row `top`: one
+/// (viewport row, first col, last col) per visible row; full rows end at `last_col`
+pub fn selection_rows(a: (usize, usize), b: (usize, usize), top: usize, rows: usize, last_col: usize) -> Vec<(usize, usize, usize)> {
+    let ((minx, miny), (maxx, maxy)) = if (a.1, a.0) <= (b.1, b.0) { (a, b) } else { (b, a) };
+    (miny.max(top)..(maxy + 1).min(top + rows))
+        .map(|abs_row| {
+            let start_col = if abs_row == miny { minx } else { 0 };
+            let end_col = if abs_row == maxy { maxx } else { last_col };
+            (abs_row - top, start_col, end_col)
+        })
+        .collect()
+}
+
+/// Why `render_frame` drew nothing, by what the caller should do about it
+#[derive(Debug)]
+pub enum RenderError {
+    /// No surface texture in time; skip this frame and ask for another
+    Timeout,
+    /// The surface no longer fits the window; reconfigure it (`resize`) and redraw
+    Reconfigure,
+    /// Out of memory for another frame; rendering can't continue
+    OutOfMemory,
+    /// Anything else; this frame is lost
+    Other(anyhow::Error),
+}
+
+impl RenderError {
+    /// Whether a later frame can succeed
+    pub fn is_recoverable(&self) -> bool {
+        !matches!(self, RenderError::OutOfMemory)
+    }
+}
+
+impl From<SurfaceError> for RenderError {
+    fn from(e: SurfaceError) -> Self {
+        match e {
+            SurfaceError::Timeout => RenderError::Timeout,
+            SurfaceError::Outdated | SurfaceError::Lost => RenderError::Reconfigure,
+            SurfaceError::OutOfMemory => RenderError::OutOfMemory,
+        }
+    }
+}
+
+impl std::fmt::Display for RenderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RenderError::Timeout => write!(f, "timed out acquiring the next frame"),
+            RenderError::Reconfigure => write!(f, "surface is lost or outdated"),
+            RenderError::OutOfMemory => write!(f, "out of memory for a new frame"),
+            RenderError::Other(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for RenderError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RenderError::Other(e) => Some(e.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+/// Where frames go: the window's surface, or a texture for tests and screenshots
+pub enum RenderTarget {
+    Window(Surface<'static>),
+    Offscreen(Texture),
+}
+
 pub struct Renderer {
     pub device: Device,
     pub queue: Queue,
-    pub surface: Surface<'static>,
+    pub target: RenderTarget,
+    // Size and format of the target (only configures a surface for windows)
     pub config: SurfaceConfiguration,
     // Text rendering
     font_system: FontSystem,
     swash_cache: SwashCache,
     text_renderer: GlyphonRenderer,
     text_atlas: TextAtlas,
-    text_buffer: TextBuffer,
-    pending_text: String,
-    pending_cells: Vec<Cell>,
-    pending_cols: usize,
-    pending_rows: usize,
+    // Shaped text per row, so each row's baseline can be pinned
+    text_rows: Vec<RowText>,
+    // Baseline offset from the row top for the primary font
+    baseline: f32,
+    // The viewport being drawn; text, colors and cursor all come from it
+    snapshot: DisplaySnapshot,
+    // Per snapshot row: text must be rebuilt before the next frame
+    dirty_rows: Vec<bool>,
     colored_text_renderer: ColoredTextRenderer,
     font_size: f32,
     pub cell_width: f32,
     pub cell_height: f32,
+    // Gap between the window edge and the first cell (appearance.window_padding), logical px
+    pub padding: f32,
+    // Window DPI scale; cell metrics are in physical pixels
+    pub scale_factor: f32,
     // Selection (for visual highlighting)
-    pub selection: Option<((usize, usize), (usize, usize))>,
+    // Selected regions as (start, end) cells in absolute rows (scrollback first);
+    // several when e.g. all search matches are selected
+    pub selections: Vec<((usize, usize), (usize, usize))>,
+    // Search matches as (start, end) cells in absolute rows, and which one
+    // search is on; drawn in the palette's search colors
+    pub search_matches: Vec<((usize, usize), (usize, usize))>,
+    pub current_match: Option<usize>,
     // Selection pipeline state
     sel_pipeline: RenderPipeline,
     sel_bindgroup: BindGroup,
@@ -55,14 +212,33 @@ pub struct Renderer {
     // Viewport controls for smooth scrolling
     pub viewport_top_row: usize,
     pub y_offset_px: f32,
-    // Cursor position
-    pub cursor_x: usize,
-    pub cursor_y: usize,
-    pub cursor_visible: bool,
+    // Visual bell: brighten the background while set
+    pub visual_bell: bool,
+    // Absolute rows of shell-integration marks (prompts and SetMarks), drawn
+    // as markers in the left gutter
+    pub mark_rows: Vec<usize>,
+    // Theme colors cells resolve against; the background is the clear color
+    pub palette: Palette,
+    // Codepoints the font could not shape, reported once per session
+    missing_glyphs: MissingGlyphReport,
+    bidi: BidiMode,
+    // Per display row, the column each cell is drawn at (IsolateRuns only)
+    row_maps: Vec<Option<Vec<usize>>>,
+    // Message drawn on a band across the middle of the window, e.g. a close confirmation
+    overlay: Option<(String, TextBuffer)>,
 }
 
 impl Renderer {
-    pub async fn new(window: Arc<Window>) -> Result<Self> {
+    /// Set up the GPU, fonts and pipelines, recording each phase in `profile`
+    pub async fn new(window: Arc<Window>, padding: f32, profile: &mut StartupProfile) -> Result<Self> {
+        // Font discovery doesn't need the GPU; do it while the adapter comes up
+        let fonts = std::thread::spawn(|| {
+            let timer = PerfTimer::new("font system (worker)");
+            let font_system = FontSystem::new();
+            (font_system, timer.elapsed())
+        });
+        
+        let gpu_timer = PerfTimer::new("gpu adapter + device");
         let instance = Instance::new(wgpu::InstanceDescriptor {
             backends: wgpu::Backends::METAL,
             ..Default::default()
@@ -79,16 +255,7 @@ impl Renderer {
             .await
             .ok_or_else(|| anyhow::anyhow!("Failed to find suitable adapter"))?;
         
-        let (device, queue) = adapter
-            .request_device(
-                &wgpu::DeviceDescriptor {
-                    label: Some("The-Dev-Terminal Device"),
-                    required_features: wgpu::Features::empty(),
-                    required_limits: wgpu::Limits::default(),
-                },
-                None,
-            )
-            .await?;
+        let (device, queue) = request_device(&adapter).await?;
         
         let surface_caps = surface.get_capabilities(&adapter);
         let surface_format = surface_caps
@@ -111,11 +278,66 @@ impl Renderer {
         };
         
         surface.configure(&device, &config);
+        profile.record(&gpu_timer);
         
         // Initialize text rendering
-        let mut font_system = FontSystem::new();
+        let wait_timer = PerfTimer::new("font system wait");
+        let (font_system, font_time) = fonts
+            .join()
+            .map_err(|_| anyhow::anyhow!("Font system initialization panicked"))?;
+        profile.record_duration("font system (worker)", font_time);
+        profile.record(&wait_timer);
+        
+        let target = RenderTarget::Window(surface);
+        let scale_factor = window.scale_factor() as f32;
+        Ok(Self::with_target(device, queue, target, config, font_system, scale_factor, padding, profile))
+    }
+    
+    /// Render `width`×`height` physical pixels into an offscreen RGBA texture
+    /// instead of a window, on any available backend; read frames back with
+    /// `capture_frame`. Fails when there is no usable GPU adapter
+    pub async fn new_headless(width: u32, height: u32) -> Result<Self> {
+        let instance = Instance::new(wgpu::InstanceDescriptor::default());
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::HighPerformance,
+                compatible_surface: None,
+                force_fallback_adapter: false,
+            })
+            .await
+            .ok_or_else(|| anyhow::anyhow!("Failed to find suitable adapter"))?;
+        let (device, queue) = request_device(&adapter).await?;
+        
+        let config = SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            format: TextureFormat::Rgba8UnormSrgb,
+            width: width.max(1),
+            height: height.max(1),
+            present_mode: wgpu::PresentMode::AutoVsync,
+            alpha_mode: CompositeAlphaMode::Opaque,
+            view_formats: Vec::new(),
+            desired_maximum_frame_latency: 2,
+        };
+        let target = RenderTarget::Offscreen(offscreen_texture(&device, &config));
+        let mut profile = StartupProfile::new();
+        Ok(Self::with_target(device, queue, target, config, FontSystem::new(), 1.0, 0.0, &mut profile))
+    }
+    
+    // Pipelines, fonts and state shared by window and offscreen renderers
+    #[allow(clippy::too_many_arguments)]
+    fn with_target(
+        device: Device,
+        queue: Queue,
+        target: RenderTarget,
+        config: SurfaceConfiguration,
+        mut font_system: FontSystem,
+        scale_factor: f32,
+        padding: f32,
+        profile: &mut StartupProfile,
+    ) -> Self {
+        let pipelines_timer = PerfTimer::new("pipelines");
         let swash_cache = SwashCache::new();
-        let mut text_atlas = TextAtlas::new(&device, &queue, surface_format);
+        let mut text_atlas = TextAtlas::new(&device, &queue, config.format);
         let text_renderer = GlyphonRenderer::new(
             &mut text_atlas,
             &device,
@@ -124,13 +346,11 @@ impl Renderer {
         );
         
         let font_size = 18.0;
-        let cell_width = font_size * 0.6;
-        let cell_height = font_size * 1.25;
-        
-        let mut text_buffer = TextBuffer::new(&mut font_system, Metrics::new(font_size, cell_height));
-        text_buffer.set_size(&mut font_system, size.width as f32, size.height as f32);
+        let cell_width = font_size * 0.6 * scale_factor;
+        let cell_height = font_size * 1.25 * scale_factor;
         
-        let pending_text = "Hello from The Dev Terminal\n(type will show once PTY is wired)".to_string();
+        let metrics = Metrics::new(font_size * scale_factor, cell_height);
+        let baseline = primary_baseline(&mut font_system, metrics);
         
         // --- selection pipeline setup ---
         let shader = device.create_shader_module(ShaderModuleDescriptor {
@@ -221,27 +441,31 @@ impl Renderer {
         });
         
         // Create the colored text renderer
-        let colored_text_renderer = ColoredTextRenderer::new(&device, &queue, config.format);
+        let colored_text_renderer = ColoredTextRenderer::new(&device, config.format, metrics);
+        profile.record(&pipelines_timer);
         
-        Ok(Self {
+        Self {
             device,
             queue,
-            surface,
+            target,
             config,
             font_system,
             swash_cache,
             text_renderer,
             text_atlas,
-            text_buffer,
-            pending_text,
-            pending_cells: Vec::new(),
-            pending_cols: 0,
-            pending_rows: 0,
+            text_rows: Vec::new(),
+            baseline,
+            snapshot: DisplaySnapshot::default(),
+            dirty_rows: Vec::new(),
             colored_text_renderer,
             font_size,
             cell_width,
             cell_height,
-            selection: None,
+            padding: padding.max(0.0),
+            scale_factor,
+            selections: Vec::new(),
+            search_matches: Vec::new(),
+            current_match: None,
             sel_pipeline,
             _sel_bind_layout: sel_bind_layout,
             sel_bindgroup,
@@ -250,39 +474,80 @@ impl Renderer {
             sel_vertices: Vec::with_capacity(6 * 4096),
             viewport_top_row: 0,
             y_offset_px: 0.0,
-            cursor_x: 0,
-            cursor_y: 0,
-            cursor_visible: true,
-        })
+            visual_bell: false,
+            mark_rows: Vec::new(),
+            palette: Palette::default(),
+            missing_glyphs: MissingGlyphReport::new(),
+            bidi: BidiMode::Logical,
+            row_maps: Vec::new(),
+            overlay: None,
+        }
     }
     
     pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
         if new_size.width > 0 && new_size.height > 0 {
             self.config.width = new_size.width;
             self.config.height = new_size.height;
-            self.surface.configure(&self.device, &self.config);
+            match &mut self.target {
+                RenderTarget::Window(surface) => surface.configure(&self.device, &self.config),
+                RenderTarget::Offscreen(texture) => *texture = offscreen_texture(&self.device, &self.config),
+            }
             
-            // Update text buffer size
-            self.text_buffer.set_size(
-                &mut self.font_system,
-                new_size.width as f32,
-                new_size.height as f32
-            );
+            // Update row buffer widths
+            for piece in self.text_rows.iter_mut().flat_map(|r| r.pieces.iter_mut()) {
+                piece.buffer.set_size(&mut self.font_system, new_size.width as f32, self.cell_height);
+            }
             
             // Update screen UBO for selection shader
             let screen_data = [new_size.width as f32, new_size.height as f32];
             self.queue.write_buffer(&self.sel_screen_ubo, 0, bytemuck::cast_slice(&screen_data));
+            self.colored_text_renderer.update_screen_size(
+                &self.queue,
+                new_size.width as f32,
+                new_size.height as f32
+            );
         }
     }
     
-    pub fn set_text(&mut self, s: impl Into<String>) {
-        self.pending_text = s.into();
+    /// Draw this viewport from the next frame on, every row of it new
+    pub fn set_snapshot(&mut self, snapshot: DisplaySnapshot) {
+        let rows: Vec<usize> = (0..snapshot.rows).collect();
+        self.set_snapshot_rows(snapshot, &rows);
     }
     
-    pub fn set_cells(&mut self, cells: Vec<Cell>, cols: usize, rows: usize) {
-        self.pending_cells = cells;
-        self.pending_cols = cols;
-        self.pending_rows = rows;
+    /// Like `set_snapshot` when only `rows` differ from the last snapshot;
+    /// the text of the other rows is reused as is
+    pub fn set_snapshot_rows(&mut self, snapshot: DisplaySnapshot, rows: &[usize]) {
+        self.row_maps = match self.bidi {
+            BidiMode::Logical => Vec::new(),
+            BidiMode::IsolateRuns if snapshot.cols == 0 => Vec::new(),
+            BidiMode::IsolateRuns => snapshot.cells.chunks(snapshot.cols).map(isolate_runs).collect(),
+        };
+        self.dirty_rows.resize(snapshot.rows, true);
+        for &row in rows {
+            if let Some(d) = self.dirty_rows.get_mut(row) {
+                *d = true;
+            }
+        }
+        self.snapshot = snapshot;
+    }
+    
+    pub fn set_bidi(&mut self, mode: BidiMode) {
+        self.bidi = mode;
+        // Cached rows were split for the old mode
+        self.text_rows.clear();
+        self.dirty_rows.fill(true);
+    }
+    
+    /// Column where the cell at `col` of display row `row` is drawn. Runs are
+    /// reversed in place, so this also maps a drawn column back to its cell.
+    pub fn display_col(&self, row: usize, col: usize) -> usize {
+        self.row_maps
+            .get(row)
+            .and_then(|m| m.as_ref())
+            .and_then(|m| m.get(col))
+            .copied()
+            .unwrap_or(col)
     }
     
     pub fn set_viewport(&mut self, top_row: usize, y_offset_px: f32) {
@@ -290,12 +555,68 @@ impl Renderer {
         self.y_offset_px = y_offset_px;
     }
     
-    pub fn set_cursor(&mut self, x: usize, y: usize, visible: bool) {
-        self.cursor_x = x;
-        self.cursor_y = y;
-        self.cursor_visible = visible;
+    /// Cursor (col, row) in the viewport, None while it is scrolled out of view
+    pub fn cursor(&self) -> Option<(usize, usize)> {
+        self.snapshot.cursor
+    }
+    
+    /// Move the cursor without a new snapshot (cursor moves don't dirty rows)
+    pub fn set_cursor(&mut self, cursor: Option<(usize, usize)>) {
+        self.snapshot.cursor = cursor;
+    }
+    
+    /// Show `text` (one or more lines) on a band across the middle of the
+    /// window, or remove it
+    pub fn set_overlay(&mut self, text: Option<&str>) {
+        let Some(text) = text else {
+            self.overlay = None;
+            return;
+        };
+        if self.overlay.as_ref().is_some_and(|(shown, _)| shown == text) {
+            return;
+        }
+        let metrics = Metrics::new(self.font_size * self.scale_factor, self.cell_height);
+        let mut buffer = TextBuffer::new(&mut self.font_system, metrics);
+        let lines = text.lines().count().max(1);
+        buffer.set_size(&mut self.font_system, self.config.width as f32, lines as f32 * self.cell_height);
+        buffer.set_text(&mut self.font_system, text, Attrs::new().family(cosmic_text::Family::Monospace), Shaping::Advanced);
+        self.overlay = Some((text.to_string(), buffer));
     }
     
+    // Rows covered by the overlay band: its text lines, centered, with one row of margin each side
+    fn overlay_rows(&self) -> Option<std::ops::Range<usize>> {
+        let (text, _) = self.overlay.as_ref()?;
+        let lines = text.lines().count().max(1);
+        let rows = ((self.config.height as f32 - 2.0 * self.padding_px()) / self.cell_height).max(1.0) as usize;
+        let start = rows.saturating_sub(lines + 2) / 2;
+        Some(start..start + lines + 2)
+    }
+    
+    pub fn set_visual_bell(&mut self, on: bool) {
+        self.visual_bell = on;
+    }
+    
+    pub fn missing_glyphs(&self) -> &MissingGlyphReport {
+        &self.missing_glyphs
+    }
+
+    /// One-time notice once enough glyphs have fallen back to notdef
+    pub fn take_missing_glyph_notice(&mut self) -> Option<String> {
+        self.missing_glyphs.take_notice()
+    }
+
+    /// Switch theme colors; every row is redrawn in them
+    pub fn set_palette(&mut self, palette: Palette) {
+        self.palette = palette;
+        // Glyph colors are set when a row is shaped
+        self.text_rows.clear();
+        self.dirty_rows.fill(true);
+    }
+
+    pub fn set_padding(&mut self, px: f32) {
+        self.padding = px.max(0.0);
+    }
+
     pub fn font_size(&self) -> f32 {
         self.font_size
     }
@@ -304,23 +625,111 @@ impl Renderer {
         const MIN_PT: f32 = 8.0;
         const MAX_PT: f32 = 48.0;
         
-        let pt = pt.clamp(MIN_PT, MAX_PT);
-        self.font_size = pt;
-        self.cell_width = pt * 0.6;
-        self.cell_height = pt * 1.25;
+        self.font_size = pt.clamp(MIN_PT, MAX_PT);
+        self.update_metrics();
+    }
+    
+    /// Apply a new DPI scale (e.g. the window moved to another display)
+    pub fn set_scale_factor(&mut self, scale: f32) {
+        if scale > 0.0 && scale != self.scale_factor {
+            self.scale_factor = scale;
+            self.update_metrics();
+        }
+    }
+    
+    /// Padding in physical pixels
+    pub fn padding_px(&self) -> f32 {
+        self.padding * self.scale_factor
+    }
+    
+    // Recompute physical cell metrics from the point size and scale
+    fn update_metrics(&mut self) {
+        let px = self.font_size * self.scale_factor;
+        self.cell_width = px * 0.6;
+        self.cell_height = px * 1.25;
         
-        // Update glyphon buffer metrics
-        self.text_buffer.set_metrics(
-            &mut self.font_system,
-            Metrics::new(self.font_size, self.cell_height)
-        );
+        // Update glyphon buffer metrics; every row is one cell tall
+        let metrics = Metrics::new(px, self.cell_height);
+        for piece in self.text_rows.iter_mut().flat_map(|r| r.pieces.iter_mut()) {
+            piece.buffer.set_metrics(&mut self.font_system, metrics);
+            piece.buffer.set_size(&mut self.font_system, self.config.width as f32, self.cell_height);
+        }
+        self.baseline = primary_baseline(&mut self.font_system, metrics);
+        self.colored_text_renderer.set_metrics(metrics);
+    }
+    
+    // Leading column and width in cells of the character under the cursor
+    fn cursor_span(&self, (x, y): (usize, usize)) -> (usize, usize) {
+        let row = self.snapshot.row(y);
+        let cell = |col: usize| row.get(col);
+        if x > 0 && cell(x).is_some_and(|c| c.is_wide_trailer()) {
+            (x - 1, 2)
+        } else if cell(x + 1).is_some_and(|c| c.is_wide_trailer()) {
+            (x, 2)
+        } else {
+            (x, 1)
+        }
+    }
+    
+    // Reshape only the dirty rows whose text changed since the last frame
+    fn sync_text_rows(&mut self) {
+        let metrics = Metrics::new(self.font_size * self.scale_factor, self.cell_height);
         
-        // Recompute buffer size to the window
-        self.text_buffer.set_size(
-            &mut self.font_system,
-            self.config.width as f32,
-            self.config.height as f32
-        );
+        self.text_rows.truncate(self.snapshot.rows);
+        for i in 0..self.snapshot.rows {
+            if i == self.text_rows.len() {
+                self.text_rows.push(RowText { line: String::new(), fg: Vec::new(), pieces: Vec::new() });
+            } else if !self.dirty_rows[i] {
+                continue;
+            }
+            self.dirty_rows[i] = false;
+            let cells = self.snapshot.row(i);
+            let text = cells_text(cells, self.snapshot.cols);
+            let line = text.trim_end_matches('\n');
+            let fg: Vec<CellRgb> = cells.iter().map(|c| self.palette.text_fg(&c.attrs())).collect();
+            if self.text_rows[i].line == line && self.text_rows[i].fg == fg {
+                continue;
+            }
+            
+            // Reuse this row's buffers; shaping is the expensive part anyway
+            let mut spare: Vec<TextBuffer> = self.text_rows[i].pieces.drain(..).map(|p| p.buffer).collect();
+            let mut pieces = Vec::new();
+            let map = self.row_maps.get(i).and_then(|m| m.as_deref());
+            for (col, cells, from) in split_wide(line, map) {
+                let mut buffer = spare.pop().unwrap_or_else(|| {
+                    let mut buf = TextBuffer::new(&mut self.font_system, metrics);
+                    buf.set_size(&mut self.font_system, self.config.width as f32, self.cell_height);
+                    buf
+                });
+                let spans = fg_spans(&self.snapshot.row(i)[from], &self.palette);
+                buffer.set_rich_text(
+                    &mut self.font_system,
+                    spans.iter().map(|(text, fg)| {
+                        let attrs = Attrs::new()
+                            .family(cosmic_text::Family::Monospace)
+                            .color(cosmic_text::Color::rgb(fg.r, fg.g, fg.b));
+                        (text.as_str(), attrs)
+                    }),
+                    Shaping::Advanced,
+                );
+                
+                // Glyph id 0 is the font's notdef (tofu) glyph
+                for run in buffer.layout_runs() {
+                    for glyph in run.glyphs.iter().filter(|g| g.glyph_id == 0) {
+                        if let Some(ch) = run.text.get(glyph.start..glyph.end).and_then(|t| t.chars().next()) {
+                            self.missing_glyphs.record_missing(ch);
+                        }
+                    }
+                }
+                pieces.push(TextPiece { col, cells, buffer });
+            }
+            
+            let row = &mut self.text_rows[i];
+            row.pieces = pieces;
+            row.fg = fg;
+            row.line.clear();
+            row.line.push_str(line);
+        }
     }
     
     #[inline]
@@ -369,14 +778,32 @@ impl Renderer {
         self.sel_vertices.clear();
     }
     
-    pub fn render_frame(&mut self) -> Result<()> {
-        let output = self.surface.get_current_texture()?;
-        let view = output.texture.create_view(&TextureViewDescriptor::default());
+    pub fn render_frame(&mut self) -> Result<(), RenderError> {
+        let (output, view) = match &self.target {
+            RenderTarget::Window(surface) => {
+                let frame = surface.get_current_texture()?;
+                let view = frame.texture.create_view(&TextureViewDescriptor::default());
+                (Some(frame), view)
+            }
+            RenderTarget::Offscreen(texture) => (None, texture.create_view(&TextureViewDescriptor::default())),
+        };
         let mut encoder = self.device.create_command_encoder(&CommandEncoderDescriptor { 
             label: Some("encoder") 
         });
 
-        // 1) clear background
+        let pad = self.padding_px();
+
+        // 1) clear background (brighter while the visual bell is flashing)
+        let clear = if self.visual_bell {
+            Color { r: 0.25, g: 0.25, b: 0.27, a: 1.0 }
+        } else {
+            Color {
+                r: self.palette.background.r as f64 / 255.0,
+                g: self.palette.background.g as f64 / 255.0,
+                b: self.palette.background.b as f64 / 255.0,
+                a: 1.0,
+            }
+        };
         {
             let _rp = encoder.begin_render_pass(&RenderPassDescriptor {
                 label: Some("clear"),
@@ -384,7 +811,7 @@ impl Renderer {
                     view: &view, 
                     resolve_target: None,
                     ops: Operations { 
-                        load: LoadOp::Clear(Color { r: 0.06, g: 0.06, b: 0.07, a: 1.0 }), 
+                        load: LoadOp::Clear(clear), 
                         store: StoreOp::Store 
                     },
                 })],
@@ -395,26 +822,27 @@ impl Renderer {
         }
 
         // 2) Draw colored cell backgrounds
-        if !self.pending_cells.is_empty() {
+        if !self.snapshot.cells.is_empty() {
             let visible_rows = (self.config.height as f32 / self.cell_height) as usize + 2;
             let visible_cols = (self.config.width as f32 / self.cell_width) as usize + 2;
             
-            for row in 0..visible_rows.min(self.pending_rows) {
-                for col in 0..visible_cols.min(self.pending_cols) {
-                    let idx = row * self.pending_cols + col;
-                    if idx >= self.pending_cells.len() {
+            for row in 0..visible_rows.min(self.snapshot.rows) {
+                for col in 0..visible_cols.min(self.snapshot.cols) {
+                    let idx = row * self.snapshot.cols + col;
+                    if idx >= self.snapshot.cells.len() {
                         break;
                     }
                     
-                    let cell = &self.pending_cells[idx];
-                    // Only draw background if it's not the default black
-                    if cell.bg.r != 0 || cell.bg.g != 0 || cell.bg.b != 0 {
-                        let x = 12.0 + col as f32 * self.cell_width;
-                        let y = 12.0 + row as f32 * self.cell_height + self.y_offset_px;
+                    let cell = &self.snapshot.cells[idx];
+                    // Default-background cells are transparent so the clear color shows through
+                    if cell.bg != CellColor::Default {
+                        let x = pad + self.display_col(row, col) as f32 * self.cell_width;
+                        let y = pad + row as f32 * self.cell_height + self.y_offset_px;
+                        let bg = self.palette.bg(cell.bg);
                         let color = [
-                            cell.bg.r as f32 / 255.0,
-                            cell.bg.g as f32 / 255.0,
-                            cell.bg.b as f32 / 255.0,
+                            bg.r as f32 / 255.0,
+                            bg.g as f32 / 255.0,
+                            bg.b as f32 / 255.0,
                             1.0,
                         ];
                         self.push_rect(x, y, self.cell_width, self.cell_height, color);
@@ -423,62 +851,125 @@ impl Renderer {
             }
         }
         
+        // Search matches, the current one in its own color
+        let last_visible_col = ((self.config.width as f32 / self.cell_width) as usize).saturating_sub(1);
+        for i in 0..self.search_matches.len() {
+            let (a, b) = self.search_matches[i];
+            let c = if self.current_match == Some(i) { self.palette.search_current_match } else { self.palette.search_match };
+            let color = [c.r as f32 / 255.0, c.g as f32 / 255.0, c.b as f32 / 255.0, 1.0];
+            for (row, start_col, end_col) in selection_rows(a, b, self.viewport_top_row, self.snapshot.rows, last_visible_col) {
+                for col in start_col..=end_col {
+                    let x = pad + self.display_col(row, col) as f32 * self.cell_width;
+                    let y = pad + row as f32 * self.cell_height + self.y_offset_px;
+                    self.push_rect(x, y, self.cell_width, self.cell_height, color);
+                }
+            }
+        }
+        
+        // Gutter markers in the left padding; they sit on the first cell when there is no padding
+        let fg = self.palette.foreground;
+        let mark_color = [fg.r as f32 / 255.0, fg.g as f32 / 255.0, fg.b as f32 / 255.0, 0.5];
+        let mark_w = (pad / 4.0).max(2.0 * self.scale_factor);
+        for i in 0..self.mark_rows.len() {
+            let Some(row) = self.mark_rows[i].checked_sub(self.viewport_top_row).filter(|&r| r < self.snapshot.rows) else {
+                continue;
+            };
+            let x = ((pad - mark_w) / 2.0).max(0.0);
+            let y = pad + row as f32 * self.cell_height + self.y_offset_px;
+            self.push_rect(x, y, mark_w, self.cell_height, mark_color);
+        }
+        
         // 3) Draw cursor if visible
-        if self.cursor_visible {
-            let cursor_x = 12.0 + self.cursor_x as f32 * self.cell_width;
-            let cursor_y = 12.0 + self.cursor_y as f32 * self.cell_height + self.y_offset_px;
+        if let Some(cursor) = self.snapshot.cursor {
+            // On either half of a wide character the cursor covers both cells
+            let (col, cells) = self.cursor_span(cursor);
+            let cursor_x = pad + self.display_col(cursor.1, col) as f32 * self.cell_width;
+            let cursor_y = pad + cursor.1 as f32 * self.cell_height + self.y_offset_px;
             // Draw cursor as a bright block
-            self.push_rect(cursor_x, cursor_y, self.cell_width, self.cell_height, [0.9, 0.9, 0.9, 0.8]);
+            self.push_rect(cursor_x, cursor_y, self.cell_width * cells as f32, self.cell_height, [0.9, 0.9, 0.9, 0.8]);
         }
         
         // 4) push selection rects (with viewport offset)
-        if let Some(((x0, y0), (x1, y1))) = self.selection {
-            let minx = x0.min(x1);
-            let maxx = x0.max(x1);
-            let miny = y0.min(y1);
-            let maxy = y0.max(y1);
-            
-            for row in miny..=maxy {
-                let start_col = if row == miny { minx } else { 0 };
-                let end_col = if row == maxy { maxx } else { 
-                    (self.config.width as f32 / self.cell_width) as usize - 1 
-                };
-                
+        for i in 0..self.selections.len() {
+            // Rows are absolute; draw the ones inside the viewport
+            let (a, b) = self.selections[i];
+            for (row, start_col, end_col) in selection_rows(a, b, self.viewport_top_row, self.snapshot.rows, last_visible_col) {
                 for col in start_col..=end_col {
-                    let x = 12.0 + col as f32 * self.cell_width;
+                    let x = pad + self.display_col(row, col) as f32 * self.cell_width;
                     // Apply y_offset_px for smooth scrolling
-                    let y = 12.0 + row as f32 * self.cell_height + self.y_offset_px;
+                    let y = pad + row as f32 * self.cell_height + self.y_offset_px;
                     // Semi-transparent blue selection background
                     self.push_rect(x, y, self.cell_width, self.cell_height, [0.2, 0.4, 0.8, 0.3]);
                 }
             }
         }
         
+        // The overlay band hides the rows under it
+        let overlay_rows = self.overlay_rows();
+        if let Some(band) = &overlay_rows {
+            let y = pad + band.start as f32 * self.cell_height;
+            let h = band.len() as f32 * self.cell_height;
+            self.push_rect(0.0, y, self.config.width as f32, h, [0.12, 0.12, 0.14, 1.0]);
+        }
+        
         // Flush selection and cursor rectangles
         self.flush_rects(&mut encoder, &view);
 
-        // 5) draw text on top
-        // For now, use glyphon for text rendering until we implement proper glyph atlas
-        self.text_buffer.set_text(
-            &mut self.font_system,
-            &self.pending_text,
-            Attrs::new().family(cosmic_text::Family::Monospace),
-            Shaping::Advanced,
-        );
-        
-        let text_areas = vec![TextArea {
-            buffer: &self.text_buffer,
-            left: 12.0,
-            top: 12.0 + self.y_offset_px,
-            scale: 1.0,
-            bounds: TextBounds {
-                left: 0,
-                top: 0,
-                right: self.config.width as i32,
-                bottom: self.config.height as i32,
-            },
-            default_color: glyphon::Color::rgb(255, 255, 255),
-        }];
+        // 5) draw text on top, one area per row so fallback fonts (emoji, CJK)
+        //    with a different ascent can't move the baseline; glyphs taller
+        //    than the cell are clipped to it
+        self.sync_text_rows();
+        let (width, height) = (self.config.width as i32, self.config.height as i32);
+        let fg = self.palette.foreground;
+        let text_color = glyphon::Color::rgb(fg.r, fg.g, fg.b);
+        let mut text_areas: Vec<TextArea> = self.text_rows
+            .iter()
+            .enumerate()
+            .filter(|(row, _)| !overlay_rows.as_ref().is_some_and(|band| band.contains(row)))
+            .flat_map(|(row, text)| text.pieces.iter().map(move |piece| (row, piece)))
+            .map(|(row, piece)| {
+                let row_top = pad + row as f32 * self.cell_height + self.y_offset_px;
+                let run = piece.buffer.layout_runs().next();
+                let shift = run
+                    .as_ref()
+                    .map(|run| self.baseline - (run.line_y - run.line_top))
+                    .unwrap_or(0.0);
+                // Standalone clusters are centered on their cells
+                let center = match &run {
+                    Some(run) if piece.cells > 0 => ((piece.cells as f32 * self.cell_width - run.line_w) / 2.0).max(0.0),
+                    _ => 0.0,
+                };
+                TextArea {
+                    buffer: &piece.buffer,
+                    left: pad + piece.col as f32 * self.cell_width + center,
+                    top: row_top + shift,
+                    scale: 1.0,
+                    bounds: TextBounds {
+                        left: 0,
+                        top: (row_top.floor() as i32).clamp(0, height),
+                        right: width,
+                        bottom: ((row_top + self.cell_height).ceil() as i32).clamp(0, height),
+                    },
+                    default_color: text_color,
+                }
+            })
+            .collect();
+        if let (Some(band), Some((_, buffer))) = (&overlay_rows, &self.overlay) {
+            let top = pad + (band.start + 1) as f32 * self.cell_height;
+            text_areas.push(TextArea {
+                buffer,
+                left: pad + self.cell_width,
+                top,
+                scale: 1.0,
+                bounds: TextBounds {
+                    left: 0,
+                    top: (top.floor() as i32).clamp(0, height),
+                    right: width,
+                    bottom: ((top + (band.len() - 2) as f32 * self.cell_height).ceil() as i32).clamp(0, height),
+                },
+                default_color: glyphon::Color::rgb(255, 255, 255),
+            });
+        }
         
         self.text_renderer.prepare(
             &self.device,
@@ -491,7 +982,7 @@ impl Renderer {
             },
             text_areas,
             &mut self.swash_cache,
-        )?;
+        ).map_err(|e| RenderError::Other(e.into()))?;
         
         {
             let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
@@ -509,13 +1000,114 @@ impl Renderer {
                 timestamp_writes: None,
             });
             
-            self.text_renderer.render(&self.text_atlas, &mut render_pass)?;
+            self.text_renderer.render(&self.text_atlas, &mut render_pass).map_err(|e| RenderError::Other(e.into()))?;
         }
 
         // 4) submit
         self.queue.submit([encoder.finish()]);
-        output.present();
+        if let Some(frame) = output {
+            frame.present();
+        }
         
         Ok(())
     }
-}
\ No newline at end of file
+    
+    /// RGBA pixels of the last frame, row-major, `config.width` × `config.height`;
+    /// only offscreen renderers can be read back
+    pub fn capture_frame(&self) -> Result<Vec<u8>> {
+        let RenderTarget::Offscreen(texture) = &self.target else {
+            anyhow::bail!("Only headless renderers can capture frames");
+        };
+        let (width, height) = (self.config.width, self.config.height);
+        // Buffer rows must be padded to the copy alignment
+        let row_bytes = width * 4;
+        let padded = row_bytes.div_ceil(COPY_BYTES_PER_ROW_ALIGNMENT) * COPY_BYTES_PER_ROW_ALIGNMENT;
+        let buffer = self.device.create_buffer(&BufferDescriptor {
+            label: Some("capture"),
+            size: (padded * height) as BufferAddress,
+            usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        let mut encoder = self.device.create_command_encoder(&CommandEncoderDescriptor { label: Some("capture") });
+        encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            ImageCopyBuffer {
+                buffer: &buffer,
+                layout: ImageDataLayout { offset: 0, bytes_per_row: Some(padded), rows_per_image: None },
+            },
+            Extent3d { width, height, depth_or_array_layers: 1 },
+        );
+        self.queue.submit([encoder.finish()]);
+        
+        let slice = buffer.slice(..);
+        let (tx, rx) = std::sync::mpsc::channel();
+        slice.map_async(MapMode::Read, move |result| {
+            let _ = tx.send(result);
+        });
+        self.device.poll(Maintain::Wait);
+        rx.recv()??;
+        let mapped = slice.get_mapped_range();
+        let pixels = mapped
+            .chunks(padded as usize)
+            .flat_map(|row| &row[..row_bytes as usize])
+            .copied()
+            .collect();
+        drop(mapped);
+        buffer.unmap();
+        Ok(pixels)
+    }
+}
+
+/// Pixels of a `width`-wide RGBA frame from `capture_frame` that differ from
+/// `background`, within columns `xs` and rows `ys` (clipped to the frame)
+pub fn drawn_pixels(frame: &[u8], width: u32, xs: std::ops::Range<u32>, ys: std::ops::Range<u32>, background: [u8; 4]) -> usize {
+    let height = frame.len() as u32 / 4 / width.max(1);
+    let mut drawn = 0;
+    for y in ys.start..ys.end.min(height) {
+        for x in xs.start..xs.end.min(width) {
+            let i = ((y * width + x) * 4) as usize;
+            if frame[i..i + 4] != background {
+                drawn += 1;
+            }
+        }
+    }
+    drawn
+}
+
+async fn request_device(adapter: &Adapter) -> Result<(Device, Queue)> {
+    Ok(adapter
+        .request_device(
+            &wgpu::DeviceDescriptor {
+                label: Some("The-Dev-Terminal Device"),
+                required_features: wgpu::Features::empty(),
+                required_limits: wgpu::Limits::default(),
+            },
+            None,
+        )
+        .await?)
+}
+
+// Render target texture for headless renderers, sized and formatted like `config`
+fn offscreen_texture(device: &Device, config: &SurfaceConfiguration) -> Texture {
+    device.create_texture(&TextureDescriptor {
+        label: Some("offscreen"),
+        size: Extent3d { width: config.width, height: config.height, depth_or_array_layers: 1 },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: TextureDimension::D2,
+        format: config.format,
+        usage: config.usage,
+        view_formats: &[],
+    })
+}
+
+// Where the primary monospace font puts its baseline inside a row
+fn primary_baseline(font_system: &mut FontSystem, metrics: Metrics) -> f32 {
+    let mut buf = TextBuffer::new(font_system, metrics);
+    buf.set_size(font_system, metrics.font_size * 4.0, metrics.line_height);
+    buf.set_text(font_system, "M", Attrs::new().family(cosmic_text::Family::Monospace), Shaping::Advanced);
+    buf.layout_runs()
+        .next()
+        .map(|run| run.line_y - run.line_top)
+        .unwrap_or(metrics.line_height * 0.8)
+}
@@ -0,0 +1,43 @@
+use cosmic_text::{FontSystem, Metrics, SwashCache};
+use the_dev_terminal_ui_wgpu::glyph_atlas::{AtlasPacker, GlyphAtlas, GlyphKey};
+
+fn key(ch: char) -> GlyphKey {
+    GlyphKey { ch, bold: false, italic: false }
+}
+
+#[test]
+fn packer_fills_shelves_until_full() {
+    let mut packer = AtlasPacker::new(32, 20);
+    assert_eq!(packer.allocate(10, 8), Some((0, 0)));
+    assert_eq!(packer.allocate(10, 6), Some((11, 0)));
+    // No room left on the first shelf: the next starts below its tallest glyph
+    assert_eq!(packer.allocate(12, 8), Some((0, 9)));
+    assert_eq!(packer.allocate(4, 12), None);
+    assert_eq!(packer.allocate(40, 1), None);
+}
+
+#[test]
+fn different_characters_get_distinct_uv_rects() {
+    let mut font_system = FontSystem::new();
+    let mut swash_cache = SwashCache::new();
+    let mut atlas = GlyphAtlas::new(256, Metrics::new(18.0, 22.5));
+    
+    let a = atlas.glyph(&mut font_system, &mut swash_cache, key('a')).expect("'a' rasterizes");
+    let b = atlas.glyph(&mut font_system, &mut swash_cache, key('b')).expect("'b' rasterizes");
+    assert_ne!(a.uv, b.uv);
+    for glyph in [a, b] {
+        assert!(glyph.uv.u0 < glyph.uv.u1 && glyph.uv.v0 < glyph.uv.v1, "{:?}", glyph.uv);
+        assert!(glyph.width > 0.0 && glyph.height > 0.0);
+    }
+    // Rects don't overlap
+    assert!(a.uv.u1 <= b.uv.u0 || b.uv.u1 <= a.uv.u0 || a.uv.v1 <= b.uv.v0 || b.uv.v1 <= a.uv.v0);
+    
+    // Cached after the first rasterization, with coverage in the atlas
+    assert!(atlas.take_dirty());
+    assert_eq!(atlas.glyph(&mut font_system, &mut swash_cache, key('a')), Some(a));
+    assert!(!atlas.take_dirty());
+    assert!(atlas.pixels().iter().any(|&p| p > 0));
+    
+    // Nothing to draw for a space
+    assert_eq!(atlas.glyph(&mut font_system, &mut swash_cache, key(' ')), None);
+}
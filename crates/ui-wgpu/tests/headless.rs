@@ -0,0 +1,159 @@
+//! Offscreen rendering: frames drawn without a window and read back as pixels.
+//!
+//! Needs a GPU adapter (or a software one); where there is none the tests
+//! report that and pass without drawing.
+
+use the_dev_terminal_core::grid::{CellColor, Color, Grid};
+use the_dev_terminal_core::Terminal;
+use the_dev_terminal_core::palette::Palette;
+use the_dev_terminal_ui_wgpu::{drawn_pixels, fg_spans, selection_rows, Renderer};
+
+async fn headless(width: u32, height: u32) -> Option<Renderer> {
+    match Renderer::new_headless(width, height).await {
+        Ok(r) => Some(r),
+        Err(e) => {
+            eprintln!("skipping: {e}");
+            None
+        }
+    }
+}
+
+// RGBA of the pixel at (x, y)
+fn pixel(frame: &[u8], width: u32, x: u32, y: u32) -> [u8; 4] {
+    let i = ((y * width + x) * 4) as usize;
+    frame[i..i + 4].try_into().unwrap()
+}
+
+#[tokio::test]
+async fn red_background_cell_is_drawn_red() {
+    let Some(mut r) = headless(200, 100).await else { return };
+    let (cw, ch) = (r.cell_width, r.cell_height);
+    
+    // A space with a pure red background in the third column of the first row
+    let mut term = Terminal::with_grid(Grid::new(8, 2, 0));
+    term.advance(b"\x1b[2C\x1b[48;2;255;0;0m \x1b[m\x1b[2;1H");
+    assert_eq!(term.grid.cells[2].bg, CellColor::Rgb(Color { r: 255, g: 0, b: 0 }));
+    r.palette.background = Color { r: 0, g: 0, b: 255 };
+    r.set_snapshot(term.grid.snapshot_viewport(0, 2));
+    r.render_frame().unwrap();
+    
+    let frame = r.capture_frame().unwrap();
+    assert_eq!(frame.len(), 200 * 100 * 4);
+    let center = |col: f32| ((col + 0.5) * cw) as u32;
+    assert_eq!(pixel(&frame, 200, center(2.0), (ch / 2.0) as u32), [255, 0, 0, 255]);
+    // Default-background cells show the clear color
+    assert_eq!(pixel(&frame, 200, center(5.0), (ch / 2.0) as u32), [0, 0, 255, 255]);
+}
+
+#[tokio::test]
+async fn text_draws_glyph_pixels_only_where_it_is() {
+    let Some(mut r) = headless(300, 100).await else { return };
+    let (cw, ch) = (r.cell_width, r.cell_height);
+    
+    let mut term = Terminal::with_grid(Grid::new(12, 3, 0));
+    term.advance(b"HELLO");
+    let mut snapshot = term.grid.snapshot_viewport(0, 3);
+    snapshot.cursor = None;
+    r.set_snapshot(snapshot);
+    r.render_frame().unwrap();
+    let frame = r.capture_frame().unwrap();
+    
+    let background = pixel(&frame, 300, 299, 99);
+    let text_end = (5.0 * cw) as u32;
+    assert!(drawn_pixels(&frame, 300, 0..text_end, 0..ch as u32, background) > 0);
+    // Past the text and below its row the frame is the clear color
+    assert_eq!(drawn_pixels(&frame, 300, text_end + 2..300, 0..100, background), 0);
+    assert_eq!(drawn_pixels(&frame, 300, 0..300, ch as u32 + 2..100, background), 0);
+}
+
+#[test]
+fn truecolor_foreground_is_kept_exact() {
+    let mut term = Terminal::with_grid(Grid::new(12, 2, 0));
+    term.advance("a\x1b[38;2;10;20;30mbc\u{4e16}\x1b[31md\x1b[m".as_bytes());
+    let exact = Color { r: 10, g: 20, b: 30 };
+    assert_eq!(term.grid.cells[1].fg, CellColor::Rgb(exact));
+    
+    let palette = Palette::default();
+    let spans = fg_spans(&term.grid.cells[..7], &palette);
+    let expected = [("a", palette.foreground), ("bc\u{4e16}", exact), ("d", Color::RED), (" ", palette.foreground)];
+    assert_eq!(spans.len(), expected.len());
+    for ((text, color), (want_text, want_color)) in spans.iter().zip(expected) {
+        assert_eq!((text.as_str(), *color), (want_text, want_color));
+    }
+}
+
+#[tokio::test]
+async fn truecolor_text_is_drawn_in_its_color() {
+    let Some(mut r) = headless(300, 100).await else { return };
+    
+    // Full blocks, so their insides are the glyph color itself
+    let mut term = Terminal::with_grid(Grid::new(12, 2, 0));
+    term.advance("\x1b[38;2;10;200;30m\u{2588}\u{2588}\u{2588}\x1b[m \u{4e16}x".as_bytes());
+    let mut snapshot = term.grid.snapshot_viewport(0, 2);
+    snapshot.cursor = None;
+    r.set_snapshot(snapshot);
+    r.render_frame().unwrap();
+    let frame = r.capture_frame().unwrap();
+    
+    let (cw, ch) = (r.cell_width, r.cell_height);
+    assert_eq!(pixel(&frame, 300, (1.5 * cw) as u32, (ch / 2.0) as u32), [10, 200, 30, 255]);
+}
+
+#[test]
+fn selection_rows_are_relative_to_the_viewport_top() {
+    // Absolute row 5 under a viewport starting at row 3 is the third row drawn
+    assert_eq!(selection_rows((1, 5), (4, 5), 3, 4, 7), [(2, 1, 4)]);
+    // Either end may come first; rows above the viewport are cut off
+    assert_eq!(selection_rows((2, 4), (6, 1), 3, 4, 7), [(0, 0, 7), (1, 0, 2)]);
+    assert!(selection_rows((0, 9), (3, 9), 3, 4, 7).is_empty());
+}
+
+#[tokio::test]
+async fn selection_highlight_follows_its_text_when_scrolled() {
+    let Some(mut r) = headless(200, 200).await else { return };
+    let (cw, ch) = (r.cell_width, r.cell_height);
+    
+    // Eight lines printed on four rows: absolute rows 0..=3 are scrollback
+    let mut term = Terminal::with_grid(Grid::new(8, 4, 100));
+    term.advance(b"\r\n\r\n\r\n\r\n\r\n\r\n\r\n");
+    let mut snapshot = term.grid.snapshot_viewport(3, 4);
+    snapshot.cursor = None;
+    r.set_snapshot(snapshot);
+    r.set_viewport(3, 0.0);
+    r.selections = vec![((0, 5), (3, 5))];
+    r.render_frame().unwrap();
+    let frame = r.capture_frame().unwrap();
+    
+    let background = pixel(&frame, 200, 199, 199);
+    let at_row = |row: f32| pixel(&frame, 200, (1.5 * cw) as u32, ((row + 0.5) * ch) as u32);
+    assert_ne!(at_row(2.0), background);
+    assert_eq!(at_row(1.0), background);
+    assert_eq!(at_row(3.0), background);
+}
+
+#[tokio::test]
+async fn marks_draw_gutter_markers_on_their_rows() {
+    let Some(mut r) = headless(200, 200).await else { return };
+    let ch = r.cell_height;
+    
+    // A prompt mark and a SetMark, on absolute rows 4 and 6; the view starts at row 3
+    let mut term = Terminal::with_grid(Grid::new(8, 4, 100));
+    term.advance(b"\r\n\r\n\r\n\r\n\x1b]133;A\x07\r\n\r\n\x1b]1337;SetMark\x07");
+    let rows: Vec<usize> = term.grid.marks.iter().map(|m| m.row).collect();
+    assert_eq!(rows, [4, 6]);
+    let mut snapshot = term.grid.snapshot_viewport(3, 4);
+    snapshot.cursor = None;
+    r.set_snapshot(snapshot);
+    r.set_viewport(3, 0.0);
+    r.mark_rows = rows;
+    r.render_frame().unwrap();
+    let frame = r.capture_frame().unwrap();
+    
+    // No padding here, so the marker sits at the left edge of the row
+    let background = pixel(&frame, 200, 199, 199);
+    let at_row = |row: f32| pixel(&frame, 200, 0, ((row + 0.5) * ch) as u32);
+    assert_ne!(at_row(1.0), background);
+    assert_ne!(at_row(3.0), background);
+    assert_eq!(at_row(0.0), background);
+    assert_eq!(at_row(2.0), background);
+}
@@ -0,0 +1,16 @@
+use the_dev_terminal_ui_wgpu::RenderError;
+use wgpu::SurfaceError;
+
+#[test]
+fn surface_errors_are_classified_by_recovery() {
+    assert!(matches!(RenderError::from(SurfaceError::Timeout), RenderError::Timeout));
+    assert!(matches!(RenderError::from(SurfaceError::Lost), RenderError::Reconfigure));
+    assert!(matches!(RenderError::from(SurfaceError::Outdated), RenderError::Reconfigure));
+    assert!(matches!(RenderError::from(SurfaceError::OutOfMemory), RenderError::OutOfMemory));
+    
+    for e in [SurfaceError::Timeout, SurfaceError::Lost, SurfaceError::Outdated] {
+        assert!(RenderError::from(e).is_recoverable());
+    }
+    assert!(!RenderError::from(SurfaceError::OutOfMemory).is_recoverable());
+    assert!(RenderError::Other(anyhow::anyhow!("prepare failed")).is_recoverable());
+}